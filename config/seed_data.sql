@@ -21,10 +21,10 @@ INSERT OR IGNORE INTO session_tokens (user_id, token) VALUES
     (4, 'aa765c35ba99434b'),
     (5, 'd82952210bb78d53');
 
-INSERT OR IGNORE INTO lists (id, name, description) VALUES
-    (1, 'Newsletter', 'the Studio newsletter!'),
-    (2, 'Test Group 1', 'the Studio test group 1!'),
-    (3, 'Test Group 2', 'the Studio test group 2!');
+INSERT OR IGNORE INTO lists (id, name, description, slug, public) VALUES
+    (1, 'Newsletter', 'the Studio newsletter!', 'newsletter', TRUE),
+    (2, 'Test Group 1', 'the Studio test group 1!', 'test-group-1', FALSE),
+    (3, 'Test Group 2', 'the Studio test group 2!', 'test-group-2', FALSE);
 INSERT OR IGNORE INTO list_members (list_id, user_id) VALUES
     (1, 1),
     (1, 2),
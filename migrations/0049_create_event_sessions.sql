@@ -0,0 +1,28 @@
+-- Timed sessions within an event, each with its own capacity.
+CREATE TABLE event_sessions (
+    id INTEGER PRIMARY KEY NOT NULL,
+    event_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    start TIMESTAMP NOT NULL,
+    end TIMESTAMP,
+    capacity INTEGER NOT NULL,
+    sort INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX event_sessions_event_id ON event_sessions(event_id);
+
+-- Sessions a spot grants access to. A spot with no rows here grants every session.
+CREATE TABLE spot_sessions (
+    spot_id INTEGER NOT NULL,
+    event_session_id INTEGER NOT NULL,
+    PRIMARY KEY (spot_id, event_session_id)
+);
+
+-- Per-session check-ins, keyed by attendee like the event-wide check-in routes.
+CREATE TABLE event_session_checkins (
+    event_session_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    checkin_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (event_session_id, user_id)
+);
@@ -0,0 +1,32 @@
+-- Named roles, each granting a set of permissions. `user_roles.role` refers to `roles.name`.
+CREATE TABLE roles (
+    name TEXT PRIMARY KEY NOT NULL,
+    description TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE role_permissions (
+    role TEXT NOT NULL,
+    permission TEXT NOT NULL,
+    PRIMARY KEY (role, permission)
+);
+
+INSERT INTO roles (name, description) VALUES
+    ('admin', 'Full access to everything'),
+    ('organizer', 'Runs events and sends invites'),
+    ('door', 'Checks in attendees at the door'),
+    ('writer', 'Writes and sends newsletter posts');
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('admin', 'events.edit'),
+    ('admin', 'events.checkin'),
+    ('admin', 'lists.edit'),
+    ('admin', 'lists.send'),
+    ('admin', 'posts.publish'),
+    ('admin', 'users.manage'),
+    ('organizer', 'events.edit'),
+    ('organizer', 'events.checkin'),
+    ('organizer', 'lists.send'),
+    ('door', 'events.checkin'),
+    ('writer', 'posts.publish'),
+    ('writer', 'lists.send');
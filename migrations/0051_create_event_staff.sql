@@ -0,0 +1,10 @@
+-- Per-event grants, e.g. a door volunteer who can check in attendees for a single event.
+-- Grants stop applying once the event is over.
+CREATE TABLE event_staff (
+    event_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    role TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (event_id, user_id)
+);
+CREATE INDEX event_staff_user_id ON event_staff(user_id);
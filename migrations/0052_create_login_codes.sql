@@ -0,0 +1,9 @@
+-- Short numeric login codes, emailed alongside login links. One outstanding code per user.
+CREATE TABLE login_codes (
+    user_id INTEGER PRIMARY KEY NOT NULL,
+    email TEXT NOT NULL,
+    code TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX login_codes_email ON login_codes(email);
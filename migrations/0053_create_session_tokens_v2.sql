@@ -0,0 +1,19 @@
+-- Give sessions an id and track the device they were last used from, for the sessions page.
+ALTER TABLE session_tokens RENAME TO session_tokens_old;
+DROP INDEX session_tokens_token;
+CREATE TABLE session_tokens (
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL,
+    token TEXT NOT NULL,
+    user_agent TEXT,
+    ip TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX session_tokens_token ON session_tokens(token);
+CREATE INDEX session_tokens_user_id ON session_tokens(user_id);
+
+INSERT INTO session_tokens (user_id, token, created_at, last_seen_at)
+    SELECT user_id, token, created_at, created_at
+    FROM session_tokens_old;
+DROP TABLE session_tokens_old;
@@ -0,0 +1,8 @@
+-- Pending email address changes, applied once the new address clicks its verification link.
+CREATE TABLE email_changes (
+    user_id INTEGER PRIMARY KEY NOT NULL,
+    email TEXT NOT NULL,
+    token TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX email_changes_token ON email_changes(token);
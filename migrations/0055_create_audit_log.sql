@@ -0,0 +1,15 @@
+-- Admin actions on events, lists and posts. `before` and `after` hold JSON of the changed fields only.
+CREATE TABLE audit_log (
+    id INTEGER PRIMARY KEY NOT NULL,
+    actor_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    target_kind TEXT NOT NULL,
+    target_id INTEGER NOT NULL,
+    event_id INTEGER,
+    before TEXT,
+    after TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX audit_log_actor_id ON audit_log(actor_id);
+CREATE INDEX audit_log_event_id ON audit_log(event_id);
+CREATE INDEX audit_log_action ON audit_log(action);
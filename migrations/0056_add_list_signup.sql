@@ -0,0 +1,12 @@
+-- Public lists can be joined from `/lists/{slug}/signup` and the `/newsletter` directory.
+ALTER TABLE lists ADD COLUMN slug TEXT NOT NULL DEFAULT '';
+ALTER TABLE lists ADD COLUMN public BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE lists ADD COLUMN signup_title TEXT;
+ALTER TABLE lists ADD COLUMN signup_description TEXT;
+ALTER TABLE lists ADD COLUMN confirmation_message TEXT;
+
+UPDATE lists SET slug = 'list-' || id;
+-- The newsletter was the only list open for signups before lists had a public flag.
+UPDATE lists SET slug = 'newsletter', public = TRUE WHERE id = 1;
+
+CREATE UNIQUE INDEX lists_slug ON lists(slug);
@@ -0,0 +1,9 @@
+-- Signups waiting on their emailed confirmation link. Confirming moves them to `list_members`.
+CREATE TABLE list_signups (
+    list_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    token TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (list_id, user_id)
+);
+CREATE INDEX list_signups_token ON list_signups(token);
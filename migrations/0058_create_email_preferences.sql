@@ -0,0 +1,17 @@
+-- Users who paused list email from the preference center. Posts and invites skip them until `paused_until`.
+CREATE TABLE email_pauses (
+    user_id INTEGER PRIMARY KEY NOT NULL,
+    paused_until TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Changes made from the preference center, as a record of consent.
+CREATE TABLE preference_changes (
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL,
+    email_id INTEGER NOT NULL,
+    change TEXT NOT NULL,
+    list_id INTEGER,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX preference_changes_user_id ON preference_changes(user_id);
@@ -0,0 +1,11 @@
+-- Saved audiences for posts and invites, built from JSON rules over lists, RSVPs and email opens.
+CREATE TABLE segments (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    rules TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- The segment an email was sent to, in place of `list_id`.
+ALTER TABLE emails ADD COLUMN segment_id INTEGER;
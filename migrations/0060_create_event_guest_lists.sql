@@ -0,0 +1,29 @@
+-- Further lists whose members are on a private event's guest list, alongside `events.guest_list_id`.
+CREATE TABLE event_guest_lists (
+    event_id INTEGER NOT NULL,
+    list_id INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (event_id, list_id)
+);
+
+-- Personal links onto a private event's guest list, e.g. for plus-ones or press.
+-- `max_uses` and `expires_at` are unlimited when NULL.
+CREATE TABLE event_invite_links (
+    id INTEGER PRIMARY KEY NOT NULL,
+    event_id INTEGER NOT NULL,
+    token TEXT NOT NULL UNIQUE,
+    label TEXT NOT NULL,
+    max_uses INTEGER,
+    expires_at TIMESTAMP,
+    created_by INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX event_invite_links_event_id ON event_invite_links (event_id);
+
+-- Users who got onto a guest list through an invite link. Each user uses up a link once.
+CREATE TABLE event_invite_redemptions (
+    link_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (link_id, user_id)
+);
@@ -0,0 +1,17 @@
+-- Link targets in bulk emails, so click redirects only go to urls we sent.
+CREATE TABLE email_links (
+    id INTEGER PRIMARY KEY NOT NULL,
+    url TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE email_clicks (
+    id INTEGER PRIMARY KEY NOT NULL,
+    email_id INTEGER NOT NULL,
+    link_id INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX email_clicks_email_id ON email_clicks (email_id);
+
+-- Lists can opt out of open and click tracking.
+ALTER TABLE lists ADD COLUMN tracking BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,10 @@
+-- WebP and AVIF encodings of event flyers, served instead of the JPEGs to browsers that accept them.
+-- Generated in the background after upload, flyers without them fall back to JPEG.
+CREATE TABLE event_flyer_variants (
+    event_id INTEGER NOT NULL,
+    size TEXT NOT NULL,
+    format TEXT NOT NULL,
+    image BLOB NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (event_id, size, format)
+);
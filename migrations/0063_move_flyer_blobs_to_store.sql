@@ -0,0 +1,29 @@
+-- Flyer images move to the content-addressed blob store, and tables only keep their SHA-256 hashes.
+-- Copying the images out needs the filesystem, so the old tables are renamed and then emptied at
+-- startup by `EventFlyer::migrate_legacy_blobs`, which drops them once done.
+ALTER TABLE event_flyers RENAME TO event_flyers_legacy;
+ALTER TABLE event_flyer_variants RENAME TO event_flyer_variants_legacy;
+
+CREATE TABLE event_flyers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_id INTEGER NOT NULL,
+    width INTEGER NOT NULL DEFAULT 0,
+    height INTEGER NOT NULL DEFAULT 0,
+    thumb_hash TEXT NOT NULL,
+    sm_hash TEXT NOT NULL,
+    md_hash TEXT NOT NULL,
+    lg_hash TEXT NOT NULL,
+    full_hash TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (event_id)
+);
+
+CREATE TABLE event_flyer_variants (
+    event_id INTEGER NOT NULL,
+    size TEXT NOT NULL,
+    format TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (event_id, size, format)
+);
@@ -0,0 +1,18 @@
+-- Photos from an event, shown on its page and in its own gallery. Images live in the blob store.
+CREATE TABLE event_photos (
+    id INTEGER PRIMARY KEY NOT NULL,
+    event_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    caption TEXT NOT NULL DEFAULT '',
+    -- Shown first and largest. At most one per event.
+    cover BOOLEAN NOT NULL DEFAULT FALSE,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    thumb_hash TEXT NOT NULL,
+    sm_hash TEXT NOT NULL,
+    md_hash TEXT NOT NULL,
+    lg_hash TEXT NOT NULL,
+    full_hash TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX event_photos_event_id ON event_photos (event_id, position);
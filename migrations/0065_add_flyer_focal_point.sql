@@ -0,0 +1,6 @@
+-- Where crops of a flyer center, as fractions of its width and height.
+ALTER TABLE event_flyers ADD COLUMN focal_x REAL NOT NULL DEFAULT 0.5;
+ALTER TABLE event_flyers ADD COLUMN focal_y REAL NOT NULL DEFAULT 0.5;
+-- Square and Open Graph (1200x630) crops around the focal point, generated after upload.
+ALTER TABLE event_flyers ADD COLUMN square_hash TEXT;
+ALTER TABLE event_flyers ADD COLUMN og_hash TEXT;
@@ -0,0 +1,5 @@
+-- Posts are drafts, scheduled for `published_at`, or published. Existing posts were all public.
+ALTER TABLE posts ADD COLUMN status TEXT NOT NULL DEFAULT 'draft';
+ALTER TABLE posts ADD COLUMN published_at DATETIME;
+UPDATE posts SET status = 'published', published_at = created_at;
+CREATE INDEX idx_posts_status ON posts (status, published_at);
@@ -0,0 +1,29 @@
+-- Every saved version of editor content: post bodies and event descriptions and emails.
+-- `kind` is the editor's snapshot prefix, e.g. `post` or `event/invite`, and `subject` is only set for emails.
+CREATE TABLE revisions (
+    id INTEGER PRIMARY KEY NOT NULL,
+    kind TEXT NOT NULL,
+    entity_id INTEGER NOT NULL,
+    subject TEXT,
+    html TEXT NOT NULL,
+    -- Who saved it, NULL for content from before revisions were kept.
+    author_id INTEGER,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX revisions_entity ON revisions(kind, entity_id, id);
+
+-- Start each history with the current content.
+INSERT INTO revisions (kind, entity_id, html, created_at)
+    SELECT 'post', id, content, updated_at FROM posts;
+INSERT INTO revisions (kind, entity_id, html, created_at)
+    SELECT 'event/description', id, description_html, COALESCE(description_updated_at, updated_at)
+    FROM events WHERE description_html IS NOT NULL;
+INSERT INTO revisions (kind, entity_id, subject, html, created_at)
+    SELECT 'event/invite', id, invite_subject, invite_html, COALESCE(invite_updated_at, updated_at)
+    FROM events WHERE invite_html IS NOT NULL;
+INSERT INTO revisions (kind, entity_id, subject, html, created_at)
+    SELECT 'event/confirmation', id, confirmation_subject, confirmation_html, COALESCE(confirmation_updated_at, updated_at)
+    FROM events WHERE confirmation_html IS NOT NULL;
+INSERT INTO revisions (kind, entity_id, subject, html, created_at)
+    SELECT 'event/dayof', id, dayof_subject, dayof_html, COALESCE(dayof_updated_at, updated_at)
+    FROM events WHERE dayof_html IS NOT NULL;
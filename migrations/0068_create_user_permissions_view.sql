@@ -0,0 +1,6 @@
+-- Each user's permissions through their roles, comma separated, as loaded into `User::permissions`.
+CREATE VIEW user_permissions AS
+    SELECT ur.user_id, GROUP_CONCAT(DISTINCT rp.permission) AS permissions
+    FROM user_roles ur
+    JOIN role_permissions rp ON rp.role = ur.role
+    GROUP BY ur.user_id;
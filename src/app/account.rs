@@ -0,0 +1,244 @@
+//! Self-service account pages for logged in users.
+
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+
+use crate::db::event::Event;
+use crate::db::list::List;
+use crate::db::ticket::Ticket;
+use crate::db::token::{EmailChange, SessionToken};
+use crate::db::user::UpdateUser;
+use crate::db::user_export::UserExport;
+use crate::prelude::*;
+
+/// Add all `account` routes to the router.
+#[rustfmt::skip]
+pub fn add_routes(router: AppRouter) -> AppRouter {
+    router.public_routes(|r| {
+        r.route("/account", get(account_page).post(account_form))
+         .route("/account/email", post(email_form).get(verify_email_link))
+         .route("/account/tickets", get(tickets_page))
+         .route("/account/export", get(export_download))
+         .route("/account/delete", get(delete_page).post(delete_form))
+         .route("/account/sessions", get(sessions_page))
+         .route("/account/sessions/{id}", delete(revoke_session))
+    })
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "account/account.html")]
+struct AccountHtml {
+    user: Option<User>,
+    /// Confirmed tickets, newest event first.
+    tickets: Vec<(Ticket, Event)>,
+    lists: Vec<List>,
+    message: Option<String>,
+}
+
+/// Display the user's profile along with their RSVPs and list memberships.
+async fn account_page(user: User, State(state): State<SharedAppState>) -> HtmlResult {
+    let tickets = confirmed_tickets(&state.db, user.id).await?;
+    let lists = List::list_for_user(&state.db, user.id).await?;
+    Ok(AccountHtml { user: Some(user), tickets, lists, message: None }.into_response())
+}
+
+#[derive(serde::Deserialize)]
+struct AccountForm {
+    first_name: String,
+    last_name: String,
+    phone: String,
+}
+
+/// Update the user's name and phone.
+async fn account_form(
+    user: User, State(state): State<SharedAppState>, Form(form): Form<AccountForm>,
+) -> HtmlResult {
+    let non_empty = |s: String| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+    let info = UpdateUser {
+        email: user.email.clone(),
+        first_name: non_empty(form.first_name),
+        last_name: non_empty(form.last_name),
+        phone: non_empty(form.phone),
+    };
+    user.update(&state.db, &info).await?;
+    Ok(Redirect::to("/account").into_response())
+}
+
+#[derive(serde::Deserialize)]
+struct EmailForm {
+    email: Mailbox,
+}
+
+/// Email a verification link to a new address. The change is only applied once the link is clicked.
+async fn email_form(
+    user: User, State(state): State<SharedAppState>, Form(form): Form<EmailForm>,
+) -> HtmlResult {
+    let email = form.email.email.to_string();
+    if let Some(existing) = User::lookup_by_email(&state.db, &email).await?
+        && existing.id != user.id
+    {
+        return Ok(ErrorHtml {
+            user: Some(user),
+            title: "Email in use".into(),
+            message: format!("{email} already belongs to another account."),
+            context: None,
+            backtrace: None,
+            contact_email: None,
+        }
+        .into_response());
+    }
+
+    let token = EmailChange::create(&state.db, &user, &email).await?;
+    let (email_id, email_token) = Email::create_email_change(&state.db, &user).await?;
+    let verify_url = format!("{}/account/email?token={token}", state.config.app.url);
+
+    #[derive(Template)]
+    #[template(path = "emails/email_change.html")]
+    struct EmailChangeHtml {
+        email_token: String,
+        verify_url: String,
+        email: String,
+    }
+
+    let msg = state
+        .mailer
+        .builder()
+        .header(ContentType::TEXT_HTML)
+        .to(form.email)
+        .subject(format!("Verify your new email for {}", state.config.app.domain))
+        .body(EmailChangeHtml { email_token, verify_url, email: email.clone() }.render()?)?;
+
+    match state.mailer.send(&msg).await {
+        Ok(_) => Email::mark_sent(&state.db, email_id).await?,
+        Err(e) => {
+            Email::mark_error(&state.db, email_id, e.message()).await?;
+            return Err(e.into());
+        }
+    }
+
+    let tickets = confirmed_tickets(&state.db, user.id).await?;
+    let lists = List::list_for_user(&state.db, user.id).await?;
+    let message = Some(format!("Check {email} for a link to finish changing your email."));
+    Ok(AccountHtml { user: Some(user), tickets, lists, message }.into_response())
+}
+
+async fn confirmed_tickets(db: &Db, user_id: i64) -> Result<Vec<(Ticket, Event)>> {
+    let mut tickets = Ticket::list_with_events(db, user_id).await?;
+    tickets.retain(|(ticket, _)| ticket.is_confirmed());
+    Ok(tickets)
+}
+
+#[derive(serde::Deserialize)]
+struct VerifyEmailQuery {
+    token: String,
+}
+
+/// Apply a pending email change once the new address follows its verification link.
+async fn verify_email_link(
+    State(state): State<SharedAppState>, Query(query): Query<VerifyEmailQuery>,
+) -> HtmlResult {
+    let change = EmailChange::redeem(&state.db, &query.token).await?.ok_or_else(not_found)?;
+    let user = User::lookup_by_id(&state.db, change.user_id).await?.ok_or_else(not_found)?;
+
+    // The address may have been claimed by another account since the link was sent
+    if let Some(existing) = User::lookup_by_email(&state.db, &change.email).await?
+        && existing.id != user.id
+    {
+        bail_invalid!();
+    }
+
+    let info = UpdateUser {
+        email: change.email,
+        first_name: user.first_name.clone(),
+        last_name: user.last_name.clone(),
+        phone: user.phone.clone(),
+    };
+    user.update(&state.db, &info).await?;
+    Ok(Redirect::to("/account").into_response())
+}
+
+/// List every event the user has a ticket for, split into upcoming and past.
+async fn tickets_page(user: User, State(state): State<SharedAppState>) -> HtmlResult {
+    let (past, mut upcoming): (Vec<_>, Vec<_>) = Ticket::list_with_events(&state.db, user.id)
+        .await?
+        .into_iter()
+        .partition(|(_, event)| event.is_over());
+    upcoming.reverse();
+
+    #[derive(Template, WebTemplate)]
+    #[template(path = "account/tickets.html")]
+    struct Html {
+        user: Option<User>,
+        upcoming: Vec<(Ticket, Event)>,
+        past: Vec<(Ticket, Event)>,
+    }
+    Ok(Html { user: Some(user), upcoming, past }.into_response())
+}
+
+/// Download everything we hold about the user as JSON.
+async fn export_download(user: User, State(state): State<SharedAppState>) -> HtmlResult {
+    let export = UserExport::build(&state.db, &user).await?;
+    export_response(&export)
+}
+
+/// Render a `UserExport` as a JSON file download.
+pub fn export_response(export: &UserExport) -> HtmlResult {
+    let json = serde_json::to_string_pretty(export).map_err(AnyError::from)?;
+    let disposition = format!("attachment; filename=\"user-{}.json\"", export.user.id);
+    let headers = [
+        (header::CONTENT_TYPE, "application/json".to_string()),
+        (header::CONTENT_DISPOSITION, disposition),
+    ];
+    Ok((headers, json).into_response())
+}
+
+/// Confirm before erasing the account.
+async fn delete_page(user: User) -> HtmlResult {
+    #[derive(Template, WebTemplate)]
+    #[template(path = "account/delete.html")]
+    struct Html {
+        user: Option<User>,
+    }
+    Ok(Html { user: Some(user) }.into_response())
+}
+
+/// Erase the user's personal data and log them out everywhere.
+async fn delete_form(user: User, State(state): State<SharedAppState>) -> HtmlResult {
+    user.anonymize(&state.db).await?;
+
+    let clear = Cookie::build(("session", ""))
+        .max_age(cookie::time::Duration::ZERO)
+        .domain(&state.config.app.domain)
+        .path("/")
+        .to_string();
+
+    #[derive(Template, WebTemplate)]
+    #[template(path = "account/deleted.html")]
+    struct Html {
+        user: Option<User>,
+    }
+    Ok(([(header::SET_COOKIE, clear)], Html { user: None }).into_response())
+}
+
+/// List the user's active sessions, with a button to revoke each one.
+async fn sessions_page(user: User, State(state): State<SharedAppState>, cookies: CookieJar) -> HtmlResult {
+    let sessions = SessionToken::list_for_user(&state.db, user.id).await?;
+    let current = cookies.get("session").map(|c| c.value().to_string());
+    let current_id = sessions.iter().find(|s| Some(&s.token) == current.as_ref()).map(|s| s.id);
+
+    #[derive(Template, WebTemplate)]
+    #[template(path = "account/sessions.html")]
+    struct Html {
+        user: Option<User>,
+        sessions: Vec<SessionToken>,
+        current_id: Option<i64>,
+    }
+    Ok(Html { user: Some(user), sessions, current_id }.into_response())
+}
+
+async fn revoke_session(
+    user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+) -> JsonResult<()> {
+    SessionToken::delete_by_id(&state.db, user.id, id).await?;
+    Ok(Json(()))
+}
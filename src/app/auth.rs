@@ -1,8 +1,8 @@
-//! A simple passwordless authentication flow using one-time links sent via email.
+//! A simple passwordless authentication flow using one-time links and codes sent via email.
 //!
-//! TODO: Switch to one-time codes (123-456) instead of links:
-//! * More robust against clients and intermediaries that auto-open URLs
-//! * Easier to transfer across devices than a magic link
+//! Each login email carries both a link and a 6-digit code (123-456). The code is
+//! robust against clients and intermediaries that auto-open URLs, and is easier to
+//! transfer across devices than a link.
 //!
 //! We choose this scheme instead of one with usernames/passwords to reduce
 //! friction and simplify onboarding.
@@ -12,16 +12,24 @@
 //! 1. **Email input**: User enters their email and submits a login form.
 //! 2. **Token generated**: Server creates a short-lived link with a login token and emails it to the user.
 //!    - If the user is already registered, the link points to `/login?token=...`.
-//!    - If the user is not registered, the link points to `/register?token=...`.
+//!    - If the user is not registered, a pending nameless user is created and the link points to `/register?token=...`.
+//!      Pending users that never register are deleted with the orphaned users job.
 //! 3. **Link clicked**: User clicks the link, passing the token back to the server.
 //!    - `/login`: The user gets a new session cookie and is redirected home.
 //!    - `/register`: The user is prompted to enter their first/last name.
-//!      Upon submission, the user gets a new session cookie and is redirected home.
+//!      Upon submission, the user gets a new session cookie and is redirected to `/account`.
+//! 4. **Or code entered**: User types the code into the page shown after step 1, which posts to `/login/code`.
+//!    Codes expire after a few minutes, allow a handful of wrong guesses, and only work with the
+//!    email address that requested them.
 
+use std::net::SocketAddr;
+
+use axum::extract::ConnectInfo;
 use lettre::message::Mailbox;
 use lettre::message::header::ContentType;
 
-use crate::db::token::{LoginToken, SessionToken};
+use crate::db::token::{LoginCode, LoginToken, SessionToken};
+use crate::db::user::{CreateUser, UpdateUser};
 use crate::prelude::*;
 
 /// Add all `auth` routes to the router.
@@ -29,6 +37,9 @@ use crate::prelude::*;
 pub fn add_routes(router: AppRouter) -> AppRouter {
     router.public_routes(|r| {
         r.route("/login", post(login_form).get(login_link))
+         .route("/login/code", post(login_code_form))
+         .route("/register", get(register_page).post(register_form))
+         .route("/logout", post(logout_form))
     })
 }
 
@@ -41,6 +52,16 @@ pub fn add_middleware(router: AxumRouter, state: SharedAppState) -> AxumRouter {
         if let Some(token) = cookies.get("session") {
             match User::lookup_by_session_token(&state.db, token.value()).await? {
                 Some(user) => {
+                    let ip = request
+                        .extensions()
+                        .get::<ConnectInfo<SocketAddr>>()
+                        .map(|c| c.0.ip().to_string());
+                    let user_agent = request
+                        .headers()
+                        .get(header::USER_AGENT)
+                        .and_then(|ua| ua.to_str().ok())
+                        .map(|ua| ua.chars().take(256).collect());
+                    SessionToken::touch(&state.db, token.value(), ip, user_agent).await?;
                     request.extensions_mut().insert(user);
                 }
                 None => cookies = cookies.remove("session"),
@@ -74,54 +95,63 @@ impl<S: Send + Sync> axum::extract::FromRequestParts<S> for User {
 struct LoginEmailSentHtml {
     user: Option<User>,
     email: String,
+    redirect: Option<String>,
+    error: Option<String>,
 }
 /// Process a login form and send either a login or registration link via email.
 async fn login_form(State(state): State<SharedAppState>, Form(form): Form<LoginForm>) -> HtmlResult {
     let email = form.email.email.to_string();
 
-    // Only registered users get a link
-    if let Some(user) = User::lookup_by_email(&state.db, &email).await? {
-        let (email_id, email_token) = Email::create_login(&state.db, &user).await?;
+    // Unknown emails get a registration link instead, which asks for their name before logging in.
+    // The user stays pending until then, see `User::delete_orphaned`.
+    let (user, path) = match User::lookup_by_email(&state.db, &email).await? {
+        Some(user) => (user, "login"),
+        None => {
+            let info = CreateUser { email: email.clone(), first_name: None, last_name: None, phone: None };
+            (User::create(&state.db, &info).await?, "register")
+        }
+    };
+    let (email_id, email_token) = Email::create_login(&state.db, &user).await?;
 
-        // Delete any existing tokens and re-create
-        LoginToken::delete_by_user(&state.db, &user).await?;
-        let login_token = LoginToken::create(&state.db, &user).await?;
+    // Delete any existing tokens and re-create
+    LoginToken::delete_by_user(&state.db, &user).await?;
+    let login_token = LoginToken::create(&state.db, &user).await?;
+    let code = LoginCode::create(&state.db, &user).await?;
+    let login_code = format!("{}-{}", &code[..3], &code[3..]);
 
-        let base_url = &state.config.app.url;
-        let login_url = match form.redirect {
-            Some(redirect) => format!("{base_url}/login?token={login_token}&redirect={redirect}"),
-            None => format!("{base_url}/login?token={login_token}"),
-        };
-        let domain = state.config.app.domain.clone();
-
-        #[derive(Template)]
-        #[template(path = "emails/login.html")]
-        struct LoginEmailHtml {
-            email_token: String,
-            login_url: String,
-            domain: String,
-        }
+    let base_url = &state.config.app.url;
+    let login_url = match &form.redirect {
+        Some(redirect) => format!("{base_url}/{path}?token={login_token}&redirect={redirect}"),
+        None => format!("{base_url}/{path}?token={login_token}"),
+    };
+    let domain = state.config.app.domain.clone();
 
-        let msg = state
-            .mailer
-            .builder()
-            .header(ContentType::TEXT_HTML)
-            .to(form.email)
-            .subject(format!("Login to {domain}"))
-            .body(LoginEmailHtml { email_token, login_url, domain }.render()?)?;
-
-        match state.mailer.send(&msg).await {
-            Ok(_) => Email::mark_sent(&state.db, email_id).await?,
-            Err(e) => {
-                Email::mark_error(&state.db, email_id, e.message()).await?;
-                return Err(e.into());
-            }
+    #[derive(Template)]
+    #[template(path = "emails/login.html")]
+    struct LoginEmailHtml {
+        email_token: String,
+        login_url: String,
+        login_code: String,
+        domain: String,
+    }
+
+    let msg = state
+        .mailer
+        .builder()
+        .header(ContentType::TEXT_HTML)
+        .to(form.email)
+        .subject(format!("{login_code} is your login code for {domain}"))
+        .body(LoginEmailHtml { email_token, login_url, login_code, domain }.render()?)?;
+
+    match state.mailer.send(&msg).await {
+        Ok(_) => Email::mark_sent(&state.db, email_id).await?,
+        Err(e) => {
+            Email::mark_error(&state.db, email_id, e.message()).await?;
+            return Err(e.into());
         }
     }
 
-    // Always render the same response, to avoid leaking whether users exist.
-    // We *could* revisit this for better UX if it becomes a recurring problem.
-    Ok(LoginEmailSentHtml { user: None, email }.into_response())
+    Ok(LoginEmailSentHtml { user: None, email, redirect: form.redirect, error: None }.into_response())
 }
 #[derive(serde::Deserialize)]
 struct LoginForm {
@@ -129,6 +159,35 @@ struct LoginForm {
     redirect: Option<String>,
 }
 
+/// Exchange an emailed login code for a new session.
+async fn login_code_form(State(state): State<SharedAppState>, Form(form): Form<LoginCodeForm>) -> HtmlResult {
+    // Accept "123-456", "123 456", etc.
+    let code: String = form.code.chars().filter(|c| c.is_ascii_digit()).collect();
+
+    let user = match LoginCode::redeem(&state.db, &form.email, &code).await? {
+        Some(user_id) => User::lookup_by_id(&state.db, user_id).await?,
+        None => None,
+    };
+    let Some(user) = user else {
+        let error = Some("That code is invalid or has expired.".into());
+        let html = LoginEmailSentHtml { user: None, email: form.email, redirect: form.redirect, error };
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, html).into_response());
+    };
+
+    let token = SessionToken::create(&state.db, &user).await?;
+    let cookie = session_cookie(&state.config, token);
+
+    let headers = [(header::SET_COOKIE, cookie)];
+    let redirect = Redirect::to(form.redirect.as_deref().unwrap_or("/"));
+    Ok((headers, redirect).into_response())
+}
+#[derive(serde::Deserialize)]
+struct LoginCodeForm {
+    email: String,
+    code: String,
+    redirect: Option<String>,
+}
+
 /// Show the login page or handle a login link
 #[derive(serde::Deserialize)]
 struct LoginQuery {
@@ -175,12 +234,81 @@ async fn login_link(
     Ok((headers, query.redirect()).into_response())
 }
 
+/// Show the registration form for a new user following their emailed link.
+async fn register_page(State(state): State<SharedAppState>, Query(query): Query<LoginQuery>) -> HtmlResult {
+    let Some(token) = query.token else {
+        bail_not_found!()
+    };
+    let Some(new_user) = User::lookup_by_login_token(&state.db, &token).await? else {
+        bail_not_found!();
+    };
+
+    #[derive(Template, WebTemplate)]
+    #[template(path = "auth/register.html")]
+    struct Html {
+        user: Option<User>,
+        new_user: User,
+        token: String,
+        redirect: Option<String>,
+    }
+    Ok(Html { user: None, new_user, token, redirect: query.redirect }.into_response())
+}
+
+#[derive(serde::Deserialize)]
+struct RegisterForm {
+    token: String,
+    first_name: String,
+    last_name: String,
+    phone: Option<String>,
+    redirect: Option<String>,
+}
+
+/// Save a new user's name, then log them in like a login link would.
+async fn register_form(State(state): State<SharedAppState>, Form(form): Form<RegisterForm>) -> HtmlResult {
+    let Some(user) = User::lookup_by_login_token(&state.db, &form.token).await? else {
+        bail_not_found!();
+    };
+    if form.first_name.trim().is_empty() || form.last_name.trim().is_empty() {
+        bail_invalid!();
+    }
+
+    let info = UpdateUser {
+        email: user.email.clone(),
+        first_name: Some(form.first_name),
+        last_name: Some(form.last_name),
+        phone: form.phone.filter(|p| !p.trim().is_empty()),
+    };
+    let user = user.update(&state.db, &info).await?;
+
+    LoginToken::delete_by_token(&state.db, &form.token).await?;
+    let token = SessionToken::create(&state.db, &user).await?;
+    let cookie = session_cookie(&state.config, token);
+
+    let headers = [(header::SET_COOKIE, cookie)];
+    let redirect = Redirect::to(form.redirect.as_deref().unwrap_or("/account"));
+    Ok((headers, redirect).into_response())
+}
+
 fn session_cookie(config: &Config, token: String) -> String {
     Cookie::build(("session", token))
         .secure(config.acme.is_some())
         .http_only(true)
         .same_site(cookie::SameSite::Lax)
         .domain(&config.app.domain)
+        .path("/")
         .max_age(cookie::time::Duration::days(config.app.session_expiry_days as i64))
         .to_string()
 }
+
+/// Log out of the current session.
+async fn logout_form(State(state): State<SharedAppState>, cookies: CookieJar) -> HtmlResult {
+    if let Some(token) = cookies.get("session") {
+        SessionToken::delete_by_token(&state.db, token.value()).await?;
+    }
+    let clear = Cookie::build(("session", ""))
+        .max_age(cookie::time::Duration::ZERO)
+        .domain(&state.config.app.domain)
+        .path("/")
+        .to_string();
+    Ok(([(header::SET_COOKIE, clear)], Redirect::to("/")).into_response())
+}
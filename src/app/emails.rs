@@ -1,4 +1,9 @@
+use lettre::message::MessageBuilder;
+
+use crate::db::email_click::{EmailClick, EmailLink};
 use crate::db::list::List;
+use crate::db::preference::{EmailPause, PreferenceChange};
+use crate::db::user::UpdateUser;
 use crate::prelude::*;
 
 /// Add all `email` routes to the router.
@@ -6,7 +11,9 @@ use crate::prelude::*;
 pub fn add_routes(router: AppRouter) -> AppRouter {
     router.public_routes(|r| {
         r.route("/emails/{token}/footer.gif", get(email_opened))
+         .route("/emails/{token}/click/{link_id}", get(email_clicked))
          .route("/emails/{token}/unsubscribe", get(email_unsubscribe_view).post(email_unsubscribe_form))
+         .route("/emails/{token}/preferences", get(preferences_page).post(preferences_form))
     })
 }
 
@@ -22,12 +29,29 @@ async fn email_opened(Path(token): Path<String>, State(state): State<SharedAppSt
     Ok(pixel)
 }
 
+async fn email_clicked(
+    Path((token, link_id)): Path<(String, i64)>, State(state): State<SharedAppState>,
+) -> HtmlResult {
+    let link = EmailLink::lookup_by_id(&state.db, link_id).await?.ok_or_else(not_found)?;
+
+    // Record the click IF the email exists, a forwarded or stale link should still work.
+    if let Some(email) = Email::lookup_by_token(&state.db, &token).await? {
+        EmailClick::record(&state.db, email.id, link.id).await?;
+        // Clicking means it was opened, even if images were blocked.
+        Email::mark_opened_by_token(&state.db, &token).await?;
+    }
+    Ok(Redirect::to(&link.url).into_response())
+}
+
 async fn email_unsubscribe_view(
     user: Option<User>, Path(token): Path<String>, State(state): State<SharedAppState>,
 ) -> HtmlResult {
     // TODO: Better error handling rather than silently eating
     if let Some(email) = Email::lookup_by_token(&state.db, &token).await? {
-        let list_id = email.list_id.ok_or_else(invalid)?;
+        // Emails sent to a segment span several lists, so let the recipient pick which to leave.
+        let Some(list_id) = email.list_id else {
+            return Ok(Redirect::to(&format!("/emails/{token}/preferences")).into_response());
+        };
         let list = List::lookup_by_id(&state.db, list_id).await?.ok_or_else(invalid)?;
 
         #[derive(Template, WebTemplate)]
@@ -57,10 +81,179 @@ async fn email_unsubscribe_form(
         && let Some(list_id) = email.list_id
     {
         List::remove_member(&state.db, list_id, email.user_id).await?;
+        PreferenceChange::record(&state.db, &email, PreferenceChange::UNSUBSCRIBE, Some(list_id)).await?;
     }
     Ok("You have been unsubscribed.".into_response())
 }
 
+#[derive(Template, WebTemplate)]
+#[template(path = "emails/preferences.html")]
+struct PreferencesHtml {
+    user: Option<User>,
+    email_token: String,
+    member: User,
+    lists: Vec<PreferenceList>,
+    paused_until: Option<NaiveDateTime>,
+    pause_days: i64,
+    message: Option<String>,
+}
+
+struct PreferenceList {
+    list: List,
+    subscribed: bool,
+}
+
+impl PreferencesHtml {
+    /// Every public list, plus any private list the user is on so they can leave it.
+    async fn build(
+        db: &Db, user: Option<User>, email: &Email, member: User, message: Option<String>,
+    ) -> Result<PreferencesHtml> {
+        let joined = List::list_for_user(db, member.id).await?;
+        let mut lists: Vec<PreferenceList> = List::list_public(db)
+            .await?
+            .into_iter()
+            .map(|list| PreferenceList { subscribed: joined.iter().any(|j| j.id == list.id), list })
+            .collect();
+        for list in joined {
+            if !list.public {
+                lists.push(PreferenceList { list, subscribed: true });
+            }
+        }
+        let paused_until = EmailPause::lookup(db, member.id).await?;
+
+        Ok(PreferencesHtml {
+            user,
+            email_token: email.token.clone(),
+            member,
+            lists,
+            paused_until,
+            pause_days: EmailPause::DAYS,
+            message,
+        })
+    }
+}
+
+/// Display the preference center linked from every email footer.
+async fn preferences_page(
+    user: Option<User>, Path(token): Path<String>, State(state): State<SharedAppState>,
+) -> HtmlResult {
+    let email = Email::lookup_by_token(&state.db, &token)
+        .await?
+        .filter(Email::has_preferences)
+        .ok_or_else(not_found)?;
+    let member = User::lookup_by_id(&state.db, email.user_id).await?.ok_or_else(not_found)?;
+    Ok(PreferencesHtml::build(&state.db, user, &email, member, None)
+        .await?
+        .into_response())
+}
+
+/// Apply preference center changes, recording each one.
+///
+/// Checkboxes repeat the `list_id` key, so the form is read as raw pairs.
+async fn preferences_form(
+    user: Option<User>, Path(token): Path<String>, State(state): State<SharedAppState>,
+    Form(pairs): Form<Vec<(String, String)>>,
+) -> HtmlResult {
+    let email = Email::lookup_by_token(&state.db, &token)
+        .await?
+        .filter(Email::has_preferences)
+        .ok_or_else(not_found)?;
+    let member = User::lookup_by_id(&state.db, email.user_id).await?.ok_or_else(not_found)?;
+
+    // Names are only changed when submitted, and a blank one clears the name.
+    let non_empty = |s: String| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+    let mut first_name = None;
+    let mut last_name = None;
+    let mut pause = false;
+    let mut list_ids = vec![];
+    for (key, value) in pairs {
+        match key.as_str() {
+            "first_name" => first_name = Some(non_empty(value)),
+            "last_name" => last_name = Some(non_empty(value)),
+            "pause" => pause = true,
+            "list_id" => list_ids.push(value.parse::<i64>().map_err(|_| invalid())?),
+            _ => {}
+        }
+    }
+
+    // Anyone can join a public list, but private lists can only be left.
+    let joined = List::list_for_user(&state.db, member.id).await?;
+    for list in List::list_public(&state.db).await? {
+        if list_ids.contains(&list.id) && !joined.iter().any(|j| j.id == list.id) {
+            List::add_members(&state.db, list.id, &[member.email.as_str()]).await?;
+            PreferenceChange::record(&state.db, &email, PreferenceChange::SUBSCRIBE, Some(list.id)).await?;
+        }
+    }
+    for list in &joined {
+        if !list_ids.contains(&list.id) {
+            List::remove_member(&state.db, list.id, member.id).await?;
+            PreferenceChange::record(&state.db, &email, PreferenceChange::UNSUBSCRIBE, Some(list.id)).await?;
+        }
+    }
+
+    let paused = EmailPause::lookup(&state.db, member.id).await?.is_some();
+    if pause && !paused {
+        EmailPause::pause(&state.db, member.id).await?;
+        PreferenceChange::record(&state.db, &email, PreferenceChange::PAUSE, None).await?;
+    } else if !pause && paused {
+        EmailPause::resume(&state.db, member.id).await?;
+        PreferenceChange::record(&state.db, &email, PreferenceChange::RESUME, None).await?;
+    }
+
+    let info = UpdateUser {
+        email: member.email.clone(),
+        first_name: first_name.unwrap_or(member.first_name.clone()),
+        last_name: last_name.unwrap_or(member.last_name.clone()),
+        phone: member.phone.clone(),
+    };
+    let updated = member.update(&state.db, &info).await?;
+    if updated.version != member.version {
+        PreferenceChange::record(&state.db, &email, PreferenceChange::NAME, None).await?;
+    }
+
+    let message = Some("Your preferences have been saved.".to_string());
+    Ok(PreferencesHtml::build(&state.db, user, &email, updated, message)
+        .await?
+        .into_response())
+}
+
+/// Who a test send fills in merge tags for, the sender if empty.
+#[derive(serde::Deserialize)]
+pub struct TestSendForm {
+    pub recipient: Option<String>,
+    /// The list or segment a post test stands in for, see `Audience::parse`. Invites use their guest list.
+    pub audience: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct TestSendResponse {
+    sent_to: String,
+}
+
+/// Send a test of a post or event email, already rendered for some recipient, to the admin asking
+/// for it and nobody else. `email_id` comes from `Email::create_test`, and the subject is marked so the
+/// test can't be mistaken for the real thing.
+pub async fn send_test(
+    state: &SharedAppState, user: &User, email_id: i64, builder: MessageBuilder, subject: &str, body: String,
+) -> JsonResult<TestSendResponse> {
+    let message = builder
+        .to(user.email.parse().map_err(|_| invalid())?)
+        .subject(format!("[Test] {subject}"))
+        .header(lettre::message::header::ContentType::TEXT_HTML)
+        .body(body)?;
+
+    match state.mailer.send(&message).await {
+        Ok(_) => Email::mark_sent(&state.db, email_id).await?,
+        Err(e) => {
+            let e = e.message();
+            Email::mark_error(&state.db, email_id, e).await?;
+            alert!("Error sending test email_id={email_id} to email={:?}: {e}", user.email);
+            bail_bad_request!("Failed to send the test, try again later.");
+        }
+    }
+    Ok(Json(TestSendResponse { sent_to: user.email.clone() }))
+}
+
 /// A 1x1 transparent GIF.
 #[rustfmt::skip]
 const PIXEL: &[u8] = &[
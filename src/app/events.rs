@@ -1,7 +1,13 @@
+use axum::extract::DefaultBodyLimit;
 use image::DynamicImage;
 
+use crate::db::audit_log::{AuditLog, AuditTarget};
 use crate::db::event::{Event, EventLimits, EventWithStats, UpdateEvent};
 use crate::db::event_flyer::*;
+use crate::db::event_guest_list::EventGuestList;
+use crate::db::event_photo::EventPhoto;
+use crate::db::event_session::*;
+use crate::db::event_staff::*;
 use crate::db::rsvp_session::*;
 use crate::db::spot::*;
 use crate::prelude::*;
@@ -13,9 +19,12 @@ pub fn add_routes(router: AppRouter) -> AppRouter {
         .public_routes(|r| {
             r.route("/e/{slug}", get(read::view_page))
                 .route("/e/{slug}/flyer", get(read::flyer_by_slug))
+                .route("/e/{slug}/photos", get(read::photos_page))
+                .route("/e/{slug}/photos/{id}", get(read::photo_by_slug))
                 .route("/e/{slug}/stats", get(read::stats_page))
                 .route("/e/{slug}/rsvp", get(rsvp::rsvp_form))
                 .route("/e/{slug}/rsvp/guestlist", get(rsvp::guestlist_page).post(rsvp::guestlist_form))
+                .route("/e/{slug}/invite/{token}", get(rsvp::invite_link_page).post(rsvp::invite_link_form))
                 .route("/e/{slug}/rsvp/selection", get(rsvp::selection_page).post(rsvp::selection_form))
                 .route("/e/{slug}/rsvp/attendees", get(rsvp::attendees_page).post(rsvp::attendees_form))
                 .route("/e/{slug}/rsvp/contribution", get(rsvp::contribution_page).post(rsvp::contribution_form))
@@ -23,41 +32,60 @@ pub fn add_routes(router: AppRouter) -> AppRouter {
                 .route("/e/{slug}/rsvp/add-guests", post(rsvp::add_guests_form))
                 .route("/e/{slug}/rsvp/edit", get(rsvp::edit_guests_page).post(rsvp::edit_guests_form))
         })
-        .restricted_routes(User::ADMIN, |r| {
+        .restricted_routes(Permission::EventsCheckin, |r| {
             r.route("/events", get(read::list_page))
                 .route("/events/all", get(read::list_all_page))
-                .route("/events/sessions", get(read::sessions_page))
+        })
+        .event_routes(Permission::EventsCheckin, |r| {
+            r.route("/events/{id}/attendees", get(edit::attendees_page))
+                .route("/events/{id}/attendees/{user_id}/checkin", post(edit::set_checkin).delete(edit::clear_checkin))
+                .route("/events/{id}/sessions/{session_id}/checkin/{user_id}", post(edit::set_session_checkin).delete(edit::clear_session_checkin))
+        })
+        .restricted_routes(Permission::EventsEdit, |r| {
+            r.route("/events/sessions", get(read::sessions_page))
                 .route("/events/sessions/{id}", delete(read::delete_session))
                 .route("/events/new", get(edit::new_page))
-                .route("/events/{id}/edit", get(edit::edit_page).post(edit::edit_form))
+                .route("/events/{id}/edit", get(edit::edit_page).post(edit::edit_form).layer(DefaultBodyLimit::max(EventPhoto::UPLOAD_LIMIT)))
+                .route("/events/{id}/photos", get(edit::photos_page).post(edit::photos_form))
                 .route("/events/{id}/delete", post(edit::delete_form))
                 .route("/events/{id}/duplicate", post(edit::duplicate_form))
                 .route("/events/{id}/flyer", get(read::flyer_by_id))
-                .route("/events/{id}/attendees", get(edit::attendees_page))
                 .route("/events/{id}/attendees/add", get(edit::add_attendee_page).post(edit::add_attendee_form))
                 .route("/events/{id}/attendees/search", get(edit::search_attendees))
                 .route("/events/{id}/attendees/{user_id}", delete(edit::delete_attendee))
                 .route("/events/{id}/attendees/{user_id}/refund", post(edit::refund_attendee))
-                .route("/events/{id}/attendees/{user_id}/checkin", post(edit::set_checkin).delete(edit::clear_checkin))
                 .route("/events/{id}/attendees/{user_id}/edit", get(edit::edit_attendee_page).post(edit::edit_attendee_form))
+                .route("/events/{id}/staff", post(edit::grant_staff_form))
+                .route("/events/{id}/staff/{user_id}", delete(edit::revoke_staff))
                 .route("/events/{id}/invite/edit", get(edit::edit_invite_page).post(edit::edit_invite_form))
                 .route("/events/{id}/invite/preview", get(edit::preview_invite_page))
-                .route("/events/{id}/invite/send", get(edit::send_invite_page).post(edit::send_invite_form))
+                .route("/events/{id}/invite/test", post(edit::test_invite_form))
+                .route("/events/{id}/invite-links", get(edit::invite_links_page).post(edit::create_invite_link_form))
+                .route("/events/{id}/invite-links/{link_id}/delete", post(edit::delete_invite_link_form))
                 .route("/events/{id}/confirmation/edit", get(edit::edit_confirmation_page).post(edit::edit_confirmation_form))
                 .route("/events/{id}/confirmation/preview", get(edit::preview_confirmation_page))
+                .route("/events/{id}/confirmation/test", post(edit::test_confirmation_form))
                 .route("/events/{id}/dayof/edit", get(edit::edit_dayof_page).post(edit::edit_dayof_form))
                 .route("/events/{id}/dayof/preview", get(edit::preview_dayof_page))
+                .route("/events/{id}/dayof/test", post(edit::test_dayof_form))
                 .route("/events/{id}/dayof/send", get(edit::send_dayof_page).post(edit::send_dayof_form))
                 .route("/events/{id}/description/edit", get(edit::edit_description_page).post(edit::edit_description_form))
         })
+        .restricted_routes(Permission::ListsSend, |r| {
+            r.route("/events/{id}/invite/send", get(edit::send_invite_page).post(edit::send_invite_form))
+                .route("/events/{id}/invite/report", get(edit::invite_report_page))
+        })
 }
 
 // View and list events.
 mod read {
+    use axum::http::HeaderMap;
+
     use super::*;
     use crate::db::manual_rsvp::ManualRsvp;
     use crate::db::rsvp::Rsvp;
     use crate::db::rsvp_session;
+    use crate::utils::meta::PageMeta;
 
     /// View an event.
     pub async fn view_page(
@@ -71,7 +99,10 @@ mod read {
             pub user: Option<User>,
             event: Event,
             flyer: Option<EventFlyer>,
+            sessions: Vec<EventSession>,
             full: bool,
+            photos: Vec<EventPhoto>,
+            meta: PageMeta,
         }
 
         let event = Event::lookup_by_slug(&state.db, &slug).await?.ok_or_else(not_found)?;
@@ -85,7 +116,42 @@ mod read {
         let reserved = Rsvp::list_all_reserved_for_event(&state.db, &event).await?;
         let manual_count = ManualRsvp::count_for_event(&state.db, event.id).await?;
         let full = reserved.len() as i64 + manual_count >= event.capacity;
-        Ok(Html { session, user, event, flyer, full }.into_response())
+        let sessions = EventSession::list_for_event(&state.db, event.id).await?;
+        let mut photos = EventPhoto::list_for_event(&state.db, event.id).await?;
+        photos.sort_by_key(|photo| !photo.cover);
+        let spots = Spot::list_for_event(&state.db, event.id).await?;
+        let rsvp_counts = Spot::rsvp_counts_for_event(&state.db, event.id).await?;
+        let meta = PageMeta::for_event(&event, flyer.as_ref(), &spots, &rsvp_counts);
+        Ok(Html { session, user, event, flyer, sessions, full, photos, meta }.into_response())
+    }
+
+    /// An event's photos, cover first.
+    pub async fn photos_page(
+        user: Option<User>, State(state): State<SharedAppState>, Path(slug): Path<String>,
+    ) -> HtmlResult {
+        #[derive(Template, WebTemplate)]
+        #[template(path = "events/photos.html")]
+        struct Html {
+            user: Option<User>,
+            event: Event,
+            photos: Vec<EventPhoto>,
+        }
+
+        let event = Event::lookup_by_slug(&state.db, &slug).await?.ok_or_else(not_found)?;
+        let mut photos = EventPhoto::list_for_event(&state.db, event.id).await?;
+        if photos.is_empty() {
+            bail_not_found!();
+        }
+        photos.sort_by_key(|photo| !photo.cover);
+        Ok(Html { user, event, photos }.into_response())
+    }
+
+    /// Serve an event photo.
+    pub async fn photo_by_slug(
+        State(state): State<SharedAppState>, Path((slug, id)): Path<(String, i64)>,
+        Query(params): Query<std::collections::HashMap<String, String>>,
+    ) -> HtmlResult {
+        EventPhoto::serve(&state.db, &slug, id, params.get("size")).await
     }
 
     // List all events.
@@ -110,33 +176,46 @@ mod read {
     /// Serve an event flyer.
     pub async fn flyer_by_id(
         State(state): State<SharedAppState>, Path(id): Path<i64>,
-        Query(params): Query<std::collections::HashMap<String, String>>,
+        Query(params): Query<std::collections::HashMap<String, String>>, headers: HeaderMap,
     ) -> HtmlResult {
         let Some(event) = Event::lookup_by_id(&state.db, id).await? else {
             bail_not_found!();
         };
-        EventFlyer::serve(&state.db, &event.slug, params.get("size")).await
+        EventFlyer::serve(&state.db, &event.slug, params.get("size"), flyer_accept(&params, &headers)).await
     }
     pub async fn flyer_by_slug(
         State(state): State<SharedAppState>, Path(slug): Path<String>,
-        Query(params): Query<std::collections::HashMap<String, String>>,
+        Query(params): Query<std::collections::HashMap<String, String>>, headers: HeaderMap,
     ) -> HtmlResult {
-        EventFlyer::serve(&state.db, &slug, params.get("size")).await
+        EventFlyer::serve(&state.db, &slug, params.get("size"), flyer_accept(&params, &headers)).await
+    }
+    /// The Accept header to pick a flyer format by, unless `format=jpeg` pins it, e.g. for feed enclosures.
+    fn flyer_accept<'a>(
+        params: &std::collections::HashMap<String, String>, headers: &'a HeaderMap,
+    ) -> Option<&'a str> {
+        match params.get("format").map(String::as_str) {
+            Some("jpeg") => None,
+            _ => headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()),
+        }
     }
 
     #[derive(serde::Deserialize)]
     pub struct StatsQuery {
-        secret: String,
+        secret: Option<String>,
     }
 
-    /// Semi-public stats page for event organizers, gated by the event token.
+    /// Semi-public stats page for event organizers, gated by the event token or a co-organizer grant.
     pub async fn stats_page(
         user: Option<User>, State(state): State<SharedAppState>, Path(slug): Path<String>,
         Query(query): Query<StatsQuery>,
     ) -> HtmlResult {
         let event = Event::lookup_by_slug(&state.db, &slug).await?.ok_or_else(not_found)?;
-        if query.secret != event.token {
-            bail_not_found!();
+        if query.secret.as_ref() != Some(&event.token) {
+            let Some(user) = &user else { bail_not_found!() };
+            let staff = EventStaff::lookup_active(&state.db, &event, user.id).await?;
+            if !staff.is_some_and(|s| s.is_organizer()) {
+                bail_not_found!();
+            }
         }
 
         let mut spots = Spot::stats_for_event(&state.db, event.id).await?;
@@ -200,7 +279,9 @@ mod read {
         Ok(SessionsHtml { user: Some(user), sessions }.into_response())
     }
 
-    pub async fn delete_session(State(state): State<SharedAppState>, Path(id): Path<i64>) -> JsonResult<()> {
+    pub async fn delete_session(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+    ) -> JsonResult<()> {
         let session = RsvpSession::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?;
         if matches!(
             session.status.as_str(),
@@ -212,6 +293,8 @@ mod read {
             bail_invalid!();
         }
         session.delete(&state.db, &state.stripe).await?;
+        let target = AuditTarget::RsvpSession { event_id: session.event_id, id: session.id };
+        AuditLog::record(&state.db, &user, "rsvp_session.delete", target, &session, ()).await?;
         Ok(Json(()))
     }
 }
@@ -221,11 +304,18 @@ mod edit {
     use axum::body::Body;
 
     use super::*;
+    use crate::app::emails::{TestSendForm, TestSendResponse, send_test};
+    use crate::db::email_click::EmailReport;
+    use crate::db::invite_link::{InviteLink, InviteLinkWithRedemptions};
     use crate::db::list::{List, ListWithCount};
     use crate::db::manual_rsvp::ManualRsvp;
+    use crate::db::revision::Revision;
     use crate::db::rsvp::{AdminAttendeesRsvp, Rsvp};
+    use crate::db::segment::{Audience, Segment, SegmentCount};
     use crate::db::user::{AttendeeSearchField, AttendeeSearchResult, CreateUser, UpdateUser};
     use crate::utils::editor::{Editor, EditorContent};
+    use crate::utils::merge_tags::MergeTags;
+    use crate::utils::tracking::LinkTracker;
 
     #[derive(Template, WebTemplate)]
     #[template(path = "events/edit.html")]
@@ -234,8 +324,13 @@ mod edit {
         event: Event,
         spots: Vec<Spot>,
         rsvp_counts: std::collections::HashMap<i64, SpotCounts>,
+        sessions: Vec<EventSession>,
         flyer_version: Option<i64>,
+        /// Where the flyer is cropped around, as fractions of its width and height.
+        flyer_focal: (f64, f64),
         lists: Vec<ListWithCount>,
+        /// Lists admitted besides `event.guest_list_id`.
+        guest_list_ids: Vec<i64>,
     }
 
     /// Display the form to create a new event.
@@ -281,8 +376,11 @@ mod edit {
             },
             spots: vec![],
             rsvp_counts: Default::default(),
+            sessions: vec![],
             flyer_version: None,
+            flyer_focal: (0.5, 0.5),
             lists,
+            guest_list_ids: vec![],
         }
         .into_response())
     }
@@ -294,9 +392,24 @@ mod edit {
         let event = Event::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?;
         let spots = Spot::list_for_event(&state.db, event.id).await?;
         let rsvp_counts = Spot::rsvp_counts_for_event(&state.db, event.id).await?;
-        let flyer_version = EventFlyer::version_for_event(&state.db, event.id).await?;
+        let sessions = EventSession::list_for_event(&state.db, event.id).await?;
+        let flyer = EventFlyer::lookup(&state.db, event.id).await?;
+        let flyer_version = flyer.as_ref().map(|flyer| flyer.version);
+        let flyer_focal = flyer.map_or((0.5, 0.5), |flyer| (flyer.focal_x, flyer.focal_y));
         let lists = List::list_with_counts(&state.db).await?;
-        Ok(EditHtml { user: Some(user), event, spots, rsvp_counts, flyer_version, lists }.into_response())
+        let guest_list_ids = EventGuestList::list_ids_for_event(&state.db, event.id).await?;
+        Ok(EditHtml {
+            user: Some(user),
+            event,
+            spots,
+            rsvp_counts,
+            sessions,
+            flyer_version,
+            flyer_focal,
+            lists,
+            guest_list_ids,
+        }
+        .into_response())
     }
 
     // Handle edit submission.
@@ -306,12 +419,29 @@ mod edit {
         #[serde(flatten)]
         event: UpdateEvent,
         spots: Vec<UpdateSpot>,
+        #[serde(default)]
+        sessions: Vec<EditSessionForm>,
+        /// Lists admitted besides `guest_list_id`.
+        #[serde(default)]
+        guest_list_ids: Vec<i64>,
+        /// Where to crop the flyer around, as fractions of its width and height.
+        #[serde(default)]
+        flyer_focal: Option<(f64, f64)>,
+    }
+    #[derive(Debug, serde::Deserialize)]
+    pub struct EditSessionForm {
+        #[serde(flatten)]
+        session: UpdateEventSession,
+        /// Indices into `EditForm::spots`, since new spots have no id yet.
+        spots: Vec<usize>,
     }
     pub async fn edit_form(
-        State(state): State<SharedAppState>, mut multipart: axum::extract::Multipart,
+        user: User, State(state): State<SharedAppState>, mut multipart: axum::extract::Multipart,
     ) -> JsonResult<()> {
         let mut form: Option<EditForm> = None;
         let mut flyer: Option<DynamicImage> = None;
+        // Kept encoded until the event is saved, decoding them all at once takes a lot of memory.
+        let mut photos = vec![];
 
         while let Some(field) = multipart.next_field().await? {
             match field.name().unwrap_or("") {
@@ -326,6 +456,16 @@ mod edit {
                         .map_err(|_| bad_request("Unsupported or invalid image format."))?;
                     flyer = Some(img);
                 }
+                "photo" => {
+                    let data = field.bytes().await?;
+                    if !matches!(
+                        image::guess_format(&data),
+                        Ok(image::ImageFormat::Png | image::ImageFormat::Jpeg)
+                    ) {
+                        bail_bad_request!("Unsupported or invalid image format.");
+                    }
+                    photos.push(data);
+                }
                 _ => {}
             }
         }
@@ -333,10 +473,14 @@ mod edit {
         let mut form = form.ok_or_else(invalid)?;
 
         // Kind is chosen at creation and immutable afterwards; ignore the submitted kind on update.
-        if form.id != 0 {
-            let event = Event::lookup_by_id(&state.db, form.id).await?.ok_or_else(not_found)?;
-            form.event.kind = event.kind;
+        let before = match form.id {
+            0 => None,
+            id => Some(Event::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?),
+        };
+        if let Some(event) = &before {
+            form.event.kind = event.kind.clone();
         }
+        let before_guest_list_ids = EventGuestList::list_ids_for_event(&state.db, form.id).await?;
 
         // Validate slug: must be non-empty and only contain alphanumeric characters and dashes
         if form.event.slug.is_empty()
@@ -359,25 +503,33 @@ mod edit {
                 form.event.unlisted = false;
                 form.event.closed = false;
                 form.event.guest_list_id = None;
+                form.guest_list_ids = vec![];
                 form.event.spots_per_person = None;
                 form.event.artist_share = 0;
                 form.spots = vec![];
+                form.sessions = vec![];
             }
             _ => bail_invalid!(),
         }
 
-        match form.id {
+        if form.sessions.iter().flat_map(|s| &s.spots).any(|&i| i >= form.spots.len()) {
+            bail_invalid!();
+        }
+
+        // Spot ids in form order, for linking sessions
+        let mut spot_ids = vec![];
+        let event_id = match form.id {
             0 => {
                 tracing::info!("create: {:?}", &form.event);
                 let event_id = Event::create(&state.db, &form.event, &flyer).await?;
 
-                let mut spot_ids = vec![];
                 for spot in form.spots {
                     let id = Spot::create(&state.db, &spot).await?;
                     spot_ids.push(id);
                 }
 
-                Spot::add_to_event(&state.db, event_id, spot_ids).await?;
+                Spot::add_to_event(&state.db, event_id, spot_ids.clone()).await?;
+                event_id
             }
             id => {
                 tracing::info!("edit: {:?}", &form.event);
@@ -392,10 +544,12 @@ mod edit {
                         Some(id) => {
                             Spot::update(&state.db, id, &spot).await?;
                             to_delete.retain(|&id_| id_ != id);
+                            spot_ids.push(id);
                         }
                         None => {
                             let id = Spot::create(&state.db, &spot).await?;
                             to_add.push(id);
+                            spot_ids.push(id);
                         }
                     }
                 }
@@ -406,9 +560,59 @@ mod edit {
 
                 Spot::add_to_event(&state.db, id, to_add).await?;
                 Spot::remove_from_event(&state.db, id, to_delete).await?;
+                id
             }
+        };
+
+        // Sync sessions: update existing, create new, and delete any no longer submitted
+        let mut to_delete = EventSession::list_ids_for_event(&state.db, event_id).await?;
+        for EditSessionForm { session, spots } in form.sessions {
+            let session_id = match session.id {
+                Some(id) if to_delete.contains(&id) => {
+                    EventSession::update(&state.db, id, &session).await?;
+                    to_delete.retain(|&id_| id_ != id);
+                    id
+                }
+                Some(_) => bail_invalid!(),
+                None => EventSession::create(&state.db, event_id, &session).await?,
+            };
+            EventSession::set_spots(&state.db, session_id, spots.iter().map(|&i| spot_ids[i]).collect())
+                .await?;
+        }
+        EventSession::delete(&state.db, to_delete).await?;
+
+        // Further guest lists only apply to private events
+        let mut guest_list_ids = form.guest_list_ids;
+        guest_list_ids.retain(|&id| form.event.guest_list_id.is_some_and(|primary| primary != id));
+        EventGuestList::set_for_event(&state.db, event_id, &guest_list_ids).await?;
+
+        if let Some(focal) = form.flyer_focal {
+            EventFlyer::set_focal_point(&state.db, event_id, focal).await?;
         }
 
+        let mut photo_ids = vec![];
+        for data in &photos {
+            let img = crate::utils::image::decode(data)
+                .await
+                .map_err(|_| bad_request("Unsupported or invalid image format."))?;
+            photo_ids.push(EventPhoto::create(&state.db, event_id, &img).await?);
+        }
+
+        let after = Event::lookup_by_id(&state.db, event_id).await?;
+        let action = if before.is_some() { "event.edit" } else { "event.create" };
+        let mut before = json!(before);
+        if let Some(before) = before.as_object_mut() {
+            before.insert("guest_list_ids".into(), json!(before_guest_list_ids));
+        }
+        let mut after = json!(after);
+        if let Some(after) = after.as_object_mut() {
+            after.insert("guest_list_ids".into(), json!(guest_list_ids));
+            if !photo_ids.is_empty() {
+                after.insert("added_photo_ids".into(), json!(photo_ids));
+            }
+        }
+        AuditLog::record(&state.db, &user, action, AuditTarget::Event(event_id), before, after).await?;
+
         Ok(Json(()))
     }
 
@@ -432,8 +636,10 @@ mod edit {
             event: event.clone(),
             editor: Editor {
                 url: "/events/{id}/invite/edit",
-                snapshot_prefix: "event/invite",
+                snapshot_prefix: Revision::EVENT_INVITE,
                 entity_id: Some(event.id),
+                revisions_url: Some(format!("/events/{}/invite/revisions", event.id)),
+                merge_tags: MergeTags::EVENT_TAGS,
                 content: match (event.invite_html, event.invite_updated_at) {
                     (Some(html), Some(updated_at)) => Some(EditorContent { html, updated_at }),
                     _ => None,
@@ -457,13 +663,34 @@ mod edit {
         error: Option<String>,
     }
     pub async fn edit_invite_form(
-        State(state): State<SharedAppState>, Form(form): Form<EditInviteForm>,
+        user: User, State(state): State<SharedAppState>, Form(form): Form<EditInviteForm>,
     ) -> JsonResult<EditInviteResponse> {
         let Some(event) = Event::lookup_by_id(&state.db, form.id).await? else {
             bail_not_found!();
         };
 
-        let updated_at = Event::update_invite(&state.db, event.id, form.subject, form.content).await?;
+        let before = json!({"subject": event.invite_subject, "html": event.invite_html});
+        let after = json!({"subject": form.subject, "html": form.content});
+        let updated_at =
+            Event::update_invite(&state.db, event.id, form.subject.clone(), form.content.clone()).await?;
+        Revision::record(
+            &state.db,
+            Revision::EVENT_INVITE,
+            event.id,
+            Some(&form.subject),
+            &form.content,
+            &user,
+        )
+        .await?;
+        AuditLog::record(
+            &state.db,
+            &user,
+            "event.invite.edit",
+            AuditTarget::Event(event.id),
+            before,
+            after,
+        )
+        .await?;
 
         Ok(Json(EditInviteResponse {
             id: Some(event.id),
@@ -476,35 +703,85 @@ mod edit {
     #[template(path = "emails/event_invite.html")]
     struct InviteEmailHtml {
         email_token: String,
+        track_opens: bool,
         email: String,
         event: Event,
         flyer: Option<EventFlyer>,
     }
+    #[derive(serde::Deserialize)]
+    pub struct PreviewQuery {
+        /// Email of the user to fill in merge tags for, otherwise the viewer.
+        recipient: Option<String>,
+    }
     // Preview invite page.
     pub async fn preview_invite_page(
         user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+        Query(query): Query<PreviewQuery>,
     ) -> HtmlResult {
-        let Some(event) = Event::lookup_by_id(&state.db, id).await? else {
+        let Some(mut event) = Event::lookup_by_id(&state.db, id).await? else {
             bail_not_found!()
         };
         let flyer = EventFlyer::lookup(&state.db, event.id).await?;
+        let recipient =
+            MergeTags::preview_recipient::<HtmlError>(&state.db, user, query.recipient.as_deref()).await?;
+        MergeTags::for_event(&state.db, &event, &recipient).await?.apply(&mut event);
 
-        Ok(InviteEmailHtml { email_token: String::new(), email: user.email, event, flyer }.into_response())
+        Ok(InviteEmailHtml {
+            email_token: String::new(),
+            track_opens: false,
+            email: recipient.email,
+            event,
+            flyer,
+        }
+        .into_response())
     }
 
-    /// Display the form to send a post.
+    /// Send the invite, as a recipient would get it, to the viewer only.
+    pub async fn test_invite_form(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>, Form(form): Form<TestSendForm>,
+    ) -> JsonResult<TestSendResponse> {
+        let Some(mut event) = Event::lookup_by_id(&state.db, id).await? else {
+            bail_not_found!()
+        };
+        let flyer = EventFlyer::lookup(&state.db, event.id).await?;
+        let recipient =
+            MergeTags::preview_recipient::<JsonError>(&state.db, user.clone(), form.recipient.as_deref())
+                .await?;
+        MergeTags::for_event(&state.db, &event, &recipient).await?.apply(&mut event);
+        let Some(subject) = event.invite_subject.clone() else {
+            bail_bad_request!("Save the invite before sending a test.");
+        };
+
+        let (email_id, token) = Email::create_test(&state.db, &user, None, Some(event.id)).await?;
+        let list_ids: Vec<_> = event.guest_list_id.into_iter().collect();
+        let tracking = List::tracking_allowed(&state.db, &list_ids).await?;
+        let mut tracker = LinkTracker::new(tracking);
+        let template = InviteEmailHtml {
+            email_token: token.clone(),
+            track_opens: tracking,
+            email: recipient.email,
+            event,
+            flyer,
+        };
+        let body = tracker.track(&state.db, template.render()?, &token).await?;
+
+        let from = &state.config.email.from;
+        let reply_to = config().email.contact_to.as_ref().unwrap_or(from);
+        let builder = state.mailer.builder().reply_to(reply_to.clone());
+        send_test(&state, &user, email_id, builder, &subject, body).await
+    }
+
+    /// Display the form to send invites to the guest list or a segment.
     pub async fn send_invite_page(
         user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
     ) -> HtmlResult {
         let Some(event) = Event::lookup_by_id(&state.db, id).await? else {
             bail_not_found!()
         };
-        let Some(guest_list_id) = event.guest_list_id else {
-            bail_invalid!()
-        };
 
         #[derive(sqlx::FromRow)]
         struct ListCounts {
+            id: i64,
             name: String,
             count: i64,
             sent: i64,
@@ -513,6 +790,7 @@ mod edit {
             ListCounts,
             r#"
             SELECT
+                l.id AS id,
                 l.name AS name,
                 COUNT(lm.user_id) AS count,
                 SUM(
@@ -534,45 +812,97 @@ mod edit {
             "#,
             Email::EVENT_INVITE,
             event.id,
-            guest_list_id,
+            event.guest_list_id,
         )
-        .fetch_one(&state.db)
+        .fetch_optional(&state.db)
         .await?;
+        let segments =
+            Segment::list_with_counts(&state.db, Email::EVENT_INVITE, None, Some(event.id)).await?;
+        if list.is_none() && segments.is_empty() {
+            bail_bad_request!("Set a guest list or create a segment to send invites to.");
+        }
 
         #[derive(Template, WebTemplate)]
         #[template(path = "events/send_invites.html")]
         struct SendHtml {
             user: Option<User>,
-            list: ListCounts,
+            list: Option<ListCounts>,
+            segments: Vec<SegmentCount>,
             event: Event,
             ratelimit: usize,
         }
         let ratelimit = state.config.email.ratelimit;
-        Ok(SendHtml { user: Some(user), event, list, ratelimit }.into_response())
+        Ok(SendHtml { user: Some(user), event, list, segments, ratelimit }.into_response())
     }
 
-    pub async fn send_invite_form(State(state): State<SharedAppState>, Path(id): Path<i64>) -> HtmlResult {
+    #[derive(serde::Deserialize)]
+    pub struct SendInviteForm {
+        /// The guest list or a segment, see `Audience::parse`.
+        audience: String,
+    }
+    pub async fn send_invite_form(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+        Form(form): Form<SendInviteForm>,
+    ) -> HtmlResult {
         let Some(event) = Event::lookup_by_id(&state.db, id).await? else {
             bail_not_found!();
         };
-        let Some(guest_list_id) = event.guest_list_id else {
+        let Some(audience) = Audience::parse(&form.audience) else {
             bail_invalid!()
         };
 
-        let emails = Email::create_send_invites(&state.db, event.id, guest_list_id).await?;
+        let (emails, list_ids) = match audience {
+            Audience::List(list_id) if Some(list_id) == event.guest_list_id => {
+                (Email::create_send_invites(&state.db, event.id, list_id).await?, vec![list_id])
+            }
+            Audience::List(_) => bail_invalid!(),
+            Audience::Segment(segment_id) => {
+                let segment = Segment::lookup_by_id(&state.db, segment_id).await?.ok_or_else(not_found)?;
+                let user_ids = segment.resolve(&state.db, Some(event.id)).await?;
+                let emails = Email::create_send_segment(
+                    &state.db,
+                    Email::EVENT_INVITE,
+                    None,
+                    Some(event.id),
+                    segment.id,
+                    &user_ids,
+                    false,
+                )
+                .await?;
+                (emails, segment.rules.lists)
+            }
+        };
+        let tracking = List::tracking_allowed(&state.db, &list_ids).await?;
+        let mut tracker = LinkTracker::new(tracking);
         let flyer = EventFlyer::lookup(&state.db, event.id).await?;
 
-        let mut email_template =
-            InviteEmailHtml { email_token: String::new(), email: "".into(), event: event.clone(), flyer };
+        let mut email_template = InviteEmailHtml {
+            email_token: String::new(),
+            track_opens: tracking,
+            email: "".into(),
+            event: event.clone(),
+            flyer,
+        };
         let mut messages = vec![];
         let mut email_ids = vec![];
-        for Email { id, address, sent_at, token, .. } in emails {
+        for Email { id, user_id, address, sent_at, token, .. } in emails {
             if sent_at.is_some() {
                 continue;
             }
+            let Some(recipient) = User::lookup_by_id(&state.db, user_id).await? else {
+                Email::mark_error(&state.db, id, "recipient not found").await?;
+                continue;
+            };
 
             email_template.email_token = token;
             email_template.email = address.clone();
+            email_template.event = event.clone();
+            MergeTags::for_event(&state.db, &event, &recipient)
+                .await?
+                .apply(&mut email_template.event);
+            let body = tracker
+                .track(&state.db, email_template.render()?, &email_template.email_token)
+                .await?;
 
             let from = &state.config.email.from;
             let reply_to = config().email.contact_to.as_ref().unwrap_or(from);
@@ -581,9 +911,9 @@ mod edit {
                 .builder()
                 .to(address.parse().unwrap())
                 .reply_to(reply_to.clone())
-                .subject(event.invite_subject.as_deref().expect("missing invite_subject"))
+                .subject(email_template.event.invite_subject.as_deref().expect("missing invite_subject"))
                 .header(lettre::message::header::ContentType::TEXT_HTML)
-                .body(email_template.render()?)
+                .body(body)
                 .unwrap();
 
             messages.push(message);
@@ -591,6 +921,9 @@ mod edit {
         }
 
         event.mark_sent_invites(&state.db).await?;
+        let after = json!({"audience": audience, "recipients": messages.len()});
+        AuditLog::record(&state.db, &user, "event.invite.send", AuditTarget::Event(event.id), (), after)
+            .await?;
 
         let email_ids = futures::stream::iter(email_ids);
         let results = state.mailer.send_batch(Arc::clone(&state), messages).await;
@@ -616,6 +949,182 @@ mod edit {
         Ok(body.into_response())
     }
 
+    /// Display delivery, opens, clicks and unsubscribes for an event's invites.
+    pub async fn invite_report_page(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+    ) -> HtmlResult {
+        let event = Event::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?;
+        let report = EmailReport::for_invites(&state.db, event.id).await?;
+
+        #[derive(Template, WebTemplate)]
+        #[template(path = "emails/report.html")]
+        struct Html {
+            user: Option<User>,
+            title: String,
+            send_url: String,
+            report: EmailReport,
+        }
+        Ok(Html {
+            user: Some(user),
+            title: format!("Invites to {}", event.title),
+            send_url: format!("/events/{}/invite/send", event.id),
+            report,
+        }
+        .into_response())
+    }
+
+    /// Display an event's personal invite links and who redeemed them.
+    pub async fn invite_links_page(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+    ) -> HtmlResult {
+        let event = Event::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?;
+        let links = InviteLink::list_for_event(&state.db, event.id).await?;
+
+        #[derive(Template, WebTemplate)]
+        #[template(path = "events/invite_links.html")]
+        struct InviteLinksHtml {
+            user: Option<User>,
+            event: Event,
+            links: Vec<InviteLinkWithRedemptions>,
+            app_url: String,
+        }
+        let app_url = state.config.app.url.clone();
+        Ok(InviteLinksHtml { user: Some(user), event, links, app_url }.into_response())
+    }
+
+    #[derive(serde::Deserialize)]
+    pub struct InviteLinkForm {
+        label: String,
+        /// Blank for unlimited uses.
+        max_uses: String,
+        /// Blank for a link that never expires.
+        expires_in_days: String,
+    }
+    pub async fn create_invite_link_form(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+        Form(form): Form<InviteLinkForm>,
+    ) -> HtmlResult {
+        let event = Event::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?;
+        let label = form.label.trim();
+        if label.is_empty() {
+            bail_bad_request!("Invite links need a label.");
+        }
+        let max_uses = match form.max_uses.trim() {
+            "" => None,
+            s => Some(s.parse::<i64>().ok().filter(|n| *n > 0).ok_or_else(invalid)?),
+        };
+        let expires_at = match form.expires_in_days.trim() {
+            "" => None,
+            s => {
+                let days = s.parse::<i64>().ok().filter(|n| *n > 0).ok_or_else(invalid)?;
+                Some(Utc::now().naive_utc() + chrono::Duration::days(days))
+            }
+        };
+
+        let link_id = InviteLink::create(&state.db, event.id, label, max_uses, expires_at, user.id).await?;
+        let after =
+            json!({"link_id": link_id, "label": label, "max_uses": max_uses, "expires_at": expires_at});
+        AuditLog::record(
+            &state.db,
+            &user,
+            "event.invite_link.create",
+            AuditTarget::Event(event.id),
+            (),
+            after,
+        )
+        .await?;
+
+        Ok(Redirect::to(&format!("/events/{}/invite-links", event.id)).into_response())
+    }
+
+    pub async fn delete_invite_link_form(
+        user: User, State(state): State<SharedAppState>, Path((id, link_id)): Path<(i64, i64)>,
+    ) -> HtmlResult {
+        let link = InviteLink::lookup_by_id(&state.db, link_id).await?.ok_or_else(not_found)?;
+        if link.event_id != id {
+            bail_not_found!();
+        }
+        InviteLink::delete(&state.db, link.id).await?;
+        AuditLog::record(&state.db, &user, "event.invite_link.delete", AuditTarget::Event(id), &link, ())
+            .await?;
+
+        Ok(Redirect::to(&format!("/events/{id}/invite-links")).into_response())
+    }
+
+    /// Manage an event's photos.
+    pub async fn photos_page(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+    ) -> HtmlResult {
+        let event = Event::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?;
+        let photos = EventPhoto::list_for_event(&state.db, event.id).await?;
+
+        #[derive(Template, WebTemplate)]
+        #[template(path = "events/photos_edit.html")]
+        struct PhotosEditHtml {
+            user: Option<User>,
+            event: Event,
+            photos: Vec<EventPhoto>,
+            has_cover: bool,
+        }
+        let has_cover = photos.iter().any(|photo| photo.cover);
+        Ok(PhotosEditHtml { user: Some(user), event, photos, has_cover }.into_response())
+    }
+
+    /// Fields repeat per photo, e.g. `caption-12`, so the form is read as raw pairs.
+    pub async fn photos_form(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+        Form(pairs): Form<Vec<(String, String)>>,
+    ) -> HtmlResult {
+        let event = Event::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?;
+        let before = EventPhoto::list_for_event(&state.db, event.id).await?;
+        let photo_id = |value: &str| {
+            let id = value.parse::<i64>().map_err(|_| invalid())?;
+            match before.iter().any(|photo| photo.id == id) {
+                true => Ok(id),
+                false => Err(invalid()),
+            }
+        };
+
+        let mut captions = std::collections::HashMap::new();
+        let mut positions = std::collections::HashMap::new();
+        let mut cover = None;
+        let mut to_delete = vec![];
+        for (key, value) in pairs {
+            if let Some(id) = key.strip_prefix("caption-") {
+                captions.insert(photo_id(id)?, value.trim().to_string());
+            } else if let Some(id) = key.strip_prefix("position-") {
+                positions.insert(photo_id(id)?, value.parse::<i64>().map_err(|_| invalid())?);
+            } else if key == "cover" {
+                cover = if value.is_empty() { None } else { Some(photo_id(&value)?) };
+            } else if key == "delete" {
+                to_delete.push(photo_id(&value)?);
+            }
+        }
+
+        for photo in &before {
+            let caption = captions.get(&photo.id).unwrap_or(&photo.caption);
+            let position = positions.get(&photo.id).unwrap_or(&photo.position);
+            EventPhoto::update(&state.db, photo.id, caption, *position).await?;
+        }
+        EventPhoto::set_cover(&state.db, event.id, cover).await?;
+        for photo_id in to_delete {
+            EventPhoto::delete(&state.db, photo_id).await?;
+        }
+
+        let after = EventPhoto::list_for_event(&state.db, event.id).await?;
+        AuditLog::record(
+            &state.db,
+            &user,
+            "event.photos.edit",
+            AuditTarget::Event(event.id),
+            &before,
+            &after,
+        )
+        .await?;
+
+        Ok(Redirect::to(&format!("/events/{id}/photos")).into_response())
+    }
+
     // Edit confirmation page.
     pub async fn edit_confirmation_page(
         user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
@@ -636,8 +1145,10 @@ mod edit {
             event: event.clone(),
             editor: Editor {
                 url: "/events/{id}/confirmation/edit",
-                snapshot_prefix: "event/confirmation",
+                snapshot_prefix: Revision::EVENT_CONFIRMATION,
                 entity_id: Some(event.id),
+                revisions_url: Some(format!("/events/{}/confirmation/revisions", event.id)),
+                merge_tags: MergeTags::EVENT_TAGS,
                 content: match (event.confirmation_html, event.confirmation_updated_at) {
                     (Some(html), Some(updated_at)) => Some(EditorContent { html, updated_at }),
                     _ => None,
@@ -661,13 +1172,35 @@ mod edit {
         error: Option<String>,
     }
     pub async fn edit_confirmation_form(
-        State(state): State<SharedAppState>, Form(form): Form<EditConfirmationForm>,
+        user: User, State(state): State<SharedAppState>, Form(form): Form<EditConfirmationForm>,
     ) -> JsonResult<EditConfirmationResponse> {
         let Some(event) = Event::lookup_by_id(&state.db, form.id).await? else {
             bail_not_found!();
         };
 
-        let updated_at = Event::update_confirmation(&state.db, event.id, form.subject, form.content).await?;
+        let before = json!({"subject": event.confirmation_subject, "html": event.confirmation_html});
+        let after = json!({"subject": form.subject, "html": form.content});
+        let updated_at =
+            Event::update_confirmation(&state.db, event.id, form.subject.clone(), form.content.clone())
+                .await?;
+        Revision::record(
+            &state.db,
+            Revision::EVENT_CONFIRMATION,
+            event.id,
+            Some(&form.subject),
+            &form.content,
+            &user,
+        )
+        .await?;
+        AuditLog::record(
+            &state.db,
+            &user,
+            "event.confirmation.edit",
+            AuditTarget::Event(event.id),
+            before,
+            after,
+        )
+        .await?;
 
         Ok(Json(EditConfirmationResponse {
             id: Some(event.id),
@@ -678,12 +1211,16 @@ mod edit {
 
     // Preview confirmation page.
     pub async fn preview_confirmation_page(
-        State(state): State<SharedAppState>, Path(id): Path<i64>,
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+        Query(query): Query<PreviewQuery>,
     ) -> HtmlResult {
-        let Some(event) = Event::lookup_by_id(&state.db, id).await? else {
+        let Some(mut event) = Event::lookup_by_id(&state.db, id).await? else {
             bail_not_found!()
         };
         let flyer = EventFlyer::lookup(&state.db, event.id).await?;
+        let recipient =
+            MergeTags::preview_recipient::<HtmlError>(&state.db, user, query.recipient.as_deref()).await?;
+        MergeTags::for_event(&state.db, &event, &recipient).await?.apply(&mut event);
 
         #[derive(Template, WebTemplate)]
         #[template(path = "emails/event_confirmation.html")]
@@ -702,6 +1239,47 @@ mod edit {
         .into_response())
     }
 
+    /// Send the confirmation, as a recipient would get it, to the viewer only.
+    pub async fn test_confirmation_form(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>, Form(form): Form<TestSendForm>,
+    ) -> JsonResult<TestSendResponse> {
+        let Some(mut event) = Event::lookup_by_id(&state.db, id).await? else {
+            bail_not_found!()
+        };
+        let flyer = EventFlyer::lookup(&state.db, event.id).await?;
+        let recipient =
+            MergeTags::preview_recipient::<JsonError>(&state.db, user.clone(), form.recipient.as_deref())
+                .await?;
+        MergeTags::for_event(&state.db, &event, &recipient).await?.apply(&mut event);
+        let subject = event
+            .confirmation_subject
+            .clone()
+            .unwrap_or_else(|| format!("Confirmation for {}", event.title));
+
+        // The reservation link goes to the recipient's reservation if they have one.
+        let sessions = RsvpSession::list_for_user(&state.db, recipient.id, event.id).await?;
+        let token = match sessions.into_iter().find(|session| session.parent_session_id.is_none()) {
+            Some(session) => session.token,
+            None => "xxxxxxxx".into(),
+        };
+
+        let (email_id, email_token) = Email::create_test(&state.db, &user, None, Some(event.id)).await?;
+        #[derive(Template)]
+        #[template(path = "emails/event_confirmation.html")]
+        struct ConfirmationEmailHtml {
+            email_token: String,
+            event: Event,
+            token: String,
+            flyer: Option<EventFlyer>,
+        }
+        let body = ConfirmationEmailHtml { email_token, event, token, flyer }.render()?;
+
+        let from = &state.config.email.from;
+        let reply_to = state.config.email.contact_to.as_ref().unwrap_or(from);
+        let builder = state.mailer.builder().reply_to(reply_to.clone());
+        send_test(&state, &user, email_id, builder, &subject, body).await
+    }
+
     // Edit dayof page.
     pub async fn edit_dayof_page(
         user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
@@ -722,8 +1300,10 @@ mod edit {
             event: event.clone(),
             editor: Editor {
                 url: "/events/{id}/dayof/edit",
-                snapshot_prefix: "event/dayof",
+                snapshot_prefix: Revision::EVENT_DAYOF,
                 entity_id: Some(event.id),
+                revisions_url: Some(format!("/events/{}/dayof/revisions", event.id)),
+                merge_tags: MergeTags::EVENT_TAGS,
                 content: match (event.dayof_html, event.dayof_updated_at) {
                     (Some(html), Some(updated_at)) => Some(EditorContent { html, updated_at }),
                     _ => None,
@@ -747,13 +1327,34 @@ mod edit {
         error: Option<String>,
     }
     pub async fn edit_dayof_form(
-        State(state): State<SharedAppState>, Form(form): Form<EditDayofForm>,
+        user: User, State(state): State<SharedAppState>, Form(form): Form<EditDayofForm>,
     ) -> JsonResult<EditDayofResponse> {
         let Some(event) = Event::lookup_by_id(&state.db, form.id).await? else {
             bail_not_found!();
         };
 
-        let updated_at = Event::update_dayof(&state.db, event.id, form.subject, form.content).await?;
+        let before = json!({"subject": event.dayof_subject, "html": event.dayof_html});
+        let after = json!({"subject": form.subject, "html": form.content});
+        let updated_at =
+            Event::update_dayof(&state.db, event.id, form.subject.clone(), form.content.clone()).await?;
+        Revision::record(
+            &state.db,
+            Revision::EVENT_DAYOF,
+            event.id,
+            Some(&form.subject),
+            &form.content,
+            &user,
+        )
+        .await?;
+        AuditLog::record(
+            &state.db,
+            &user,
+            "event.dayof.edit",
+            AuditTarget::Event(event.id),
+            before,
+            after,
+        )
+        .await?;
 
         Ok(Json(EditDayofResponse {
             id: Some(event.id),
@@ -770,15 +1371,46 @@ mod edit {
         flyer: Option<EventFlyer>,
     }
     // Preview dayof page.
-    pub async fn preview_dayof_page(State(state): State<SharedAppState>, Path(id): Path<i64>) -> HtmlResult {
-        let Some(event) = Event::lookup_by_id(&state.db, id).await? else {
+    pub async fn preview_dayof_page(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+        Query(query): Query<PreviewQuery>,
+    ) -> HtmlResult {
+        let Some(mut event) = Event::lookup_by_id(&state.db, id).await? else {
             bail_not_found!()
         };
         let flyer = EventFlyer::lookup(&state.db, event.id).await?;
+        let recipient =
+            MergeTags::preview_recipient::<HtmlError>(&state.db, user, query.recipient.as_deref()).await?;
+        MergeTags::for_event(&state.db, &event, &recipient).await?.apply(&mut event);
 
         Ok(DayofEmailHtml { email_token: String::new(), event: event.clone(), flyer }.into_response())
     }
 
+    /// Send the day-of info, as a recipient would get it, to the viewer only.
+    pub async fn test_dayof_form(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>, Form(form): Form<TestSendForm>,
+    ) -> JsonResult<TestSendResponse> {
+        let Some(mut event) = Event::lookup_by_id(&state.db, id).await? else {
+            bail_not_found!()
+        };
+        let flyer = EventFlyer::lookup(&state.db, event.id).await?;
+        let recipient =
+            MergeTags::preview_recipient::<JsonError>(&state.db, user.clone(), form.recipient.as_deref())
+                .await?;
+        MergeTags::for_event(&state.db, &event, &recipient).await?.apply(&mut event);
+        let Some(subject) = event.dayof_subject.clone() else {
+            bail_bad_request!("Save the day-of info before sending a test.");
+        };
+
+        let (email_id, email_token) = Email::create_test(&state.db, &user, None, Some(event.id)).await?;
+        let body = DayofEmailHtml { email_token, event, flyer }.render()?;
+
+        let from = &state.config.email.from;
+        let reply_to = config().email.contact_to.as_ref().unwrap_or(from);
+        let builder = state.mailer.builder().reply_to(reply_to.clone());
+        send_test(&state, &user, email_id, builder, &subject, body).await
+    }
+
     /// Display the form to send a post.
     pub async fn send_dayof_page(
         user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
@@ -839,7 +1471,9 @@ mod edit {
         Ok(SendHtml { user: Some(user), event, list, ratelimit }.into_response())
     }
 
-    pub async fn send_dayof_form(State(state): State<SharedAppState>, Path(id): Path<i64>) -> HtmlResult {
+    pub async fn send_dayof_form(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+    ) -> HtmlResult {
         let Some(event) = Event::lookup_by_id(&state.db, id).await? else {
             bail_not_found!();
         };
@@ -850,12 +1484,20 @@ mod edit {
         let mut email_template = DayofEmailHtml { email_token: String::new(), event: event.clone(), flyer };
         let mut messages = vec![];
         let mut email_ids = vec![];
-        for Email { id, address, sent_at, token, .. } in emails {
+        for Email { id, user_id, address, sent_at, token, .. } in emails {
             if sent_at.is_some() {
                 continue;
             }
+            let Some(recipient) = User::lookup_by_id(&state.db, user_id).await? else {
+                Email::mark_error(&state.db, id, "recipient not found").await?;
+                continue;
+            };
 
             email_template.email_token = token;
+            email_template.event = event.clone();
+            MergeTags::for_event(&state.db, &event, &recipient)
+                .await?
+                .apply(&mut email_template.event);
 
             let from = &state.config.email.from;
             let reply_to = config().email.contact_to.as_ref().unwrap_or(from);
@@ -864,7 +1506,7 @@ mod edit {
                 .builder()
                 .to(address.parse().unwrap())
                 .reply_to(reply_to.clone())
-                .subject(event.dayof_subject.as_deref().expect("missing dayof_subject"))
+                .subject(email_template.event.dayof_subject.as_deref().expect("missing dayof_subject"))
                 .header(lettre::message::header::ContentType::TEXT_HTML)
                 .body(email_template.render()?)
                 .unwrap();
@@ -874,6 +1516,9 @@ mod edit {
         }
 
         event.mark_sent_dayof(&state.db).await?;
+        let after = json!({"recipients": messages.len()});
+        AuditLog::record(&state.db, &user, "event.dayof.send", AuditTarget::Event(event.id), (), after)
+            .await?;
 
         let email_ids = futures::stream::iter(email_ids);
         let results = state.mailer.send_batch(Arc::clone(&state), messages).await;
@@ -919,8 +1564,10 @@ mod edit {
             event: event.clone(),
             editor: Editor {
                 url: "/events/{id}/description/edit",
-                snapshot_prefix: "event/description",
+                snapshot_prefix: Revision::EVENT_DESCRIPTION,
                 entity_id: Some(event.id),
+                revisions_url: Some(format!("/events/{}/description/revisions", event.id)),
+                merge_tags: &[],
                 content: match (event.description_html, event.description_updated_at) {
                     (Some(html), Some(updated_at)) => Some(EditorContent { html, updated_at }),
                     _ => None,
@@ -943,13 +1590,26 @@ mod edit {
         error: Option<String>,
     }
     pub async fn edit_description_form(
-        State(state): State<SharedAppState>, Form(form): Form<EditDescriptionForm>,
+        user: User, State(state): State<SharedAppState>, Form(form): Form<EditDescriptionForm>,
     ) -> JsonResult<EditDescriptionResponse> {
         let Some(event) = Event::lookup_by_id(&state.db, form.id).await? else {
             bail_not_found!();
         };
 
-        let updated_at = Event::update_description(&state.db, event.id, form.content).await?;
+        let before = json!({"html": event.description_html});
+        let after = json!({"html": form.content});
+        let updated_at = Event::update_description(&state.db, event.id, form.content.clone()).await?;
+        Revision::record(&state.db, Revision::EVENT_DESCRIPTION, event.id, None, &form.content, &user)
+            .await?;
+        AuditLog::record(
+            &state.db,
+            &user,
+            "event.description.edit",
+            AuditTarget::Event(event.id),
+            before,
+            after,
+        )
+        .await?;
 
         Ok(Json(EditDescriptionResponse {
             id: Some(event.id),
@@ -958,12 +1618,41 @@ mod edit {
         }))
     }
 
-    /// View an event.
+    #[derive(serde::Deserialize)]
+    pub struct AttendeesQuery {
+        session: Option<i64>,
+    }
+
+    /// View an event's attendees, optionally narrowed to one session for check-in.
     pub async fn attendees_page(
         user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+        Query(query): Query<AttendeesQuery>,
     ) -> HtmlResult {
         let event = Event::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?;
-        let rsvps = Rsvp::list_for_admin_attendees(&state.db, event.id).await?;
+        let mut rsvps = Rsvp::list_for_admin_attendees(&state.db, event.id).await?;
+        let sessions = EventSession::list_for_event(&state.db, event.id).await?;
+
+        // Only attendees whose spot grants the session; manual attendees are admitted to all.
+        let (session, checkins) = match query.session {
+            Some(session_id) => {
+                let session = sessions.iter().find(|s| s.id == session_id).ok_or_else(not_found)?.clone();
+                rsvps.retain(|r| r.is_manual || session.grants(&sessions, r.spot_id));
+                let checkins = EventSession::list_checkins(&state.db, session.id).await?;
+                (Some(session), checkins)
+            }
+            None => (None, HashMap::default()),
+        };
+
+        // Editors manage staff grants, co-organizers get a link to the stats page
+        let can_edit = user.has_permission(Permission::EventsEdit);
+        let staff = match can_edit {
+            true => EventStaff::list_for_event(&state.db, event.id).await?,
+            false => vec![],
+        };
+        let show_stats = can_edit
+            || EventStaff::lookup_active(&state.db, &event, user.id)
+                .await?
+                .is_some_and(|s| s.is_organizer());
 
         #[derive(Template, WebTemplate)]
         #[template(path = "events/attendees.html")]
@@ -975,6 +1664,11 @@ mod edit {
             total_contributions: i64,
             show_notes: bool,
             rsvps: Vec<AdminAttendeesRsvp>,
+            sessions: Vec<EventSession>,
+            session: Option<EventSession>,
+            checkins: HashMap<i64, NaiveDateTime>,
+            staff: Vec<EventStaffMember>,
+            show_stats: bool,
         }
 
         let rsvp_count = rsvps.iter().filter(|r| !r.is_refunded()).count();
@@ -989,21 +1683,68 @@ mod edit {
             total_contributions,
             show_notes,
             rsvps,
+            sessions,
+            session,
+            checkins,
+            staff,
+            show_stats,
         }
         .into_response())
     }
 
+    #[derive(serde::Deserialize)]
+    pub struct GrantStaffForm {
+        email: String,
+        role: String,
+    }
+
+    /// Grant a user check-in or co-organizer access to a single event, creating the user if needed.
+    pub async fn grant_staff_form(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+        Form(form): Form<GrantStaffForm>,
+    ) -> HtmlResult {
+        let event = Event::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?;
+        if !EventStaff::is_valid_role(&form.role) {
+            bail_invalid!();
+        }
+
+        let info = CreateUser { email: form.email, first_name: None, last_name: None, phone: None };
+        let staff = User::get_or_create(&state.db, &info).await?;
+        EventStaff::grant(&state.db, event.id, staff.id, &form.role).await?;
+        let target = AuditTarget::Attendee { event_id: event.id, user_id: staff.id };
+        let after = json!({"email": staff.email, "role": form.role});
+        AuditLog::record(&state.db, &user, "event.staff.grant", target, (), after).await?;
+
+        Ok(Redirect::to(&format!("/events/{}/attendees", event.id)).into_response())
+    }
+
+    pub async fn revoke_staff(
+        user: User, State(state): State<SharedAppState>, Path(path): Path<AttendeePath>,
+    ) -> JsonResult<()> {
+        EventStaff::revoke(&state.db, path.id, path.user_id).await?;
+        let target = AuditTarget::Attendee { event_id: path.id, user_id: path.user_id };
+        AuditLog::record(&state.db, &user, "event.staff.revoke", target, (), ()).await?;
+        Ok(Json(()))
+    }
+
     /// Handle delete submission.
-    pub async fn delete_form(State(state): State<SharedAppState>, Path(id): Path<i64>) -> HtmlResult {
+    pub async fn delete_form(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+    ) -> HtmlResult {
         let event = Event::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?;
         Event::delete(&state.db, event.id, &event.slug).await?;
+        AuditLog::record(&state.db, &user, "event.delete", AuditTarget::Event(event.id), &event, ()).await?;
         Ok(Redirect::to("/events").into_response())
     }
 
     /// Handle duplicate submission.
-    pub async fn duplicate_form(State(state): State<SharedAppState>, Path(id): Path<i64>) -> HtmlResult {
+    pub async fn duplicate_form(
+        user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+    ) -> HtmlResult {
         let event = Event::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?;
         let new_id = Event::duplicate(&state.db, event.id).await?;
+        let after = json!({"duplicated_from": event.id});
+        AuditLog::record(&state.db, &user, "event.duplicate", AuditTarget::Event(new_id), (), after).await?;
         Ok(Redirect::to(&format!("/events/{new_id}/edit")).into_response())
     }
 
@@ -1020,7 +1761,7 @@ mod edit {
     }
 
     pub async fn set_checkin(
-        State(state): State<SharedAppState>, Path(path): Path<AttendeePath>,
+        user: User, State(state): State<SharedAppState>, Path(path): Path<AttendeePath>,
         Query(query): Query<CheckinQuery>,
     ) -> JsonResult<()> {
         let event = Event::lookup_by_id(&state.db, path.id).await?.ok_or_else(not_found)?;
@@ -1029,11 +1770,21 @@ mod edit {
         } else {
             Rsvp::set_checkin_at_for_event(&state.db, event.id, path.user_id).await?;
         }
+        let target = AuditTarget::Attendee { event_id: event.id, user_id: path.user_id };
+        AuditLog::record(
+            &state.db,
+            &user,
+            "attendee.checkin",
+            target,
+            (),
+            json!({"manual": query.manual}),
+        )
+        .await?;
         Ok(Json(()))
     }
 
     pub async fn clear_checkin(
-        State(state): State<SharedAppState>, Path(path): Path<AttendeePath>,
+        user: User, State(state): State<SharedAppState>, Path(path): Path<AttendeePath>,
         Query(query): Query<CheckinQuery>,
     ) -> JsonResult<()> {
         let event = Event::lookup_by_id(&state.db, path.id).await?.ok_or_else(not_found)?;
@@ -1042,11 +1793,80 @@ mod edit {
         } else {
             Rsvp::clear_checkin_at_for_event(&state.db, event.id, path.user_id).await?;
         }
+        let target = AuditTarget::Attendee { event_id: event.id, user_id: path.user_id };
+        AuditLog::record(
+            &state.db,
+            &user,
+            "attendee.checkin.clear",
+            target,
+            (),
+            json!({"manual": query.manual}),
+        )
+        .await?;
+        Ok(Json(()))
+    }
+
+    #[derive(serde::Deserialize)]
+    pub struct SessionAttendeePath {
+        id: i64,
+        session_id: i64,
+        user_id: i64,
+    }
+
+    /// Check an attendee in to one session of a multi-session event, if their spot grants it.
+    pub async fn set_session_checkin(
+        user: User, State(state): State<SharedAppState>, Path(path): Path<SessionAttendeePath>,
+    ) -> JsonResult<()> {
+        let sessions = EventSession::list_for_event(&state.db, path.id).await?;
+        let Some(session) = sessions.iter().find(|s| s.id == path.session_id) else {
+            bail_not_found!();
+        };
+        // Same as the attendees page: manual attendees are admitted to all sessions.
+        let rsvps = Rsvp::list_for_admin_attendees(&state.db, path.id).await?;
+        let admitted = rsvps
+            .iter()
+            .filter(|r| r.user_id == path.user_id)
+            .any(|r| r.is_manual || session.grants(&sessions, r.spot_id));
+        if !admitted {
+            bail_bad_request!("This attendee's spot doesn't include that session.");
+        }
+        EventSession::set_checkin_at(&state.db, path.session_id, path.user_id).await?;
+        let target = AuditTarget::Attendee { event_id: path.id, user_id: path.user_id };
+        AuditLog::record(
+            &state.db,
+            &user,
+            "attendee.session_checkin",
+            target,
+            (),
+            json!({"session_id": path.session_id}),
+        )
+        .await?;
+        Ok(Json(()))
+    }
+
+    pub async fn clear_session_checkin(
+        user: User, State(state): State<SharedAppState>, Path(path): Path<SessionAttendeePath>,
+    ) -> JsonResult<()> {
+        let sessions = EventSession::list_ids_for_event(&state.db, path.id).await?;
+        if !sessions.contains(&path.session_id) {
+            bail_not_found!();
+        }
+        EventSession::clear_checkin_at(&state.db, path.session_id, path.user_id).await?;
+        let target = AuditTarget::Attendee { event_id: path.id, user_id: path.user_id };
+        AuditLog::record(
+            &state.db,
+            &user,
+            "attendee.session_checkin.clear",
+            target,
+            (),
+            json!({"session_id": path.session_id}),
+        )
+        .await?;
         Ok(Json(()))
     }
 
     pub async fn delete_attendee(
-        State(state): State<SharedAppState>, Path(path): Path<AttendeePath>,
+        user: User, State(state): State<SharedAppState>, Path(path): Path<AttendeePath>,
         Query(query): Query<CheckinQuery>,
     ) -> JsonResult<()> {
         let event = Event::lookup_by_id(&state.db, path.id).await?.ok_or_else(not_found)?;
@@ -1055,11 +1875,14 @@ mod edit {
         } else {
             Rsvp::delete_for_event(&state.db, event.id, path.user_id).await?;
         }
+        let target = AuditTarget::Attendee { event_id: event.id, user_id: path.user_id };
+        AuditLog::record(&state.db, &user, "attendee.delete", target, json!({"manual": query.manual}), ())
+            .await?;
         Ok(Json(()))
     }
 
     pub async fn refund_attendee(
-        State(state): State<SharedAppState>, Path(path): Path<AttendeePath>,
+        user: User, State(state): State<SharedAppState>, Path(path): Path<AttendeePath>,
     ) -> JsonResult<()> {
         let event = Event::lookup_by_id(&state.db, path.id).await?.ok_or_else(not_found)?;
         let sessions = RsvpSession::list_for_user(&state.db, path.user_id, event.id).await?;
@@ -1077,6 +1900,10 @@ mod edit {
             }
         }
 
+        let target = AuditTarget::Attendee { event_id: event.id, user_id: path.user_id };
+        let before = json!({"sessions": sessions.iter().map(|s| (s.id, &s.status)).collect::<Vec<_>>()});
+        AuditLog::record(&state.db, &user, "attendee.refund", target, before, ()).await?;
+
         Ok(Json(()))
     }
 
@@ -1194,6 +2021,16 @@ mod edit {
         // Create manual RSVP
         let note = form.note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
         ManualRsvp::create(&state.db, event.id, user.id, admin.id, note.as_deref()).await?;
+        let target = AuditTarget::Attendee { event_id: event.id, user_id: user.id };
+        AuditLog::record(
+            &state.db,
+            &admin,
+            "attendee.add",
+            target,
+            (),
+            json!({"email": user.email, "note": note}),
+        )
+        .await?;
 
         Ok(Redirect::to(&format!("/events/{id}/attendees")).into_response())
     }
@@ -1207,11 +2044,20 @@ mod edit {
 
     /// Handle edit attendee form submission.
     pub async fn edit_attendee_form(
-        _admin: User, State(state): State<SharedAppState>, Path(path): Path<AttendeePath>,
+        admin: User, State(state): State<SharedAppState>, Path(path): Path<AttendeePath>,
         Query(query): Query<CheckinQuery>, Form(form): Form<EditAttendeeForm>,
     ) -> HtmlResult {
         let event = Event::lookup_by_id(&state.db, path.id).await?.ok_or_else(not_found)?;
         let attendee = User::lookup_by_id(&state.db, path.user_id).await?.ok_or_else(not_found)?;
+        let before = if query.manual {
+            ManualRsvp::lookup_for_edit(&state.db, event.id, path.user_id).await?
+        } else {
+            Rsvp::lookup_for_edit(&state.db, event.id, path.user_id).await?
+        }
+        .ok_or_else(not_found)?;
+        let before =
+            json!({"first_name": before.first_name, "last_name": before.last_name, "note": before.note});
+        let mut after = json!({"first_name": form.first_name, "last_name": form.last_name});
 
         // Email is read-only, so reuse the existing email and phone.
         attendee
@@ -1232,6 +2078,9 @@ mod edit {
         } else {
             Rsvp::update_note(&state.db, event.id, path.user_id, note.as_deref()).await?;
         }
+        after["note"] = json!(note);
+        let target = AuditTarget::Attendee { event_id: event.id, user_id: path.user_id };
+        AuditLog::record(&state.db, &admin, "attendee.edit", target, before, after).await?;
 
         Ok(Redirect::to(&format!("/events/{}/attendees", path.id)).into_response())
     }
@@ -1242,11 +2091,12 @@ mod rsvp {
 
     use super::*;
     use crate::app::events::rsvp::parse::ParsedAttendee;
-    use crate::db::list::List;
+    use crate::db::invite_link::InviteLink;
     use crate::db::manual_rsvp::ManualRsvp;
     use crate::db::rsvp::{AttendeeRsvp, ContributionRsvp, CreateRsvp, EventRsvp, Rsvp};
     use crate::db::rsvp_session::RsvpSession;
     use crate::db::user::CreateUser;
+    use crate::utils::merge_tags::MergeTags;
     use crate::utils::stripe;
 
     #[derive(Template, WebTemplate)]
@@ -1290,10 +2140,10 @@ mod rsvp {
 
         match event.guest_list_id {
             None => goto::selection_page(&state.db, &user, &session, &event).await,
-            Some(guest_list_id) => match session {
+            Some(_) => match session {
                 Some(session) => {
                     if let Some(user_id) = session.user_id
-                        && List::has_user_id(&state.db, guest_list_id, user_id).await?
+                        && EventGuestList::admits(&state.db, &event, user_id).await?
                     {
                         goto::selection_page(&state.db, &user, &Some(session), &event).await
                     } else {
@@ -1329,11 +2179,13 @@ mod rsvp {
         email: String,
     }
     pub async fn guestlist_form(
-        mut session: Option<RsvpSession>, State(state): State<SharedAppState>, Path(slug): Path<String>,
+        session: Option<RsvpSession>, State(state): State<SharedAppState>, Path(slug): Path<String>,
         Form(form): Form<GuestlistForm>,
     ) -> HtmlResult {
         let event = Event::lookup_by_slug(&state.db, &slug).await?.ok_or_else(not_found)?;
-        let guest_list_id = event.guest_list_id.ok_or_else(invalid)?;
+        if event.guest_list_id.is_none() {
+            bail_invalid!();
+        }
         if !event.registration_open() {
             return goto::error_registration_closed(&state.db, &state.stripe, &None).await;
         }
@@ -1342,14 +2194,8 @@ mod rsvp {
         let Some(user) = User::lookup_by_email(&state.db, &form.email).await? else {
             return goto::error_not_on_guestlist();
         };
-        let primary_user = CreateUser {
-            email: user.email.clone(),
-            first_name: user.first_name.clone(),
-            last_name: user.last_name.clone(),
-            phone: user.phone.clone(),
-        };
 
-        match List::has_user_id(&state.db, guest_list_id, user.id).await? {
+        match EventGuestList::admits(&state.db, &event, user.id).await? {
             true => {
                 tracing::info!(
                     "Guestlist check passed with event_id={} user_id={} user_email={:?}",
@@ -1357,27 +2203,115 @@ mod rsvp {
                     user.id,
                     user.email
                 );
+                admit(&state, session, &event, user).await
+            }
+            false => goto::error_not_on_guestlist(),
+        }
+    }
 
-                // Check for conflicts (no guests, so only a primary conflict is possible).
-                let exclude_ids: Vec<i64> = session.as_ref().map(|s| vec![s.id]).unwrap_or_default();
-                let other_users =
-                    Rsvp::list_reserved_users_for_event(&state.db, &event, &exclude_ids).await?;
-                if let Some(conflict) = validate::no_conflicts(&other_users, &primary_user, &[]) {
-                    let exclude = session.as_ref().map(|s| s.id);
-                    if let Some(resp) = resolve_conflict(&state, &event, exclude, conflict).await? {
-                        return Ok(resp);
-                    }
-                }
+    /// Continue to spot selection as a user who got past the guest list.
+    async fn admit(
+        state: &SharedAppState, mut session: Option<RsvpSession>, event: &Event, user: User,
+    ) -> HtmlResult {
+        let primary_user = CreateUser {
+            email: user.email.clone(),
+            first_name: user.first_name.clone(),
+            last_name: user.last_name.clone(),
+            phone: user.phone.clone(),
+        };
 
-                // Set user on session if already exists
-                if let Some(session) = session.as_mut() {
-                    session.set_user(&state.db, &user).await?;
-                }
+        // Check for conflicts (no guests, so only a primary conflict is possible).
+        let exclude_ids: Vec<i64> = session.as_ref().map(|s| vec![s.id]).unwrap_or_default();
+        let other_users = Rsvp::list_reserved_users_for_event(&state.db, event, &exclude_ids).await?;
+        if let Some(conflict) = validate::no_conflicts(&other_users, &primary_user, &[]) {
+            let exclude = session.as_ref().map(|s| s.id);
+            if let Some(resp) = resolve_conflict(state, event, exclude, conflict).await? {
+                return Ok(resp);
+            }
+        }
 
-                goto::selection_page(&state.db, &Some(user), &session, &event).await
+        // Set user on session if already exists
+        if let Some(session) = session.as_mut() {
+            session.set_user(&state.db, &user).await?;
+        }
+
+        goto::selection_page(&state.db, &Some(user), &session, event).await
+    }
+
+    #[derive(Template, WebTemplate)]
+    #[template(path = "events/rsvp_invite.html")]
+    struct InviteLinkHtml {
+        user: Option<User>,
+        slug: String,
+        token: String,
+        label: String,
+    }
+
+    // Display the page for redeeming a personal invite link
+    pub async fn invite_link_page(
+        user: Option<User>, State(state): State<SharedAppState>, Path((slug, token)): Path<(String, String)>,
+    ) -> HtmlResult {
+        let event = Event::lookup_by_slug(&state.db, &slug).await?.ok_or_else(not_found)?;
+        let link = InviteLink::lookup_by_token(&state.db, event.id, &token)
+            .await?
+            .ok_or_else(not_found)?;
+        if !event.registration_open() {
+            return goto::error_registration_closed(&state.db, &state.stripe, &None).await;
+        }
+        if link.is_expired() {
+            return goto::error_invite_link_unusable();
+        }
+
+        Ok(InviteLinkHtml { user, slug, token, label: link.label }.into_response())
+    }
+
+    // Handle redeeming a personal invite link, creating the user if needed
+    #[derive(Debug, serde::Deserialize)]
+    pub struct InviteLinkForm {
+        email: String,
+        first_name: String,
+        last_name: String,
+    }
+    pub async fn invite_link_form(
+        session: Option<RsvpSession>, State(state): State<SharedAppState>,
+        Path((slug, token)): Path<(String, String)>, Form(form): Form<InviteLinkForm>,
+    ) -> HtmlResult {
+        let event = Event::lookup_by_slug(&state.db, &slug).await?.ok_or_else(not_found)?;
+        let link = InviteLink::lookup_by_token(&state.db, event.id, &token)
+            .await?
+            .ok_or_else(not_found)?;
+        if !event.registration_open() {
+            return goto::error_registration_closed(&state.db, &state.stripe, &None).await;
+        }
+
+        let email = form.email.trim();
+        if email.parse::<lettre::message::Mailbox>().is_err() {
+            bail_bad_request!("Invalid email address.");
+        }
+        let user = match User::lookup_by_email(&state.db, email).await? {
+            Some(user) => user,
+            None => {
+                let create = CreateUser {
+                    email: email.to_string(),
+                    first_name: Some(form.first_name.trim().to_string()).filter(|s| !s.is_empty()),
+                    last_name: Some(form.last_name.trim().to_string()).filter(|s| !s.is_empty()),
+                    phone: None,
+                };
+                User::create(&state.db, &create).await?
             }
-            false => goto::error_not_on_guestlist(),
+        };
+
+        if !link.redeem(&state.db, user.id).await? {
+            return goto::error_invite_link_unusable();
         }
+        tracing::info!(
+            "Invite link redeemed with event_id={} link_id={} user_id={} user_email={:?}",
+            event.id,
+            link.id,
+            user.id,
+            user.email
+        );
+        admit(&state, session, &event, user).await
     }
 
     // Display the "Choose a contribution" page
@@ -1402,7 +2336,8 @@ mod rsvp {
         let all_rsvps = Rsvp::list_reserved_for_event(&state.db, &event, &session).await?;
         let user_rsvps = Rsvp::list_user_reserved_for_event(&state.db, &event, session.user_id).await?;
         let manual_count = ManualRsvp::count_for_event(&state.db, event.id).await?;
-        let limits = event.compute_limits(&spots, &all_rsvps, &user_rsvps, manual_count);
+        let sessions = EventSession::list_for_event(&state.db, event.id).await?;
+        let limits = event.compute_limits(&spots, &sessions, &all_rsvps, &user_rsvps, manual_count);
         if limits.total_limit == 0 {
             return goto::error_at_capacity(&state.db, &state.stripe, &Some(session)).await;
         }
@@ -1421,16 +2356,24 @@ mod rsvp {
         struct SelectionHtml {
             event: Event,
             spots: Vec<Spot>,
+            sessions: Vec<EventSession>,
             our_qtys: HashMap<i64, usize>,
             our_contributions: HashMap<i64, i64>,
             limits: EventLimits,
             stats: VariableSpotStats,
             parent_token: Option<String>,
         }
-        Ok(
-            SelectionHtml { event, spots, our_qtys, our_contributions, limits, stats, parent_token }
-                .into_response(),
-        )
+        Ok(SelectionHtml {
+            event,
+            spots,
+            sessions,
+            our_qtys,
+            our_contributions,
+            limits,
+            stats,
+            parent_token,
+        }
+        .into_response())
     }
 
     // Handle submission of the "Choose a contribution" form
@@ -1459,7 +2402,8 @@ mod rsvp {
         let all_rsvps = Rsvp::list_reserved_for_event(&state.db, &event, &session).await?;
         let user_rsvps = Rsvp::list_user_reserved_for_event(&state.db, &event, session.user_id).await?;
         let manual_count = ManualRsvp::count_for_event(&state.db, event.id).await?;
-        let limits = event.compute_limits(&spots, &all_rsvps, &user_rsvps, manual_count);
+        let sessions = EventSession::list_for_event(&state.db, event.id).await?;
+        let limits = event.compute_limits(&spots, &sessions, &all_rsvps, &user_rsvps, manual_count);
         if limits.total_limit == 0 {
             return goto::error_at_capacity(&state.db, &state.stripe, &Some(session)).await;
         }
@@ -1599,7 +2543,8 @@ mod rsvp {
         let all_rsvps = Rsvp::list_reserved_for_event(&state.db, &event, &our_session).await?;
         let user_rsvps = Rsvp::list_user_reserved_for_event(&state.db, &event, our_session.user_id).await?;
         let manual_count = ManualRsvp::count_for_event(&state.db, event.id).await?;
-        let limits = event.compute_limits(&spots, &all_rsvps, &user_rsvps, manual_count);
+        let sessions = EventSession::list_for_event(&state.db, event.id).await?;
+        let limits = event.compute_limits(&spots, &sessions, &all_rsvps, &user_rsvps, manual_count);
         if limits.total_limit == 0 {
             return goto::error_at_capacity(&state.db, &state.stripe, &Some(our_session)).await;
         }
@@ -1736,9 +2681,12 @@ mod rsvp {
                 flyer: Option<EventFlyer>,
             }
 
+            let mut email_event = event.clone();
+            MergeTags::for_event(&state.db, &event, &user).await?.apply(&mut email_event);
+
             let from = &state.config.email.from;
             let reply_to = state.config.email.contact_to.as_ref().unwrap_or(from);
-            let subject = event
+            let subject = email_event
                 .confirmation_subject
                 .clone()
                 .unwrap_or_else(|| format!("Confirmation for {}", event.title));
@@ -1752,7 +2700,7 @@ mod rsvp {
                 .body(
                     ConfirmationEmailHtml {
                         email_token: email.token,
-                        event: event.clone(),
+                        event: email_event.clone(),
                         token: session.token.clone(),
                         flyer,
                     }
@@ -1860,6 +2808,11 @@ mod rsvp {
                 flyer: Option<EventFlyer>,
             }
 
+            let mut email_event = event.clone();
+            MergeTags::for_event(&state.db, &event, &session_user)
+                .await?
+                .apply(&mut email_event);
+
             let from = &state.config.email.from;
             let reply_to = state.config.email.contact_to.as_ref().unwrap_or(from);
             let message = state
@@ -1868,7 +2821,7 @@ mod rsvp {
                 .to(session_user.email.parse().unwrap())
                 .reply_to(reply_to.clone())
                 .subject(
-                    event
+                    email_event
                         .confirmation_subject
                         .clone()
                         .unwrap_or_else(|| format!("Confirmation for {}", event.title)),
@@ -1877,7 +2830,7 @@ mod rsvp {
                 .body(
                     ConfirmationEmailHtml {
                         email_token: email.token,
-                        event: event.clone(),
+                        event: email_event.clone(),
                         token: session.token.clone(),
                         flyer,
                     }
@@ -1923,12 +2876,12 @@ mod rsvp {
                     .builder()
                     .to(session_user.email.parse().unwrap())
                     .reply_to(reply_to.clone())
-                    .subject(event.dayof_subject.as_deref().expect("missing dayof_subject"))
+                    .subject(email_event.dayof_subject.as_deref().expect("missing dayof_subject"))
                     .header(lettre::message::header::ContentType::TEXT_HTML)
                     .body(
                         DayofEmailHtml {
                             email_token: dayof_email.token,
-                            event: event.clone(),
+                            event: email_event.clone(),
                             flyer: dayof_flyer,
                         }
                         .render()?,
@@ -1968,7 +2921,8 @@ mod rsvp {
             let all_rsvps = Rsvp::list_all_reserved_for_event(&state.db, &event).await?;
             let user_rsvps = Rsvp::list_user_reserved_for_event(&state.db, &event, session.user_id).await?;
             let manual_count = ManualRsvp::count_for_event(&state.db, event.id).await?;
-            let limits = event.compute_limits(&spots, &all_rsvps, &user_rsvps, manual_count);
+            let sessions = EventSession::list_for_event(&state.db, event.id).await?;
+            let limits = event.compute_limits(&spots, &sessions, &all_rsvps, &user_rsvps, manual_count);
             limits.total_limit > 0
         } else {
             false
@@ -2023,7 +2977,8 @@ mod rsvp {
         let all_rsvps = Rsvp::list_all_reserved_for_event(&state.db, &event).await?;
         let user_rsvps = Rsvp::list_user_reserved_for_event(&state.db, &event, parent.user_id).await?;
         let manual_count = ManualRsvp::count_for_event(&state.db, event.id).await?;
-        let limits = event.compute_limits(&spots, &all_rsvps, &user_rsvps, manual_count);
+        let sessions = EventSession::list_for_event(&state.db, event.id).await?;
+        let limits = event.compute_limits(&spots, &sessions, &all_rsvps, &user_rsvps, manual_count);
         if limits.total_limit == 0 {
             return goto::error_at_capacity(&state.db, &state.stripe, &None).await;
         }
@@ -2188,6 +3143,10 @@ mod rsvp {
             let error = ErrorHtml { user: None, message: "Sorry, you're not on the list.".into() };
             Ok(error.into_response())
         }
+        pub fn error_invite_link_unusable() -> HtmlResult {
+            let error = ErrorHtml { user: None, message: "Sorry, this invite link has expired or been used up.".into() };
+            Ok(error.into_response())
+        }
         pub async fn error_at_capacity(db: &Db, stripe: &Stripe, session: &Option<RsvpSession>) -> HtmlResult {
             if let Some(session) = session {
                 session.delete(db, stripe).await?;
@@ -2286,7 +3245,7 @@ mod rsvp {
         use super::*;
         use crate::db::rsvp::{AttendeeRsvp, UserRsvp};
 
-        /// Returns true if rsvps satisfy total and per-spot limits.
+        /// Returns true if rsvps satisfy total, per-spot, and per-session limits.
         pub fn within_limits(limits: &EventLimits, rsvps: &[EventRsvp]) -> bool {
             let total_qty = rsvps.len() as i64;
             let mut spot_qtys: HashMap<i64, i64> = HashMap::default();
@@ -2297,11 +3256,18 @@ mod rsvp {
             if total_qty > limits.total_limit {
                 return false;
             }
-            for (spot_id, spot_qty) in spot_qtys {
+            for (&spot_id, &spot_qty) in &spot_qtys {
                 if spot_qty > *limits.spot_limits.get(&spot_id).unwrap_or(&0) {
                     return false;
                 }
             }
+            for session_limit in &limits.session_limits {
+                let session_qty: i64 =
+                    session_limit.spot_ids.iter().map(|id| spot_qtys.get(id).unwrap_or(&0)).sum();
+                if session_qty > session_limit.limit {
+                    return false;
+                }
+            }
 
             true
         }
@@ -0,0 +1,201 @@
+use crate::db::event::Event;
+use crate::db::event_flyer::EventFlyer;
+use crate::db::post::Post;
+use crate::prelude::*;
+use crate::utils::merge_tags::MergeTags;
+
+/// Add all `feeds` routes to the router.
+pub fn add_routes(router: AppRouter) -> AppRouter {
+    router.public_routes(|r| {
+        r.route("/posts.xml", get(posts_rss))
+            .route("/posts.atom", get(posts_atom))
+            .route("/events.xml", get(events_rss))
+            .route("/events.atom", get(events_atom))
+    })
+}
+
+/// A feed, rendered as RSS by `feeds/rss.xml` or as Atom by `feeds/atom.xml`.
+struct Feed {
+    title: String,
+    description: String,
+    /// Absolute url of the feed itself.
+    url: String,
+    site_url: String,
+    updated: NaiveDateTime,
+    entries: Vec<FeedEntry>,
+}
+
+struct FeedEntry {
+    title: String,
+    /// Absolute url, which is also the entry's id.
+    url: String,
+    published: NaiveDateTime,
+    updated: NaiveDateTime,
+    author: Option<String>,
+    /// Full content, with absolute links so it works in readers.
+    content_html: String,
+    enclosure: Option<FeedEnclosure>,
+}
+
+struct FeedEnclosure {
+    url: String,
+    length: u64,
+    content_type: &'static str,
+}
+
+#[derive(Template)]
+#[template(path = "feeds/rss.xml")]
+struct RssXml {
+    feed: Feed,
+}
+
+#[derive(Template)]
+#[template(path = "feeds/atom.xml")]
+struct AtomXml {
+    feed: Feed,
+}
+
+async fn posts_rss(State(state): State<SharedAppState>) -> HtmlResult {
+    let feed = posts_feed(&state, "posts.xml").await?;
+    Ok((
+        [(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")],
+        RssXml { feed }.render()?,
+    )
+        .into_response())
+}
+
+async fn posts_atom(State(state): State<SharedAppState>) -> HtmlResult {
+    let feed = posts_feed(&state, "posts.atom").await?;
+    Ok((
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        AtomXml { feed }.render()?,
+    )
+        .into_response())
+}
+
+async fn events_rss(State(state): State<SharedAppState>) -> HtmlResult {
+    let feed = events_feed(&state, "events.xml").await?;
+    Ok((
+        [(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")],
+        RssXml { feed }.render()?,
+    )
+        .into_response())
+}
+
+async fn events_atom(State(state): State<SharedAppState>) -> HtmlResult {
+    let feed = events_feed(&state, "events.atom").await?;
+    Ok((
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        AtomXml { feed }.render()?,
+    )
+        .into_response())
+}
+
+/// Published posts newest first, in full.
+async fn posts_feed(state: &SharedAppState, path: &str) -> Result<Feed> {
+    let app_url = &state.config.app.url;
+    let tags = MergeTags::empty(MergeTags::USER_TAGS);
+    let entries: Vec<_> = Post::list_published(&state.db)
+        .await?
+        .into_iter()
+        .map(|post| FeedEntry {
+            url: format!("{app_url}/p/{}", post.slug),
+            title: tags.expand_text(&post.title),
+            published: post.published_at.unwrap_or(post.created_at),
+            updated: post.updated_at,
+            author: Some(post.author).filter(|author| !author.is_empty()),
+            content_html: absolute_links(&tags.expand_html(&post.content), app_url),
+            enclosure: None,
+        })
+        .collect();
+
+    Ok(Feed {
+        title: "light and sound design - posts".into(),
+        description: "Posts from light and sound design".into(),
+        url: format!("{app_url}/{path}"),
+        site_url: app_url.clone(),
+        updated: entries
+            .iter()
+            .map(|entry| entry.updated)
+            .max()
+            .unwrap_or_else(|| Utc::now().naive_utc()),
+        entries,
+    })
+}
+
+/// Upcoming events soonest first, leaving out unlisted ones, with their flyers as enclosures.
+async fn events_feed(state: &SharedAppState, path: &str) -> Result<Feed> {
+    let app_url = &state.config.app.url;
+    let mut entries = vec![];
+    for event in Event::list_upcoming(&state.db).await? {
+        let url = match (event.is_external(), &event.url) {
+            (true, Some(url)) => url.clone(),
+            _ => format!("{app_url}/e/{}", event.slug),
+        };
+
+        let mut content_html = String::new();
+        let when = filters::format_datetime(&event.start, "%A %B %-d, %-I:%M%p")?;
+        let _ = write!(content_html, "<p><time>{when}</time></p>");
+        let enclosure = match EventFlyer::enclosure(&state.db, event.id).await? {
+            Some((version, length)) => {
+                // Pinned to JPEG, so the enclosure's type and length hold whatever the reader accepts.
+                let src = format!("{app_url}/e/{}/flyer?v={version}&size=md&format=jpeg", event.slug);
+                let _ = write!(
+                    content_html,
+                    r#"<p><img src="{}" alt="flyer" /></p>"#,
+                    src.replace('&', "&amp;")
+                );
+                Some(FeedEnclosure { url: src, length, content_type: EventFlyer::CONTENT_TYPE })
+            }
+            None => None,
+        };
+        if let Some(description) = &event.description_html {
+            content_html.push_str(&absolute_links(description, app_url));
+        }
+
+        entries.push(FeedEntry {
+            title: event.title,
+            url,
+            published: event.created_at,
+            updated: event.updated_at,
+            author: None,
+            content_html,
+            enclosure,
+        });
+    }
+
+    Ok(Feed {
+        title: "light and sound design - events".into(),
+        description: "Upcoming events at light and sound design".into(),
+        url: format!("{app_url}/{path}"),
+        site_url: app_url.clone(),
+        updated: entries
+            .iter()
+            .map(|entry| entry.updated)
+            .max()
+            .unwrap_or_else(|| Utc::now().naive_utc()),
+        entries,
+    })
+}
+
+/// Root-relative links and images only work on the site itself, so point them at it.
+fn absolute_links(html: &str, app_url: &str) -> String {
+    let mut html = html.to_string();
+    for attr in [r#"href="/"#, r#"src="/"#] {
+        let mut out = String::with_capacity(html.len());
+        let mut rest = html.as_str();
+        while let Some(i) = rest.find(attr) {
+            let (head, tail) = rest.split_at(i + attr.len() - 1);
+            out.push_str(head);
+            // Leave protocol-relative urls like `//cdn.example.com` alone.
+            if !tail.starts_with("//") {
+                out.push_str(app_url);
+            }
+            out.push('/');
+            rest = &tail[1..];
+        }
+        out.push_str(rest);
+        html = out;
+    }
+    html
+}
@@ -1,4 +1,5 @@
 use crate::db::event::Event;
+use crate::db::event_staff::EventStaff;
 use crate::prelude::*;
 
 /// Add all `home` routes to the router.
@@ -9,8 +10,7 @@ pub fn add_routes(router: AppRouter) -> AppRouter {
                 .route("/past", get(past_page))
                 .route("/sublet", get(sublet_page))
         })
-        // TODO: Rethink roles, not WRITER. Template out buttons based on role.
-        .restricted_routes(User::WRITER, |r| r.route("/dashboard", get(dashboard_page)))
+        .staff_routes(|r| r.route("/dashboard", get(dashboard_page)))
 }
 
 #[derive(Template, WebTemplate)]
@@ -25,6 +25,7 @@ struct HomeHtml {
 #[template(path = "dashboard.html")]
 struct DashboardHtml {
     user: Option<User>,
+    staff_events: Vec<(EventStaff, Event)>,
 }
 
 /// Display the front page.
@@ -36,8 +37,10 @@ async fn past_page(user: Option<User>, State(state): State<SharedAppState>) -> H
     Ok(HomeHtml { user, events: Event::list_past(&state.db).await?, past: true }.into_response())
 }
 
-async fn dashboard_page(user: User) -> HtmlResult {
-    Ok(DashboardHtml { user: Some(user) }.into_response())
+/// Display links to everything the user can manage, including events they are staffing.
+async fn dashboard_page(user: User, State(state): State<SharedAppState>) -> HtmlResult {
+    let staff_events = EventStaff::list_active_for_user(&state.db, user.id).await?;
+    Ok(DashboardHtml { user: Some(user), staff_events }.into_response())
 }
 
 #[derive(Template, WebTemplate)]
@@ -2,18 +2,22 @@ use std::net::SocketAddr;
 
 use axum::extract::ConnectInfo;
 use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
 
-use crate::db::list::{List, UpdateList};
+use crate::db::audit_log::{AuditLog, AuditTarget};
+use crate::db::list::{List, ListSignup, ListWithCount, UpdateList};
+use crate::db::user::CreateUser;
 use crate::prelude::*;
 
 /// Add all `lists` routes to the router.
 #[rustfmt::skip]
 pub fn add_routes(router: AppRouter) -> AppRouter {
     router.public_routes(|r| {
-        r.route("/newsletter", get(newsletter_signup_page))
-         .route("/lists/{id}/signup", get(signup_page).post(signup_form))
+        r.route("/newsletter", get(directory_page).post(directory_form))
+         .route("/lists/{slug}/signup", get(signup_page).post(signup_form))
+         .route("/lists/confirm", get(confirm_page).post(confirm_form))
     })
-    .restricted_routes(User::ADMIN, |r| {
+    .restricted_routes(Permission::ListsEdit, |r| {
         r.route("/lists", get(list_lists_page))
          .route("/lists/new", get(create_list_page))
          .route("/lists/{id}", get(edit_list_page).post(edit_list_form))
@@ -31,13 +35,13 @@ struct ListEditHtml {
 
 /// Display a list of all lists
 async fn list_lists_page(user: User, State(state): State<SharedAppState>) -> HtmlResult {
-    let lists = List::list(&state.db).await?;
+    let lists = List::list_with_counts(&state.db).await?;
 
     #[derive(Template, WebTemplate)]
     #[template(path = "lists/list.html")]
     struct Html {
         user: Option<User>,
-        lists: Vec<List>,
+        lists: Vec<ListWithCount>,
     }
     Ok(Html { user: Some(user), lists }.into_response())
 }
@@ -59,6 +63,12 @@ async fn create_list_page(user: User) -> HtmlResult {
         description: "".into(),
         created_at: Utc::now().naive_utc(),
         updated_at: Utc::now().naive_utc(),
+        slug: "".into(),
+        public: false,
+        signup_title: None,
+        signup_description: None,
+        confirmation_message: None,
+        tracking: true,
     };
 
     Ok(ListEditHtml { user: Some(user), list, members: vec![] }.into_response())
@@ -68,6 +78,20 @@ async fn create_list_page(user: User) -> HtmlResult {
 async fn edit_list_form(
     user: User, State(state): State<SharedAppState>, Form(form): Form<UpdateList>,
 ) -> HtmlResult {
+    let before = match form.id {
+        Some(id) => Some(List::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?),
+        None => None,
+    };
+
+    // Slugs name the public signup page, so they follow the same rules as event slugs.
+    if form.slug.is_empty() || !form.slug.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        bail_bad_request!("Slug can only contain letters, numbers, and dashes.");
+    }
+    if let Some(existing) = List::lookup_by_slug(&state.db, &form.slug).await?
+        && Some(existing.id) != form.id
+    {
+        bail_bad_request!("A list with that slug already exists.");
+    }
     let id = match form.id {
         Some(id) => {
             List::update(&state.db, id, &form).await?;
@@ -110,35 +134,113 @@ async fn edit_list_form(
         List::add_members(&state.db, id, &email_refs).await?;
     }
 
+    let after = json!({
+        "name": form.name,
+        "description": form.description,
+        "slug": form.slug,
+        "public": form.public,
+        "tracking": form.tracking,
+        "added": emails,
+    });
+    let before = before.map(|list| {
+        json!({
+            "name": list.name,
+            "description": list.description,
+            "slug": list.slug,
+            "public": list.public,
+            "tracking": list.tracking,
+        })
+    });
+    let action = if before.is_some() { "list.edit" } else { "list.create" };
+    AuditLog::record(&state.db, &user, action, AuditTarget::List(id), before, after).await?;
+
     Ok(Redirect::to(&format!("/lists/{id}")).into_response())
 }
 
 async fn remove_list_member(
-    State(state): State<SharedAppState>, Path((id, user_id)): Path<(i64, i64)>,
+    user: User, State(state): State<SharedAppState>, Path((id, user_id)): Path<(i64, i64)>,
 ) -> JsonResult<()> {
     List::remove_member(&state.db, id, user_id).await?;
+    let before = json!({"user_id": user_id});
+    AuditLog::record(&state.db, &user, "list.member.remove", AuditTarget::List(id), before, ()).await?;
     Ok(Json(()))
 }
 
-/// Display the newsletter signup page.
-// XXX: Hard coded to list with id=1.
-pub async fn newsletter_signup_page(user: Option<User>, State(state): State<SharedAppState>) -> HtmlResult {
-    signup_page(user, State(state), Path(1)).await
+/// Display the directory of public lists, or the signup page if there's only one.
+async fn directory_page(user: Option<User>, State(state): State<SharedAppState>) -> HtmlResult {
+    let lists = List::list_public(&state.db).await?;
+    match lists.as_slice() {
+        [] => bail_not_found!(),
+        [list] => return Ok(Redirect::to(&format!("/lists/{}/signup", list.slug)).into_response()),
+        _ => {}
+    }
+
+    #[derive(Template, WebTemplate)]
+    #[template(path = "lists/directory.html")]
+    struct Html {
+        user: Option<User>,
+        lists: Vec<List>,
+        turnstile_site_key: String,
+    }
+    Ok(Html {
+        user,
+        lists,
+        turnstile_site_key: state.config.cloudflare.turnstile_site_key.clone(),
+    }
+    .into_response())
 }
 
-/// Display the list signup page.
-async fn signup_page(
-    user: Option<User>, State(state): State<SharedAppState>, Path(list_id): Path<i64>,
+/// Process the directory form, signing up for every ticked list.
+///
+/// Checkboxes repeat the `list_id` key, so the form is read as raw pairs.
+async fn directory_form(
+    user: Option<User>, State(state): State<SharedAppState>, ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Form(pairs): Form<Vec<(String, String)>>,
 ) -> HtmlResult {
-    // XXX: Hard code only allow id 1 to be signed up to.
-    // A flag should be added to List whether it's public or not, and what the signup page looks like.
-    if list_id != 1 {
-        bail_unauthorized!()
+    let mut email = None;
+    let mut turnstile_token = None;
+    let mut list_ids = vec![];
+    for (key, value) in pairs {
+        match key.as_str() {
+            "email" => email = Some(value.parse::<Mailbox>().map_err(|_| invalid())?),
+            "cf-turnstile-response" => turnstile_token = Some(value),
+            "list_id" => list_ids.push(value.parse::<i64>().map_err(|_| invalid())?),
+            _ => {}
+        }
+    }
+    let (Some(email), Some(turnstile_token)) = (email, turnstile_token) else {
+        bail_invalid!();
+    };
+    if list_ids.is_empty() {
+        bail_bad_request!("Pick at least one list to sign up for.");
+    }
+
+    let public = List::list_public(&state.db).await?;
+    let lists: Vec<List> = public.into_iter().filter(|l| list_ids.contains(&l.id)).collect();
+    if lists.len() != list_ids.len() {
+        bail_invalid!();
     }
 
-    let Some(list) = List::lookup_by_id(&state.db, list_id).await? else {
+    subscribe(user, &state, client, &turnstile_token, &email, lists).await
+}
+
+/// Display the list signup page.
+async fn signup_page(
+    user: Option<User>, State(state): State<SharedAppState>, Path(slug): Path<String>,
+) -> HtmlResult {
+    let Some(list) = List::lookup_by_slug(&state.db, &slug).await? else {
+        // Signup links used to have the list id in place of the slug.
+        if let Ok(id) = slug.parse()
+            && let Some(list) = List::lookup_by_id(&state.db, id).await?
+            && list.public
+        {
+            return Ok(Redirect::permanent(&format!("/lists/{}/signup", list.slug)).into_response());
+        }
         bail_not_found!();
     };
+    if !list.public {
+        bail_not_found!();
+    }
 
     #[derive(Template, WebTemplate)]
     #[template(path = "lists/signup.html")]
@@ -158,41 +260,147 @@ async fn signup_page(
 /// Process the list signup form.
 async fn signup_form(
     user: Option<User>, State(state): State<SharedAppState>, ConnectInfo(client): ConnectInfo<SocketAddr>,
-    Form(form): Form<NewsletterForm>,
+    Path(slug): Path<String>, Form(form): Form<SignupForm>,
+) -> HtmlResult {
+    let Some(list) = List::lookup_by_slug(&state.db, &slug).await? else {
+        bail_not_found!();
+    };
+    if !list.public {
+        bail_not_found!();
+    }
+
+    subscribe(user, &state, client, &form.turnstile_token, &form.email, vec![list]).await
+}
+#[derive(serde::Deserialize)]
+struct SignupForm {
+    email: Mailbox,
+    #[serde(rename = "cf-turnstile-response")]
+    turnstile_token: String,
+}
+
+/// Email a confirmation link for public lists after checking the captcha.
+///
+/// Nobody is added until the link is followed, so a third party can't be signed up.
+async fn subscribe(
+    user: Option<User>, state: &SharedAppState, client: SocketAddr, turnstile_token: &str, email: &Mailbox,
+    lists: Vec<List>,
 ) -> HtmlResult {
-    if !state.cloudflare.validate_turnstile(client.ip(), &form.turnstile_token).await? {
+    if !state.cloudflare.validate_turnstile(client.ip(), turnstile_token).await? {
         bail_invalid!();
     }
 
-    // XXX: Hard code only allow id 1 to be signed up to.
-    // A flag should be added to List whether it's public or not, and what the signup page looks like.
-    if form.list_id != 1 {
-        bail_unauthorized!()
+    // Skip lists the email is already on
+    let mut list_ids = vec![];
+    for list in &lists {
+        if !List::has_email(&state.db, list.id, email.email.as_ref()).await? {
+            list_ids.push(list.id);
+        }
     }
 
-    let Some(list) = List::lookup_by_id(&state.db, form.list_id).await? else {
-        bail_not_found!();
-    };
+    if !list_ids.is_empty() {
+        let info = CreateUser {
+            email: email.email.to_string(),
+            first_name: None,
+            last_name: None,
+            phone: None,
+        };
+        let member = User::get_or_create(&state.db, &info).await?;
+        let token = ListSignup::create(&state.db, member.id, &list_ids).await?;
+        let (email_id, email_token) = Email::create_list_signup(&state.db, &member).await?;
+        let confirm_url = format!("{}/lists/confirm?token={token}", state.config.app.url);
+
+        #[derive(Template)]
+        #[template(path = "emails/list_signup.html")]
+        struct ListSignupHtml<'a> {
+            email_token: String,
+            confirm_url: String,
+            lists: Vec<&'a List>,
+        }
+        let pending = lists.iter().filter(|l| list_ids.contains(&l.id)).collect();
+
+        let msg = state
+            .mailer
+            .builder()
+            .header(ContentType::TEXT_HTML)
+            .to(email.clone())
+            .subject(format!("Confirm your signup to {}", state.config.app.domain))
+            .body(ListSignupHtml { email_token, confirm_url, lists: pending }.render()?)?;
 
-    // No-op if email is already on the list
-    if !List::has_email(&state.db, form.list_id, form.email.email.as_ref()).await? {
-        List::add_members(&state.db, list.id, &[form.email.email.as_ref()]).await?;
+        match state.mailer.send(&msg).await {
+            Ok(_) => Email::mark_sent(&state.db, email_id).await?,
+            Err(e) => {
+                Email::mark_error(&state.db, email_id, e.message()).await?;
+                return Err(e.into());
+            }
+        }
     }
 
     // Always render the same response, to avoid leaking whether emails were on the list.
     #[derive(Template, WebTemplate)]
-    #[template(path = "lists/confirmation.html")]
-    struct SuccessHtml {
+    #[template(path = "lists/check_email.html")]
+    struct CheckEmailHtml {
         user: Option<User>,
-        list: List,
         email: String,
     }
-    Ok(SuccessHtml { user, list, email: state.config.email.from.email.to_string() }.into_response())
+    Ok(CheckEmailHtml { user, email: email.email.to_string() }.into_response())
 }
+
 #[derive(serde::Deserialize)]
-struct NewsletterForm {
-    list_id: i64,
-    email: Mailbox,
-    #[serde(rename = "cf-turnstile-response")]
-    turnstile_token: String,
+struct ConfirmQuery {
+    token: String,
+}
+
+/// Display the button to confirm a signup. Confirming takes a POST so link scanners can't do it.
+async fn confirm_page(
+    user: Option<User>, State(state): State<SharedAppState>, Query(query): Query<ConfirmQuery>,
+) -> HtmlResult {
+    let lists = ListSignup::lookup_lists(&state.db, &query.token).await?;
+    if lists.is_empty() {
+        return Ok(ErrorHtml {
+            user,
+            title: "Link expired".into(),
+            message: "This confirmation link has expired or was already used. Please sign up again.".into(),
+            context: None,
+            backtrace: None,
+            contact_email: None,
+        }
+        .into_response());
+    }
+
+    #[derive(Template, WebTemplate)]
+    #[template(path = "lists/confirm.html")]
+    struct Html {
+        user: Option<User>,
+        lists: Vec<List>,
+        token: String,
+    }
+    Ok(Html { user, lists, token: query.token }.into_response())
+}
+
+/// Confirm a signup, adding the user to its lists.
+async fn confirm_form(
+    user: Option<User>, State(state): State<SharedAppState>, Form(form): Form<ConfirmQuery>,
+) -> HtmlResult {
+    let lists = ListSignup::lookup_lists(&state.db, &form.token).await?;
+    if lists.is_empty() {
+        bail_not_found!();
+    }
+    ListSignup::confirm(&state.db, &form.token).await?;
+
+    #[derive(Template, WebTemplate)]
+    #[template(path = "lists/confirmation.html")]
+    struct SuccessHtml {
+        user: Option<User>,
+        lists: Vec<List>,
+        message: Option<String>,
+        email: String,
+    }
+    let message = match lists.as_slice() {
+        [list] => list.confirmation_message.clone(),
+        _ => None,
+    };
+    Ok(
+        SuccessHtml { user, lists, message, email: state.config.email.from.email.to_string() }
+            .into_response(),
+    )
 }
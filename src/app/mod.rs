@@ -9,14 +9,19 @@ use crate::utils::cloudflare::Cloudflare;
 use crate::utils::emailer::Emailer;
 use crate::utils::stripe::Stripe;
 
+mod account;
 mod auth;
 mod contact;
 mod emails;
 mod events;
+mod feeds;
 mod gallery;
 mod home;
 mod lists;
 mod posts;
+mod revisions;
+mod segments;
+mod users;
 mod webhooks;
 
 pub struct AppState {
@@ -43,10 +48,15 @@ pub async fn build(config: Config) -> Result<(Router<()>, SharedAppState)> {
     let r = AppRouter::new(&state);
     let r = home::add_routes(r);
     let r = gallery::add_routes(r);
+    let r = feeds::add_routes(r);
     let r = auth::add_routes(r);
+    let r = account::add_routes(r);
     let r = posts::add_routes(r);
+    let r = revisions::add_routes(r);
     let r = events::add_routes(r);
     let r = lists::add_routes(r);
+    let r = segments::add_routes(r);
+    let r = users::add_routes(r);
     let r = emails::add_routes(r);
     let r = webhooks::add_routes(r);
     let r = contact::add_routes(r);
@@ -88,8 +98,8 @@ pub async fn build(config: Config) -> Result<(Router<()>, SharedAppState)> {
     };
     // For non-HTML pages without a <link rel="icon">, this is where the browser looks
     let r = r.route("/favicon.ico", get(|| async { Redirect::to("/static/favicon.ico") }));
-    // Opt out of all crawlers
-    let r = r.route("/robots.txt", get(|| async { "User-agent: *\nDisallow: /\n" }));
+    // Opt out of all crawlers, except link previews of public events and posts
+    let r = r.route("/robots.txt", get(|| async { ROBOTS_TXT }));
     let r = r.fallback(|| async { Err::<(), HtmlError>(not_found().into()) });
 
     // Register middleware
@@ -116,6 +126,27 @@ pub async fn build(config: Config) -> Result<(Router<()>, SharedAppState)> {
     Ok((r, state))
 }
 
+/// Chat apps and social sites fetch shared links to preview them, and some of them honor robots.txt.
+/// Feeds are open to any reader.
+const ROBOTS_TXT: &str = "\
+User-agent: Twitterbot
+User-agent: facebookexternalhit
+User-agent: Slackbot-LinkExpanding
+User-agent: Discordbot
+User-agent: TelegramBot
+User-agent: WhatsApp
+Allow: /e/
+Allow: /p/
+Disallow: /
+
+User-agent: *
+Allow: /posts.xml
+Allow: /posts.atom
+Allow: /events.xml
+Allow: /events.atom
+Disallow: /
+";
+
 /// Permanently redirect requests for non-canonical hosts to AppConfig::url.
 async fn redirect_secondary_hosts(req: Request, next: Next) -> Response {
     // Use HOST for HTTP/1, otherwise URI authority
@@ -1,6 +1,11 @@
+use crate::db::audit_log::{AuditLog, AuditTarget};
+use crate::db::email_click::EmailReport;
 use crate::db::list::List;
 use crate::db::post::{Post, UpdatePost};
+use crate::db::revision::Revision;
+use crate::db::segment::{Audience, Segment, SegmentCount};
 use crate::prelude::*;
+use crate::utils::merge_tags::MergeTags;
 
 /// Add all `post` routes to the router.
 #[rustfmt::skip]
@@ -9,66 +14,116 @@ pub fn add_routes(router: AppRouter) -> AppRouter {
         .public_routes(|r| {
             r.route("/p/{slug}", get(read::view_page))
         })
-        .restricted_routes(User::WRITER, |r| {
+        .restricted_routes(Permission::PostsPublish, |r| {
             r.route("/posts", get(read::list_page))
              .route("/posts/new", get(edit::new_page))
              .route("/posts/{slug}/edit", get(edit::edit_page).post(edit::edit_form))
              .route("/posts/{slug}/delete", post(edit::delete_form))
-             .route("/posts/{slug}/send", get(send::page).post(send::send_form))
              .route("/posts/{slug}/preview", get(read::preview_page))
+             .route("/posts/{slug}/test", post(send::test_form))
+        })
+        .restricted_routes(Permission::ListsSend, |r| {
+            r.route("/posts/{slug}/send", get(send::page).post(send::send_form))
+             .route("/posts/{slug}/report", get(send::report_page))
         })
 }
 
 /// View and list posts.
 mod read {
     use super::*;
+    use crate::utils::meta::PageMeta;
 
-    // Display a list of posts.
-    pub async fn list_page(user: User, State(state): State<SharedAppState>) -> HtmlResult {
-        let posts = Post::list(&state.db).await?;
+    // Display a list of posts, optionally only those with a status.
+    #[derive(serde::Deserialize)]
+    pub struct ListQuery {
+        status: Option<String>,
+    }
+    pub async fn list_page(
+        user: User, State(state): State<SharedAppState>, Query(query): Query<ListQuery>,
+    ) -> HtmlResult {
+        let status = query.status.filter(|status| !status.is_empty());
+        if let Some(status) = &status
+            && !Post::STATUSES.contains(&status.as_str())
+        {
+            bail_invalid!();
+        }
+        let posts = Post::list(&state.db, status.as_deref()).await?;
+        let counts = Post::count_by_status(&state.db).await?;
+        let tabs = Post::STATUSES
+            .iter()
+            .map(|status| (*status, counts.get(*status).copied().unwrap_or(0)))
+            .collect();
 
         #[derive(Template, WebTemplate)]
         #[template(path = "posts/list.html")]
         struct Html {
             user: Option<User>,
             posts: Vec<Post>,
+            status: Option<String>,
+            total: i64,
+            tabs: Vec<(&'static str, i64)>,
         }
-        Ok(Html { user: Some(user), posts }.into_response())
+        Ok(Html { user: Some(user), posts, status, total: counts.values().sum(), tabs }.into_response())
     }
 
-    // Display a single post.
+    // Display a single post. Only writers can see it before it's published.
     pub async fn view_page(
         user: Option<User>, State(state): State<SharedAppState>, Path(slug): Path<String>,
     ) -> HtmlResult {
-        let Some(post) = Post::lookup_by_slug(&state.db, &slug).await? else {
+        let Some(mut post) = Post::lookup_by_slug(&state.db, &slug).await? else {
             bail_not_found!();
         };
+        if !post.is_published() && !user.as_ref().is_some_and(|u| u.has_permission(Permission::PostsPublish))
+        {
+            bail_not_found!();
+        }
 
         #[derive(Template, WebTemplate)]
         #[template(path = "posts/view.html")]
         struct Html {
             user: Option<User>,
             post: Post,
+            meta: PageMeta,
         }
-        Ok(Html { user, post }.into_response())
+        // There's no recipient on the web, so merge tags show their fallbacks.
+        let tags = MergeTags::empty(MergeTags::USER_TAGS);
+        post.title = tags.expand_text(&post.title);
+        post.content = tags.expand_html(&post.content);
+        let meta = PageMeta::for_post(&post);
+        Ok(Html { user, post, meta }.into_response())
     }
 
     // Display a preview of a post as it would appear in an email.
-    pub async fn preview_page(State(state): State<SharedAppState>, Path(slug): Path<String>) -> HtmlResult {
-        let Some(post) = Post::lookup_by_slug(&state.db, &slug).await? else {
+    #[derive(serde::Deserialize)]
+    pub struct PreviewQuery {
+        /// Email of the user to fill in merge tags for, otherwise the viewer.
+        recipient: Option<String>,
+    }
+    pub async fn preview_page(
+        user: User, State(state): State<SharedAppState>, Path(slug): Path<String>,
+        Query(query): Query<PreviewQuery>,
+    ) -> HtmlResult {
+        let Some(mut post) = Post::lookup_by_slug(&state.db, &slug).await? else {
             bail_not_found!();
         };
+        let recipient =
+            MergeTags::preview_recipient::<HtmlError>(&state.db, user, query.recipient.as_deref()).await?;
+        let tags = MergeTags::for_user(&recipient);
+        post.title = tags.expand_text(&post.title);
+        post.content = tags.expand_html(&post.content);
 
         #[derive(Template, WebTemplate)]
         #[template(path = "emails/post.html")]
         struct EmailHtml {
             email_token: String,
+            track_opens: bool,
             post: Post,
             post_url: String,
         }
         Ok(EmailHtml {
             post_url: format!("{}/p/{}", &state.config.app.url, &post.slug),
             email_token: String::new(),
+            track_opens: false,
             post,
         }
         .into_response())
@@ -77,6 +132,8 @@ mod read {
 
 /// Create and edit posts.
 mod edit {
+    use chrono::TimeZone;
+
     use super::*;
     use crate::utils::editor::{Editor, EditorContent};
 
@@ -100,11 +157,15 @@ mod edit {
                 content: "".into(),
                 created_at: Utc::now().naive_utc(),
                 updated_at: Utc::now().naive_utc(),
+                status: Post::DRAFT.into(),
+                published_at: None,
             },
             editor: Editor {
                 url: "/posts/{id}/edit",
-                snapshot_prefix: "post",
+                snapshot_prefix: Revision::POST,
                 entity_id: None,
+                revisions_url: None,
+                merge_tags: MergeTags::USER_TAGS,
                 content: None,
             },
         }
@@ -123,8 +184,10 @@ mod edit {
             user: Some(user),
             editor: Editor {
                 url: "/posts/{id}/edit",
-                snapshot_prefix: "post",
+                snapshot_prefix: Revision::POST,
                 entity_id: Some(post.id),
+                revisions_url: Some(format!("/posts/{}/revisions", post.slug)),
+                merge_tags: MergeTags::USER_TAGS,
                 content: Some(EditorContent { html: post.content.clone(), updated_at: post.updated_at }),
             },
             post,
@@ -138,6 +201,10 @@ mod edit {
         id: i64,
         #[serde(flatten)]
         post: UpdatePost,
+        status: String,
+        /// A `datetime-local` in the app's timezone, only used when scheduling.
+        #[serde(default)]
+        publish_at: String,
     }
     #[derive(serde::Serialize)]
     pub struct EditResponse {
@@ -146,8 +213,34 @@ mod edit {
         error: Option<String>,
     }
     pub async fn edit_form(
-        State(state): State<SharedAppState>, Form(form): Form<EditForm>,
+        user: User, State(state): State<SharedAppState>, Form(form): Form<EditForm>,
     ) -> JsonResult<EditResponse> {
+        let error =
+            |error: &str| Ok(Json(EditResponse { id: None, updated_at: None, error: Some(error.into()) }));
+        if !Post::STATUSES.contains(&form.status.as_str()) {
+            bail_invalid!();
+        }
+        let publish_at = match form.status.as_str() {
+            Post::SCHEDULED => {
+                let Ok(local) = NaiveDateTime::parse_from_str(&form.publish_at, "%Y-%m-%dT%H:%M") else {
+                    return error("Pick a time to publish the post at.");
+                };
+                let Some(publish_at) = state.config.app.tz.from_local_datetime(&local).earliest() else {
+                    return error("That time doesn't exist in the local timezone.");
+                };
+                let publish_at = publish_at.naive_utc();
+                if publish_at <= Utc::now().naive_utc() {
+                    return error("A scheduled post needs a time in the future.");
+                }
+                Some(publish_at)
+            }
+            _ => None,
+        };
+
+        let before = match form.id {
+            0 => None,
+            id => Some(Post::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?),
+        };
         let (id, updated_at) = match form.id {
             0 => {
                 if Post::lookup_by_slug(&state.db, &form.post.slug).await?.is_some() {
@@ -161,6 +254,12 @@ mod edit {
             }
             id => Post::update(&state.db, id, &form.post).await?,
         };
+        Post::set_status(&state.db, id, &form.status, publish_at).await?;
+        Revision::record(&state.db, Revision::POST, id, None, &form.post.content, &user).await?;
+
+        let after = Post::lookup_by_id(&state.db, id).await?;
+        let action = if before.is_some() { "post.edit" } else { "post.create" };
+        AuditLog::record(&state.db, &user, action, AuditTarget::Post(id), &before, &after).await?;
 
         let updated_at = updated_at.and_utc().timestamp_millis();
 
@@ -168,11 +267,14 @@ mod edit {
     }
 
     // Delete post form.
-    pub async fn delete_form(State(state): State<SharedAppState>, Path(slug): Path<String>) -> HtmlResult {
+    pub async fn delete_form(
+        user: User, State(state): State<SharedAppState>, Path(slug): Path<String>,
+    ) -> HtmlResult {
         let Some(post) = Post::lookup_by_slug(&state.db, &slug).await? else {
             bail_not_found!();
         };
         Post::delete(&state.db, post.id).await?;
+        AuditLog::record(&state.db, &user, "post.delete", AuditTarget::Post(post.id), &post, ()).await?;
         Ok(Redirect::to("/posts").into_response())
     }
 }
@@ -182,6 +284,8 @@ mod send {
     use futures::StreamExt;
 
     use super::*;
+    use crate::app::emails::{TestSendForm, TestSendResponse, send_test};
+    use crate::utils::tracking::LinkTracker;
 
     /// Display the form to send a post.
     pub async fn page(
@@ -226,22 +330,26 @@ mod send {
         .fetch_all(&state.db)
         .await?;
 
+        let segments = Segment::list_with_counts(&state.db, Email::POST, Some(post.id), None).await?;
+
         #[derive(Template, WebTemplate)]
         #[template(path = "posts/send.html")]
         struct Html {
             user: Option<User>,
             post: Post,
             lists: Vec<ListExt>,
+            segments: Vec<SegmentCount>,
             ratelimit: usize,
         }
         let ratelimit = state.config.email.ratelimit;
-        Ok(Html { user: Some(user), post, lists, ratelimit }.into_response())
+        Ok(Html { user: Some(user), post, lists, segments, ratelimit }.into_response())
     }
 
     #[derive(Template, WebTemplate)]
     #[template(path = "emails/post.html")]
     struct EmailHtml {
         email_token: String,
+        track_opens: bool,
         post: Post,
         post_url: String,
     }
@@ -249,37 +357,79 @@ mod send {
     // Process the form and create or edit a post.
     #[derive(serde::Deserialize)]
     pub struct SendForm {
-        list_id: i64,
+        /// A list or segment, see `Audience::parse`.
+        audience: String,
         resend: bool,
     }
     pub async fn send_form(
-        State(state): State<SharedAppState>, Path(slug): Path<String>, Form(form): Form<SendForm>,
+        user: User, State(state): State<SharedAppState>, Path(slug): Path<String>, Form(form): Form<SendForm>,
     ) -> HtmlResult {
         let Some(post) = Post::lookup_by_slug(&state.db, &slug).await? else {
             bail_not_found!();
         };
-        let Some(list) = List::lookup_by_id(&state.db, form.list_id).await? else {
-            bail_not_found!();
+        if !post.is_published() {
+            bail_bad_request!("Publish the post before sending it.");
+        }
+        let Some(audience) = Audience::parse(&form.audience) else {
+            bail_invalid!();
         };
 
-        let emails = match form.resend {
-            false => Email::create_send_posts(&state.db, post.id, list.id).await?,
-            true => Email::create_resend_posts(&state.db, post.id, list.id).await?,
+        let (emails, list_ids) = match audience {
+            Audience::List(list_id) => {
+                let Some(list) = List::lookup_by_id(&state.db, list_id).await? else {
+                    bail_not_found!();
+                };
+                let emails = match form.resend {
+                    false => Email::create_send_posts(&state.db, post.id, list.id).await?,
+                    true => Email::create_resend_posts(&state.db, post.id, list.id).await?,
+                };
+                (emails, vec![list.id])
+            }
+            Audience::Segment(segment_id) => {
+                let Some(segment) = Segment::lookup_by_id(&state.db, segment_id).await? else {
+                    bail_not_found!();
+                };
+                let user_ids = segment.resolve(&state.db, None).await?;
+                let emails = Email::create_send_segment(
+                    &state.db,
+                    Email::POST,
+                    Some(post.id),
+                    None,
+                    segment.id,
+                    &user_ids,
+                    form.resend,
+                )
+                .await?;
+                (emails, segment.rules.lists)
+            }
         };
+        let tracking = List::tracking_allowed(&state.db, &list_ids).await?;
+        let mut tracker = LinkTracker::new(tracking);
 
         let mut email_template = EmailHtml {
             email_token: String::new(),
+            track_opens: tracking,
             post: post.clone(),
             post_url: format!("{}/p/{}", &state.config.app.url, &post.slug),
         };
         let mut messages = vec![];
         let mut email_ids = vec![];
-        for Email { id, address, sent_at, token, .. } in emails {
+        for Email { id, user_id, address, sent_at, token, .. } in emails {
             if sent_at.is_some() {
                 continue;
             }
+            let Some(recipient) = User::lookup_by_id(&state.db, user_id).await? else {
+                Email::mark_error(&state.db, id, "recipient not found").await?;
+                continue;
+            };
 
+            let tags = MergeTags::for_user(&recipient);
             email_template.email_token = token;
+            email_template.post.title = tags.expand_text(&post.title);
+            email_template.post.content = tags.expand_html(&post.content);
+            let body = tracker
+                .track(&state.db, email_template.render()?, &email_template.email_token)
+                .await?;
 
             let from = &state.config.email.from;
             let reply_to = state.config.email.newsletter_reply_to.as_ref().unwrap_or(from);
@@ -288,15 +438,18 @@ mod send {
                 .builder()
                 .to(address.parse().unwrap())
                 .reply_to(reply_to.clone())
-                .subject(&post.title)
+                .subject(&email_template.post.title)
                 .header(lettre::message::header::ContentType::TEXT_HTML)
-                .body(email_template.render()?)
+                .body(body)
                 .unwrap();
 
             messages.push(message);
             email_ids.push(id);
         }
 
+        let after = json!({"audience": audience, "resend": form.resend, "recipients": messages.len()});
+        AuditLog::record(&state.db, &user, "post.send", AuditTarget::Post(post.id), (), after).await?;
+
         let email_ids = futures::stream::iter(email_ids);
         let results = state.mailer.send_batch(Arc::clone(&state), messages).await;
 
@@ -320,4 +473,69 @@ mod send {
 
         Ok(body.into_response())
     }
+
+    /// Send the post, as a recipient would get it, to the viewer only. Drafts can be tested too.
+    pub async fn test_form(
+        user: User, State(state): State<SharedAppState>, Path(slug): Path<String>,
+        Form(form): Form<TestSendForm>,
+    ) -> JsonResult<TestSendResponse> {
+        let Some(mut post) = Post::lookup_by_slug(&state.db, &slug).await? else {
+            bail_not_found!();
+        };
+        let recipient =
+            MergeTags::preview_recipient::<JsonError>(&state.db, user.clone(), form.recipient.as_deref())
+                .await?;
+        let tags = MergeTags::for_user(&recipient);
+        post.title = tags.expand_text(&post.title);
+        post.content = tags.expand_html(&post.content);
+
+        // Tracked like a send to the chosen audience, clicks on a test count nowhere.
+        let list_ids = match form.audience.as_deref().map(Audience::parse) {
+            Some(Some(audience)) => audience.list_ids(&state.db).await?,
+            Some(None) => bail_invalid!(),
+            None => vec![],
+        };
+        let tracking = List::tracking_allowed(&state.db, &list_ids).await?;
+
+        let (email_id, token) = Email::create_test(&state.db, &user, Some(post.id), None).await?;
+        let mut tracker = LinkTracker::new(tracking);
+        let template = EmailHtml {
+            email_token: token.clone(),
+            track_opens: tracking,
+            post_url: format!("{}/p/{}", &state.config.app.url, &post.slug),
+            post,
+        };
+        let body = tracker.track(&state.db, template.render()?, &token).await?;
+
+        let from = &state.config.email.from;
+        let reply_to = state.config.email.newsletter_reply_to.as_ref().unwrap_or(from);
+        let builder = state.mailer.builder().reply_to(reply_to.clone());
+        send_test(&state, &user, email_id, builder, &template.post.title, body).await
+    }
+
+    /// Display delivery, opens, clicks and unsubscribes for a post's emails.
+    pub async fn report_page(
+        user: User, State(state): State<SharedAppState>, Path(slug): Path<String>,
+    ) -> HtmlResult {
+        let Some(post) = Post::lookup_by_slug(&state.db, &slug).await? else {
+            bail_not_found!();
+        };
+        let report = EmailReport::for_post(&state.db, post.id).await?;
+
+        #[derive(Template, WebTemplate)]
+        #[template(path = "emails/report.html")]
+        struct Html {
+            user: Option<User>,
+            title: String,
+            send_url: String,
+            report: EmailReport,
+        }
+        Ok(Html {
+            user: Some(user),
+            title: post.title,
+            send_url: format!("/posts/{}/send", post.slug),
+            report,
+        }
+        .into_response())
+    }
 }
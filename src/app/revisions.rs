@@ -0,0 +1,176 @@
+use crate::db::audit_log::{AuditLog, AuditTarget};
+use crate::db::event::Event;
+use crate::db::post::{Post, UpdatePost};
+use crate::db::revision::Revision;
+use crate::prelude::*;
+use crate::utils::diff::{DiffLine, html_diff};
+
+/// Add all `revisions` routes to the router.
+#[rustfmt::skip]
+pub fn add_routes(router: AppRouter) -> AppRouter {
+    router
+        .restricted_routes(Permission::PostsPublish, |r| {
+            r.route("/posts/{slug}/revisions", get(post_page))
+             .route("/posts/{slug}/revisions/{revision_id}/restore", post(restore_post_form))
+        })
+        .restricted_routes(Permission::EventsEdit, |r| {
+            r.route("/events/{id}/{field}/revisions", get(event_page))
+             .route("/events/{id}/{field}/revisions/{revision_id}/restore", post(restore_event_form))
+        })
+}
+
+/// Which two revisions to compare. Defaults to the newest and the one before it.
+#[derive(serde::Deserialize)]
+struct CompareQuery {
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "revisions.html")]
+struct RevisionsHtml {
+    user: Option<User>,
+    title: String,
+    edit_url: String,
+    /// Where the page lives, restore forms post below it.
+    url: String,
+    revisions: Vec<Revision>,
+    from: Option<Revision>,
+    to: Option<Revision>,
+    diff: Vec<DiffLine>,
+}
+
+impl RevisionsHtml {
+    fn new(
+        user: User, title: String, edit_url: String, url: String, revisions: Vec<Revision>,
+        query: CompareQuery,
+    ) -> Result<RevisionsHtml, AppError> {
+        let find = |id: i64| revisions.iter().position(|r| r.id == id).ok_or_else(not_found);
+        let to = match query.to {
+            Some(id) => Some(find(id)?),
+            None => (!revisions.is_empty()).then_some(0),
+        };
+        let from = match query.from {
+            Some(id) => Some(find(id)?),
+            None => to.map(|to| to + 1).filter(|&from| from < revisions.len()),
+        };
+        let from = from.map(|i| revisions[i].clone());
+        let to = to.map(|i| revisions[i].clone());
+
+        let diff = match &to {
+            Some(to) => html_diff(from.as_ref().map_or("", |from| &from.html), &to.html),
+            None => vec![],
+        };
+        Ok(RevisionsHtml { user: Some(user), title, edit_url, url, revisions, from, to, diff })
+    }
+}
+
+async fn post_page(
+    user: User, State(state): State<SharedAppState>, Path(slug): Path<String>,
+    Query(query): Query<CompareQuery>,
+) -> HtmlResult {
+    let Some(post) = Post::lookup_by_slug(&state.db, &slug).await? else {
+        bail_not_found!();
+    };
+    let revisions = Revision::list(&state.db, Revision::POST, post.id).await?;
+    Ok(RevisionsHtml::new(
+        user,
+        post.title,
+        format!("/posts/{}/edit", post.slug),
+        format!("/posts/{}/revisions", post.slug),
+        revisions,
+        query,
+    )?
+    .into_response())
+}
+
+async fn restore_post_form(
+    user: User, State(state): State<SharedAppState>, Path((slug, revision_id)): Path<(String, i64)>,
+) -> HtmlResult {
+    let Some(post) = Post::lookup_by_slug(&state.db, &slug).await? else {
+        bail_not_found!();
+    };
+    let revisions = Revision::list(&state.db, Revision::POST, post.id).await?;
+    let Some(revision) = revisions.into_iter().find(|r| r.id == revision_id) else {
+        bail_not_found!();
+    };
+
+    let update = UpdatePost {
+        title: post.title.clone(),
+        slug: post.slug.clone(),
+        author: post.author.clone(),
+        content: revision.html,
+    };
+    Post::update(&state.db, post.id, &update).await?;
+    Revision::record(&state.db, Revision::POST, post.id, None, &update.content, &user).await?;
+
+    let before = json!({"content": post.content});
+    let after = json!({"content": update.content, "revision_id": revision_id});
+    AuditLog::record(&state.db, &user, "post.restore", AuditTarget::Post(post.id), before, after).await?;
+
+    Ok(Redirect::to(&format!("/posts/{}/edit", post.slug)).into_response())
+}
+
+async fn event_page(
+    user: User, State(state): State<SharedAppState>, Path((id, field)): Path<(i64, String)>,
+    Query(query): Query<CompareQuery>,
+) -> HtmlResult {
+    let Some(kind) = Revision::event_kind(&field) else {
+        bail_not_found!();
+    };
+    let Some(event) = Event::lookup_by_id(&state.db, id).await? else {
+        bail_not_found!();
+    };
+    let revisions = Revision::list(&state.db, kind, event.id).await?;
+    Ok(RevisionsHtml::new(
+        user,
+        format!("{} - {field}", event.title),
+        format!("/events/{}/{field}/edit", event.id),
+        format!("/events/{}/{field}/revisions", event.id),
+        revisions,
+        query,
+    )?
+    .into_response())
+}
+
+async fn restore_event_form(
+    user: User, State(state): State<SharedAppState>, Path((id, field, revision_id)): Path<(i64, String, i64)>,
+) -> HtmlResult {
+    let Some(kind) = Revision::event_kind(&field) else {
+        bail_not_found!();
+    };
+    let Some(event) = Event::lookup_by_id(&state.db, id).await? else {
+        bail_not_found!();
+    };
+    let revisions = Revision::list(&state.db, kind, event.id).await?;
+    let Some(revision) = revisions.into_iter().find(|r| r.id == revision_id) else {
+        bail_not_found!();
+    };
+
+    let (before_subject, before_html) = match kind {
+        Revision::EVENT_DESCRIPTION => (None, event.description_html),
+        Revision::EVENT_INVITE => (event.invite_subject, event.invite_html),
+        Revision::EVENT_CONFIRMATION => (event.confirmation_subject, event.confirmation_html),
+        _ => (event.dayof_subject, event.dayof_html),
+    };
+    // Revisions from before subjects were saved keep the current one.
+    let subject = revision.subject.or(before_subject.clone()).unwrap_or_default();
+    let html = revision.html;
+    match kind {
+        Revision::EVENT_DESCRIPTION => Event::update_description(&state.db, id, html.clone()).await?,
+        Revision::EVENT_INVITE => Event::update_invite(&state.db, id, subject.clone(), html.clone()).await?,
+        Revision::EVENT_CONFIRMATION => {
+            Event::update_confirmation(&state.db, id, subject.clone(), html.clone()).await?
+        }
+        _ => Event::update_dayof(&state.db, id, subject.clone(), html.clone()).await?,
+    };
+    let subject = (kind != Revision::EVENT_DESCRIPTION).then_some(subject);
+    Revision::record(&state.db, kind, id, subject.as_deref(), &html, &user).await?;
+
+    let before = json!({"subject": before_subject, "html": before_html});
+    let after = json!({"subject": subject, "html": html, "revision_id": revision_id});
+    let action = format!("event.{field}.restore");
+    AuditLog::record(&state.db, &user, &action, AuditTarget::Event(id), before, after).await?;
+
+    Ok(Redirect::to(&format!("/events/{id}/{field}/edit")).into_response())
+}
@@ -0,0 +1,151 @@
+use crate::db::audit_log::{AuditLog, AuditTarget};
+use crate::db::event::{Event, EventWithStats};
+use crate::db::list::{List, ListWithCount};
+use crate::db::segment::{Segment, SegmentRules};
+use crate::prelude::*;
+
+/// Add all `segments` routes to the router.
+#[rustfmt::skip]
+pub fn add_routes(router: AppRouter) -> AppRouter {
+    router.restricted_routes(Permission::ListsEdit, |r| {
+        r.route("/segments", get(list_segments_page))
+         .route("/segments/new", get(create_segment_page))
+         .route("/segments/{id}", get(edit_segment_page).post(edit_segment_form))
+         .route("/segments/{id}/delete", post(delete_segment_form))
+    })
+}
+
+struct SegmentWithCount {
+    segment: Segment,
+    count: usize,
+}
+
+/// Display all segments with their current size.
+async fn list_segments_page(user: User, State(state): State<SharedAppState>) -> HtmlResult {
+    let mut segments = vec![];
+    for segment in Segment::list(&state.db).await? {
+        let count = segment.resolve(&state.db, None).await?.len();
+        segments.push(SegmentWithCount { segment, count });
+    }
+
+    #[derive(Template, WebTemplate)]
+    #[template(path = "segments/list.html")]
+    struct Html {
+        user: Option<User>,
+        segments: Vec<SegmentWithCount>,
+    }
+    Ok(Html { user: Some(user), segments }.into_response())
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "segments/edit.html")]
+struct SegmentEditHtml {
+    user: Option<User>,
+    id: i64,
+    name: String,
+    rules: SegmentRules,
+    /// Current size of the segment, when editing.
+    count: Option<usize>,
+    lists: Vec<ListWithCount>,
+    events: Vec<EventWithStats>,
+}
+
+impl SegmentEditHtml {
+    async fn build(db: &Db, user: User, segment: Option<Segment>) -> Result<SegmentEditHtml> {
+        let lists = List::list_with_counts(db).await?;
+        let events = Event::list(db, false).await?;
+        Ok(match segment {
+            Some(segment) => SegmentEditHtml {
+                user: Some(user),
+                count: Some(segment.resolve(db, None).await?.len()),
+                id: segment.id,
+                name: segment.name,
+                rules: segment.rules,
+                lists,
+                events,
+            },
+            None => SegmentEditHtml {
+                user: Some(user),
+                id: 0,
+                name: "".into(),
+                rules: SegmentRules::default(),
+                count: None,
+                lists,
+                events,
+            },
+        })
+    }
+}
+
+/// Display the form to create a new segment.
+async fn create_segment_page(user: User, State(state): State<SharedAppState>) -> HtmlResult {
+    Ok(SegmentEditHtml::build(&state.db, user, None).await?.into_response())
+}
+
+/// Display the form to edit a segment.
+async fn edit_segment_page(
+    user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+) -> HtmlResult {
+    let segment = Segment::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?;
+    Ok(SegmentEditHtml::build(&state.db, user, Some(segment)).await?.into_response())
+}
+
+/// Process the form and create or edit a segment. `id` is 0 for new segments.
+///
+/// Lists and events are checkboxes and a multi-select, so the form is parsed as repeated pairs.
+async fn edit_segment_form(
+    user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+    Form(pairs): Form<Vec<(String, String)>>,
+) -> HtmlResult {
+    let mut name = String::new();
+    let mut rules = SegmentRules::default();
+    for (key, value) in pairs {
+        match key.as_str() {
+            "name" => name = value.trim().to_string(),
+            "list" => rules.lists.extend(value.parse::<i64>().ok()),
+            "match" => rules.match_all_lists = value == "all",
+            "event" => rules.attended_events.extend(value.parse::<i64>().ok()),
+            "rsvp_within_months" => rules.rsvp_within_months = value.trim().parse().ok().filter(|m| *m > 0),
+            "unopened_recent" => rules.unopened_recent = true,
+            "exclude_event_rsvps" => rules.exclude_event_rsvps = true,
+            _ => {}
+        }
+    }
+    if name.is_empty() {
+        bail_bad_request!("Segments need a name.");
+    }
+    if rules.lists.is_empty() {
+        bail_bad_request!("Pick at least one list, segments only reach people who signed up for email.");
+    }
+
+    let before = match id {
+        0 => None,
+        id => Some(Segment::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?),
+    };
+    let id = match &before {
+        Some(segment) => {
+            Segment::update(&state.db, segment.id, &name, &rules).await?;
+            segment.id
+        }
+        None => Segment::create(&state.db, &name, &rules).await?,
+    };
+
+    let action = if before.is_some() { "segment.edit" } else { "segment.create" };
+    let before = before.map(|segment| json!({"name": segment.name, "rules": segment.rules}));
+    let after = json!({"name": name, "rules": rules});
+    AuditLog::record(&state.db, &user, action, AuditTarget::Segment(id), before, after).await?;
+
+    Ok(Redirect::to(&format!("/segments/{id}")).into_response())
+}
+
+async fn delete_segment_form(
+    user: User, State(state): State<SharedAppState>, Path(id): Path<i64>,
+) -> HtmlResult {
+    let segment = Segment::lookup_by_id(&state.db, id).await?.ok_or_else(not_found)?;
+    Segment::delete(&state.db, id).await?;
+
+    let before = json!({"name": segment.name, "rules": segment.rules});
+    AuditLog::record(&state.db, &user, "segment.delete", AuditTarget::Segment(id), before, ()).await?;
+
+    Ok(Redirect::to("/segments").into_response())
+}
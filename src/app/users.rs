@@ -0,0 +1,131 @@
+use crate::db::audit_log::{AuditEntry, AuditFilter, AuditLog, AuditTarget};
+use crate::db::role::{Role, RoleAssignment};
+use crate::db::token::SessionToken;
+use crate::db::user::CreateUser;
+use crate::db::user_export::UserExport;
+use crate::prelude::*;
+
+/// Add all `users` routes to the router.
+#[rustfmt::skip]
+pub fn add_routes(router: AppRouter) -> AppRouter {
+    router.restricted_routes(Permission::UsersManage, |r| {
+        r.route("/users", get(roles_page))
+         .route("/users/roles", post(assign_role_form))
+         .route("/users/{user_id}/roles/{role}", delete(unassign_role))
+         .route("/users/sessions/revoke", post(revoke_sessions_form))
+         .route("/users/export", get(export_download))
+         .route("/users/delete", post(delete_form))
+         .route("/users/audit", get(audit_page))
+    })
+}
+
+/// Display all roles, their permissions and who holds them.
+async fn roles_page(user: User, State(state): State<SharedAppState>) -> HtmlResult {
+    let roles = Role::list(&state.db).await?;
+    let assignments = Role::list_assignments(&state.db).await?;
+
+    #[derive(Template, WebTemplate)]
+    #[template(path = "users/roles.html")]
+    struct Html {
+        user: Option<User>,
+        roles: Vec<Role>,
+        assignments: Vec<RoleAssignment>,
+    }
+    Ok(Html { user: Some(user), roles, assignments }.into_response())
+}
+
+#[derive(serde::Deserialize)]
+struct AssignRoleForm {
+    email: String,
+    role: String,
+}
+
+/// Grant a role to a user by email, creating the user if needed.
+async fn assign_role_form(
+    user: User, State(state): State<SharedAppState>, Form(form): Form<AssignRoleForm>,
+) -> HtmlResult {
+    if !Role::exists(&state.db, &form.role).await? {
+        bail_invalid!();
+    }
+
+    let info = CreateUser { email: form.email, first_name: None, last_name: None, phone: None };
+    let target = User::get_or_create(&state.db, &info).await?;
+    Role::assign(&state.db, target.id, &form.role).await?;
+    let after = json!({"role": form.role});
+    AuditLog::record(&state.db, &user, "user.role.assign", AuditTarget::User(target.id), (), after).await?;
+
+    Ok(Redirect::to("/users").into_response())
+}
+
+async fn unassign_role(
+    user: User, State(state): State<SharedAppState>, Path((user_id, role)): Path<(i64, String)>,
+) -> JsonResult<()> {
+    // Someone has to be left who can manage roles, so admins can't drop their own or the last admin role.
+    if role == Role::ADMIN && (user_id == user.id || Role::count_holders(&state.db, Role::ADMIN).await? <= 1)
+    {
+        bail_invalid!();
+    }
+    Role::unassign(&state.db, user_id, &role).await?;
+    let before = json!({"role": role});
+    AuditLog::record(&state.db, &user, "user.role.unassign", AuditTarget::User(user_id), before, ()).await?;
+    Ok(Json(()))
+}
+
+#[derive(serde::Deserialize)]
+struct RevokeSessionsForm {
+    email: String,
+}
+
+/// Log a user out of every device, e.g. after a lost phone.
+async fn revoke_sessions_form(
+    user: User, State(state): State<SharedAppState>, Form(form): Form<RevokeSessionsForm>,
+) -> HtmlResult {
+    let target = User::lookup_by_email(&state.db, &form.email).await?.ok_or_else(not_found)?;
+    SessionToken::delete_by_user_id(&state.db, target.id).await?;
+    let target = AuditTarget::User(target.id);
+    AuditLog::record(&state.db, &user, "user.sessions.revoke", target, (), ()).await?;
+    Ok(Redirect::to("/users").into_response())
+}
+
+#[derive(serde::Deserialize)]
+struct UserEmailQuery {
+    email: String,
+}
+
+/// Export a user's data, for access requests that come in by email.
+async fn export_download(
+    State(state): State<SharedAppState>, Query(query): Query<UserEmailQuery>,
+) -> HtmlResult {
+    let target = User::lookup_by_email(&state.db, &query.email).await?.ok_or_else(not_found)?;
+    let export = UserExport::build(&state.db, &target).await?;
+    super::account::export_response(&export)
+}
+
+/// Erase a user's personal data, for deletion requests that come in by email.
+async fn delete_form(
+    user: User, State(state): State<SharedAppState>, Form(form): Form<UserEmailQuery>,
+) -> HtmlResult {
+    let target = User::lookup_by_email(&state.db, &form.email).await?.ok_or_else(not_found)?;
+    target.anonymize(&state.db).await?;
+    // Recorded after the erasure and without the email, so the entry itself holds nothing personal.
+    AuditLog::record(&state.db, &user, "user.delete", AuditTarget::User(target.id), (), ()).await?;
+    Ok(Redirect::to("/users").into_response())
+}
+
+/// Display recent admin actions, filtered by user, event and action.
+async fn audit_page(
+    user: User, State(state): State<SharedAppState>, Query(filter): Query<AuditFilter>,
+) -> HtmlResult {
+    let entries = AuditLog::list(&state.db, &filter).await?;
+    let actions = AuditLog::list_actions(&state.db).await?;
+
+    #[derive(Template, WebTemplate)]
+    #[template(path = "users/audit.html")]
+    struct Html {
+        user: Option<User>,
+        entries: Vec<AuditEntry>,
+        actions: Vec<String>,
+        filter: AuditFilter,
+    }
+    Ok(Html { user: Some(user), entries, actions, filter }.into_response())
+}
@@ -14,6 +14,7 @@ pub mod stripe {
     use crate::db::event::Event;
     use crate::db::event_flyer::EventFlyer;
     use crate::db::rsvp_session::RsvpSession;
+    use crate::utils::merge_tags::MergeTags;
 
     type HmacSha256 = Hmac<Sha256>;
 
@@ -55,7 +56,7 @@ pub mod stripe {
         // 4. Compare against provided signatures. We ignore the timestamp for now.
         let valid = signatures.iter().any(|sig| sig == &expected_signature);
         if !valid {
-            crate::bail_unauthorized!();
+            bail_unauthorized!();
         }
 
         // tracing::debug!("STRIPE:  {body}");
@@ -152,9 +153,12 @@ pub mod stripe {
                         flyer: Option<EventFlyer>,
                     }
 
+                    let mut email_event = event.clone();
+                    MergeTags::for_event(&state.db, &event, &user).await?.apply(&mut email_event);
+
                     let from = &state.config.email.from;
                     let reply_to = state.config.email.contact_to.as_ref().unwrap_or(from);
-                    let subject = event
+                    let subject = email_event
                         .confirmation_subject
                         .clone()
                         .unwrap_or_else(|| format!("Confirmation for {}", event.title));
@@ -168,7 +172,7 @@ pub mod stripe {
                         .body(
                             ConfirmationEmailHtml {
                                 email_token: email.token,
-                                event: event.clone(),
+                                event: email_event,
                                 token: session.token,
                                 flyer,
                             }
@@ -0,0 +1,258 @@
+use crate::prelude::*;
+
+/// What an admin action was applied to.
+#[derive(Clone, Copy, Debug)]
+pub enum AuditTarget {
+    Event(i64),
+    /// An attendee, staff member or RSVP of an event, by user id.
+    Attendee {
+        event_id: i64,
+        user_id: i64,
+    },
+    RsvpSession {
+        event_id: i64,
+        id: i64,
+    },
+    List(i64),
+    Post(i64),
+    Segment(i64),
+    User(i64),
+}
+
+impl AuditTarget {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Event(_) => "event",
+            Self::Attendee { .. } | Self::User(_) => "user",
+            Self::RsvpSession { .. } => "rsvp_session",
+            Self::List(_) => "list",
+            Self::Post(_) => "post",
+            Self::Segment(_) => "segment",
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        match *self {
+            Self::Event(id)
+            | Self::List(id)
+            | Self::Post(id)
+            | Self::Segment(id)
+            | Self::User(id)
+            | Self::RsvpSession { id, .. } => id,
+            Self::Attendee { user_id, .. } => user_id,
+        }
+    }
+
+    pub fn event_id(&self) -> Option<i64> {
+        match *self {
+            Self::Event(event_id) | Self::Attendee { event_id, .. } | Self::RsvpSession { event_id, .. } => {
+                Some(event_id)
+            }
+            Self::List(_) | Self::Post(_) | Self::Segment(_) | Self::User(_) => None,
+        }
+    }
+}
+
+/// A recorded admin action, joined with the actor and event for display.
+#[derive(Debug, serde::Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor_id: i64,
+    pub actor_email: String,
+    pub action: String,
+    pub target_kind: String,
+    pub target_id: i64,
+    pub event_id: Option<i64>,
+    pub event_title: Option<String>,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Filters for the audit log page. Empty filters match everything.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct AuditFilter {
+    /// Matches the actor, or the target when it is a user.
+    pub email: Option<String>,
+    /// Kept as text so an empty form field means no filter.
+    pub event_id: Option<String>,
+    pub action: Option<String>,
+}
+
+pub struct AuditLog;
+
+impl AuditLog {
+    /// Most recent entries shown on the audit log page.
+    pub const PAGE_SIZE: i64 = 500;
+    /// Stands in for personal details scrubbed from entries.
+    pub const REDACTED: &'static str = "[deleted]";
+
+    /// Record an admin action.
+    ///
+    /// `before` and `after` are snapshots of the target; pass `()` when there is none, e.g. for
+    /// creations and deletions. When both are objects only the fields that differ are stored.
+    pub async fn record(
+        db: &Db, actor: &User, action: &str, target: AuditTarget, before: impl serde::Serialize,
+        after: impl serde::Serialize,
+    ) -> Result<()> {
+        let (before, after) = Self::diff(serde_json::to_value(before)?, serde_json::to_value(after)?);
+        let before = (!before.is_null()).then(|| before.to_string());
+        let after = (!after.is_null()).then(|| after.to_string());
+
+        let target_kind = target.kind();
+        let target_id = target.id();
+        let event_id = target.event_id();
+        sqlx::query!(
+            r#"INSERT INTO audit_log (actor_id, action, target_kind, target_id, event_id, before, after)
+               VALUES (?, ?, ?, ?, ?, ?, ?)"#,
+            actor.id,
+            action,
+            target_kind,
+            target_id,
+            event_id,
+            before,
+            after,
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Scrub a user's personal details from the log when their account is erased: entries about
+    /// them, and any other entry mentioning their email.
+    pub async fn redact_user(conn: &mut sqlx::SqliteConnection, user: &User) -> Result<()> {
+        let rows = sqlx::query!(
+            r#"SELECT id, target_kind = 'user' AND target_id = ? AS "about_user!: bool", before, after
+               FROM audit_log
+               WHERE (target_kind = 'user' AND target_id = ?)
+                  OR instr(lower(before), lower(?)) OR instr(lower(after), lower(?))"#,
+            user.id,
+            user.id,
+            user.email,
+            user.email,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let full_name = match (&user.first_name, &user.last_name) {
+            (Some(first), Some(last)) => Some(format!("{first} {last}")),
+            _ => None,
+        };
+        // Anything containing these goes, single names only when they are the whole value.
+        let contained: Vec<String> = [Some(user.email.clone()), user.phone.clone(), full_name]
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_lowercase())
+            .collect();
+        let names: Vec<String> = [&user.first_name, &user.last_name]
+            .into_iter()
+            .flatten()
+            .map(|s| s.to_lowercase())
+            .collect();
+
+        for row in rows {
+            let redact = |json: Option<String>| -> Result<Option<String>> {
+                let Some(json) = json else {
+                    return Ok(None);
+                };
+                let mut value: serde_json::Value = serde_json::from_str(&json)?;
+                Self::redact(&mut value, &contained, &names, row.about_user);
+                Ok(Some(value.to_string()))
+            };
+            let before = redact(row.before)?;
+            let after = redact(row.after)?;
+            sqlx::query!("UPDATE audit_log SET before = ?, after = ? WHERE id = ?", before, after, row.id)
+                .execute(&mut *conn)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Replace matching strings in a snapshot, and with `by_key` every personal field as well.
+    fn redact(value: &mut serde_json::Value, contained: &[String], names: &[String], by_key: bool) {
+        use serde_json::Value;
+        const PERSONAL: [&str; 5] = ["email", "first_name", "last_name", "phone", "note"];
+        match value {
+            Value::String(s) => {
+                let lower = s.to_lowercase();
+                if contained.iter().any(|c| lower.contains(c)) || names.contains(&lower) {
+                    *value = Value::String(Self::REDACTED.into());
+                }
+            }
+            Value::Array(items) => {
+                items.iter_mut().for_each(|item| Self::redact(item, contained, names, by_key))
+            }
+            Value::Object(fields) => {
+                for (key, field) in fields.iter_mut() {
+                    match by_key && PERSONAL.contains(&key.as_str()) && !field.is_null() {
+                        true => *field = Value::String(Self::REDACTED.into()),
+                        false => Self::redact(field, contained, names, by_key),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Drop the fields two object snapshots have in common.
+    fn diff(before: serde_json::Value, after: serde_json::Value) -> (serde_json::Value, serde_json::Value) {
+        use serde_json::Value;
+        match (before, after) {
+            (Value::Object(mut before), Value::Object(mut after)) => {
+                let same: Vec<String> = before
+                    .iter()
+                    .filter(|(k, v)| after.get(*k) == Some(v))
+                    .map(|(k, _)| k.clone())
+                    .collect();
+                for key in same {
+                    before.remove(&key);
+                    after.remove(&key);
+                }
+                (Value::Object(before), Value::Object(after))
+            }
+            pair => pair,
+        }
+    }
+
+    pub async fn list(db: &Db, filter: &AuditFilter) -> Result<Vec<AuditEntry>> {
+        let email = filter.email.as_deref().map(str::trim).filter(|s| !s.is_empty());
+        let event_id = filter.event_id.as_deref().and_then(|s| s.trim().parse::<i64>().ok());
+        let action = filter.action.as_deref().filter(|s| !s.is_empty());
+        Ok(sqlx::query_as!(
+            AuditEntry,
+            r#"SELECT a.id, a.actor_id, u.email AS actor_email, a.action, a.target_kind, a.target_id,
+                      a.event_id, e.title AS "event_title?", a.before, a.after,
+                      a.created_at AS "created_at: NaiveDateTime"
+               FROM audit_log a
+               JOIN users u ON u.id = a.actor_id
+               LEFT JOIN events e ON e.id = a.event_id
+               LEFT JOIN users t ON a.target_kind = 'user' AND t.id = a.target_id
+               WHERE (? IS NULL OR u.email = ? COLLATE NOCASE OR t.email = ? COLLATE NOCASE)
+                 AND (? IS NULL OR a.event_id = ?)
+                 AND (? IS NULL OR a.action = ?)
+               ORDER BY a.id DESC
+               LIMIT ?"#,
+            email,
+            email,
+            email,
+            event_id,
+            event_id,
+            action,
+            action,
+            Self::PAGE_SIZE,
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// Distinct actions recorded so far, for the filter dropdown.
+    pub async fn list_actions(db: &Db) -> Result<Vec<String>> {
+        Ok(sqlx::query!("SELECT DISTINCT action FROM audit_log ORDER BY action")
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|row| row.action)
+            .collect())
+    }
+}
@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use crate::prelude::*;
+use crate::utils::blobs::blobs;
+
+/// References from the database into the blob store.
+pub struct Blob;
+
+impl Blob {
+    /// How long a blob must go unwritten before a sweep may delete it.
+    pub const SWEEP_AGE: Duration = Duration::from_secs(60 * 60);
+
+    /// Delete blobs nothing references anymore. Blobs are shared by identical images, e.g. across
+    /// duplicated events, so deleting or replacing one image mustn't take them out from under another.
+    ///
+    /// Images are saved by writing their blobs and then inserting the rows that reference them, so a
+    /// blob can be briefly unreferenced. Only blobs not written for `SWEEP_AGE` are considered.
+    pub async fn sweep(db: &Db) -> Result<()> {
+        let mut deleted = 0;
+        for hash in blobs().list_stale(Self::SWEEP_AGE).await? {
+            let referenced = sqlx::query_scalar!(
+                r#"SELECT EXISTS (
+                       SELECT 1 FROM event_flyers
+                       WHERE ?1 IN (thumb_hash, sm_hash, md_hash, lg_hash, full_hash, square_hash, og_hash)
+                       UNION ALL
+                       SELECT 1 FROM event_flyer_variants WHERE hash = ?1
+                       UNION ALL
+                       SELECT 1 FROM event_photos WHERE ?1 IN (thumb_hash, sm_hash, md_hash, lg_hash, full_hash)
+                   ) AS "exists!: bool""#,
+                hash
+            )
+            .fetch_one(db)
+            .await?;
+            if !referenced {
+                blobs().delete(&hash).await?;
+                deleted += 1;
+            }
+        }
+        if deleted > 0 {
+            tracing::info!("Deleted {deleted} unreferenced blobs");
+        }
+        Ok(())
+    }
+}
@@ -14,6 +14,8 @@ pub struct Email {
     pub list_id: Option<i64>,
     pub event_id: Option<i64>,
     pub notification_id: Option<i64>,
+    /// The segment a post or invite was sent to, in place of `list_id`.
+    pub segment_id: Option<i64>,
 
     pub error: Option<String>,
     pub created_at: NaiveDateTime,
@@ -24,6 +26,10 @@ pub struct Email {
 impl Email {
     /// A login email.
     pub const LOGIN: &'static str = "login";
+    /// A link to verify a new email address.
+    pub const EMAIL_CHANGE: &'static str = "email_change";
+    /// A link to confirm signing up for lists.
+    pub const LIST_SIGNUP: &'static str = "list/signup";
     /// An email containing a post.
     pub const POST: &'static str = "post";
 
@@ -33,6 +39,18 @@ impl Email {
     pub const EVENT_CONFIRMATION: &'static str = "event/confirmation";
     /// An event day-of info email.
     pub const EVENT_DAYOF: &'static str = "event/dayof";
+    /// A test of a post or event email, sent only to the admin who asked for it. Being its own kind keeps
+    /// it out of reports and sent counts.
+    pub const TEST: &'static str = "test";
+
+    /// Whether the email went out to a list, segment or event audience, so its token opens the preference
+    /// center. Login, signup and test emails don't.
+    pub fn has_preferences(&self) -> bool {
+        matches!(
+            self.kind.as_str(),
+            Email::POST | Email::EVENT_INVITE | Email::EVENT_CONFIRMATION | Email::EVENT_DAYOF
+        )
+    }
 
     /// Lookup an email by its token.
     pub async fn lookup_by_token(db: &Db, token: &str) -> Result<Option<Email>> {
@@ -51,11 +69,43 @@ impl Email {
 
     /// Create a new login email record. Returns the email id and its token.
     pub async fn create_login(db: &Db, user: &User) -> Result<(i64, String)> {
+        Self::create_transactional(db, Email::LOGIN, user).await
+    }
+
+    pub async fn create_email_change(db: &Db, user: &User) -> Result<(i64, String)> {
+        Self::create_transactional(db, Email::EMAIL_CHANGE, user).await
+    }
+
+    pub async fn create_list_signup(db: &Db, user: &User) -> Result<(i64, String)> {
+        Self::create_transactional(db, Email::LIST_SIGNUP, user).await
+    }
+
+    /// Create an email entry for a test send of a post or event email to `user`, returning its id and token.
+    pub async fn create_test(
+        db: &Db, user: &User, post_id: Option<i64>, event_id: Option<i64>,
+    ) -> Result<(i64, String)> {
+        let row = sqlx::query!(
+            r#"INSERT INTO emails (token, kind, user_id, user_version, post_id, event_id)
+               VALUES (lower(hex(randomblob(8))), ?, ?, ?, ?, ?)
+               RETURNING id AS "id!: i64", token AS "token!: String""#,
+            Email::TEST,
+            user.id,
+            user.version,
+            post_id,
+            event_id
+        )
+        .fetch_one(db)
+        .await?;
+        Ok((row.id, row.token))
+    }
+
+    /// Create an email entry for a one-off email to a single user, returning its id and token.
+    async fn create_transactional(db: &Db, kind: &str, user: &User) -> Result<(i64, String)> {
         let row = sqlx::query!(
             r#"INSERT INTO emails (token, kind, user_id, user_version)
                VALUES (lower(hex(randomblob(8))), ?, ?, ?)
                RETURNING id AS "id!: i64", token AS "token!: String""#,
-            Email::LOGIN,
+            kind,
             user.id,
             user.version
         )
@@ -66,6 +116,7 @@ impl Email {
 
     /// Create email entries for sending the given post to all users on the given list.
     /// Returns rows with `sent_at` set if the post was already emailed to a user.
+    /// Users who paused list email are skipped.
     pub async fn create_send_posts(db: &Db, post_id: i64, list_id: i64) -> Result<Vec<Email>> {
         let existing = sqlx::query_as!(
             Email,
@@ -97,6 +148,7 @@ impl Email {
                  JOIN user_history uh ON uh.user_id = u.id
                    AND uh.version = (SELECT MAX(version) FROM user_history WHERE user_id = u.id)
                  WHERE lm.list_id = ?
+                   AND NOT EXISTS (SELECT 1 FROM email_pauses p WHERE p.user_id = u.id AND p.paused_until > CURRENT_TIMESTAMP)
                    AND NOT EXISTS (
                        SELECT 1
                        FROM emails ee
@@ -124,6 +176,7 @@ impl Email {
 
     /// Create email entries for sending the given post to all users on the given list.
     /// Returns rows with `sent_at` set if the post was already emailed to a user.
+    /// Users who paused list email are skipped.
     pub async fn create_send_invites(db: &Db, event_id: i64, list_id: i64) -> Result<Vec<Email>> {
         let existing = sqlx::query_as!(
             Email,
@@ -157,6 +210,7 @@ impl Email {
                  JOIN user_history uh ON uh.user_id = u.id
                    AND uh.version = (SELECT MAX(version) FROM user_history WHERE user_id = u.id)
                  WHERE lm.list_id = ?
+                   AND NOT EXISTS (SELECT 1 FROM email_pauses p WHERE p.user_id = u.id AND p.paused_until > CURRENT_TIMESTAMP)
                    AND NOT EXISTS (
                        SELECT 1
                        FROM emails ee
@@ -186,6 +240,105 @@ impl Email {
         Ok(all)
     }
 
+    /// Create email entries for sending a post or invite to the given members of a segment.
+    /// `post_id` is set for posts and `event_id` for invites.
+    ///
+    /// Returns unsent rows for the segment, creating them for users who haven't been sent this
+    /// post or invite from any list or segment. When resending, rows are created for every user
+    /// without an unsent one.
+    pub async fn create_send_segment(
+        db: &Db, kind: &str, post_id: Option<i64>, event_id: Option<i64>, segment_id: i64, user_ids: &[i64],
+        resend: bool,
+    ) -> Result<Vec<Email>> {
+        let existing_unsent = sqlx::query_as!(
+            Email,
+            r#"
+            SELECT e.*, u.email as address
+            FROM emails e
+            JOIN users u ON u.id = e.user_id
+            WHERE e.kind = ? AND e.post_id IS ? AND e.event_id IS ? AND e.segment_id = ?
+              AND e.sent_at IS NULL
+              AND e.id = (
+                  SELECT MAX(ee.id)
+                  FROM emails ee
+                  WHERE ee.kind = e.kind
+                    AND ee.user_id = e.user_id
+                    AND ee.post_id IS e.post_id
+                    AND ee.event_id IS e.event_id
+                    AND ee.segment_id = e.segment_id
+                    AND ee.sent_at IS NULL
+              );
+            "#,
+            kind,
+            post_id,
+            event_id,
+            segment_id,
+        )
+        .fetch_all(db)
+        .await?;
+
+        let user_ids = serde_json::to_string(user_ids)?;
+        let new = sqlx::query_as!(
+            Email,
+            r#"
+            INSERT INTO emails (token, kind, user_id, user_version, post_id, event_id, segment_id)
+                SELECT lower(hex(randomblob(8))), ?, u.id, uh.version, ?, ?, ?
+                FROM users u
+                JOIN user_history uh ON uh.user_id = u.id
+                  AND uh.version = (SELECT MAX(version) FROM user_history WHERE user_id = u.id)
+                WHERE u.id IN (SELECT value FROM json_each(?))
+                  AND NOT EXISTS (
+                      SELECT 1
+                      FROM emails e
+                      WHERE e.kind = ?
+                        AND e.user_id = u.id
+                        AND e.post_id IS ?
+                        AND e.event_id IS ?
+                        AND (NOT ? OR (e.segment_id = ? AND e.sent_at IS NULL))
+                  )
+            RETURNING *, (
+                SELECT u.email FROM users u
+                WHERE u.id = emails.user_id
+            ) AS "address!"
+            "#,
+            kind,
+            post_id,
+            event_id,
+            segment_id,
+            user_ids,
+            kind,
+            post_id,
+            event_id,
+            resend,
+            segment_id,
+        )
+        .fetch_all(db)
+        .await?;
+
+        let mut all = existing_unsent;
+        all.extend(new);
+        Ok(all)
+    }
+
+    /// Count how many of the given users were sent a post or invite, from any list or segment.
+    pub async fn count_sent_to(
+        db: &Db, kind: &str, post_id: Option<i64>, event_id: Option<i64>, user_ids: &[i64],
+    ) -> Result<i64> {
+        let user_ids = serde_json::to_string(user_ids)?;
+        Ok(sqlx::query_scalar!(
+            r#"SELECT COUNT(DISTINCT e.user_id) AS "count!: i64"
+               FROM emails e
+               WHERE e.kind = ? AND e.post_id IS ? AND e.event_id IS ? AND e.sent_at IS NOT NULL
+                 AND e.user_id IN (SELECT value FROM json_each(?))"#,
+            kind,
+            post_id,
+            event_id,
+            user_ids,
+        )
+        .fetch_one(db)
+        .await?)
+    }
+
     pub async fn have_sent_confirmation(db: &Db, event_id: i64, user_id: i64) -> Result<bool> {
         let row = sqlx::query!(
             "SELECT id FROM emails WHERE kind = ? AND event_id = ? AND user_id = ?",
@@ -315,6 +468,7 @@ impl Email {
     }
 
     /// Create email entries for resending the given post to all users on the given list.
+    /// Users who paused list email are skipped.
     pub async fn create_resend_posts(db: &Db, post_id: i64, list_id: i64) -> Result<Vec<Email>> {
         let existing_unsent = sqlx::query_as!(
             Email,
@@ -349,6 +503,7 @@ impl Email {
                 JOIN user_history uh ON uh.user_id = u.id
                   AND uh.version = (SELECT MAX(version) FROM user_history WHERE user_id = u.id)
                 WHERE lm.list_id = ?
+                  AND NOT EXISTS (SELECT 1 FROM email_pauses p WHERE p.user_id = u.id AND p.paused_until > CURRENT_TIMESTAMP)
                   AND NOT EXISTS (
                       SELECT 1
                       FROM emails e
@@ -0,0 +1,190 @@
+use crate::db::preference::PreferenceChange;
+use crate::prelude::*;
+
+/// A link target in bulk emails. Click redirects go through these so they can't point anywhere else.
+#[derive(Debug, sqlx::FromRow, serde::Serialize)]
+pub struct EmailLink {
+    pub id: i64,
+    pub url: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// A click on a tracked link.
+pub struct EmailClick;
+
+/// Delivery and engagement for every email of one post or one event's invites.
+#[derive(Debug, Default, serde::Serialize)]
+pub struct EmailReport {
+    pub totals: EmailReportRow,
+    /// Counts by the day each thing happened, oldest first.
+    pub days: Vec<EmailReportRow>,
+    pub links: Vec<EmailReportLink>,
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+pub struct EmailReportRow {
+    /// The day for rows of `EmailReport::days`, empty for totals.
+    pub day: String,
+    pub delivered: i64,
+    pub errored: i64,
+    pub opened: i64,
+    /// Emails with at least one click.
+    pub clicked: i64,
+    pub unsubscribed: i64,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct EmailReportLink {
+    pub url: String,
+    pub clicks: i64,
+    /// Emails the link was clicked from.
+    pub unique_clicks: i64,
+}
+
+impl EmailLink {
+    /// Lookup the link for a url, creating it on first use.
+    pub async fn lookup_or_create(db: &Db, url: &str) -> Result<i64> {
+        let id = sqlx::query_scalar!(
+            r#"INSERT INTO email_links (url) VALUES (?)
+               ON CONFLICT (url) DO UPDATE SET url = excluded.url
+               RETURNING id AS "id!: i64""#,
+            url
+        )
+        .fetch_one(db)
+        .await?;
+        Ok(id)
+    }
+
+    pub async fn lookup_by_id(db: &Db, id: i64) -> Result<Option<EmailLink>> {
+        Ok(sqlx::query_as!(Self, "SELECT * FROM email_links WHERE id = ?", id)
+            .fetch_optional(db)
+            .await?)
+    }
+}
+
+impl EmailClick {
+    pub async fn record(db: &Db, email_id: i64, link_id: i64) -> Result<()> {
+        sqlx::query!("INSERT INTO email_clicks (email_id, link_id) VALUES (?, ?)", email_id, link_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
+
+impl EmailReportRow {
+    /// A count as a percentage of delivered emails.
+    pub fn percent(&self, count: &i64) -> i64 {
+        if self.delivered == 0 { 0 } else { count * 100 / self.delivered }
+    }
+}
+
+impl EmailReport {
+    /// Maximum number of links listed in a report.
+    pub const TOP_LINKS: i64 = 10;
+
+    pub async fn for_post(db: &Db, post_id: i64) -> Result<EmailReport> {
+        Self::build(db, Email::POST, Some(post_id), None).await
+    }
+
+    pub async fn for_invites(db: &Db, event_id: i64) -> Result<EmailReport> {
+        Self::build(db, Email::EVENT_INVITE, None, Some(event_id)).await
+    }
+
+    async fn build(db: &Db, kind: &str, post_id: Option<i64>, event_id: Option<i64>) -> Result<EmailReport> {
+        let totals = sqlx::query_as!(
+            EmailReportRow,
+            r#"
+            SELECT
+                '' AS "day!: String",
+                COALESCE(SUM(e.sent_at IS NOT NULL AND e.error IS NULL), 0) AS "delivered!: i64",
+                COALESCE(SUM(e.error IS NOT NULL), 0) AS "errored!: i64",
+                COALESCE(SUM(e.opened_at IS NOT NULL), 0) AS "opened!: i64",
+                COALESCE(SUM(EXISTS (SELECT 1 FROM email_clicks c WHERE c.email_id = e.id)), 0) AS "clicked!: i64",
+                COALESCE(SUM(EXISTS (
+                    SELECT 1 FROM preference_changes p WHERE p.email_id = e.id AND p.change = ?
+                )), 0) AS "unsubscribed!: i64"
+            FROM emails e
+            WHERE e.kind = ? AND e.post_id IS ? AND e.event_id IS ?
+            "#,
+            PreferenceChange::UNSUBSCRIBE,
+            kind,
+            post_id,
+            event_id,
+        )
+        .fetch_one(db)
+        .await?;
+
+        let days = sqlx::query_as!(
+            EmailReportRow,
+            r#"
+            SELECT
+                day AS "day!: String",
+                SUM(delivered) AS "delivered!: i64",
+                SUM(errored) AS "errored!: i64",
+                SUM(opened) AS "opened!: i64",
+                SUM(clicked) AS "clicked!: i64",
+                SUM(unsubscribed) AS "unsubscribed!: i64"
+            FROM (
+                SELECT date(e.sent_at) AS day, e.error IS NULL AS delivered, e.error IS NOT NULL AS errored,
+                       0 AS opened, 0 AS clicked, 0 AS unsubscribed
+                FROM emails e
+                WHERE e.kind = ? AND e.post_id IS ? AND e.event_id IS ? AND e.sent_at IS NOT NULL
+                UNION ALL
+                SELECT date(e.opened_at), 0, 0, 1, 0, 0
+                FROM emails e
+                WHERE e.kind = ? AND e.post_id IS ? AND e.event_id IS ? AND e.opened_at IS NOT NULL
+                UNION ALL
+                SELECT date(MIN(c.created_at)), 0, 0, 0, 1, 0
+                FROM email_clicks c
+                JOIN emails e ON e.id = c.email_id
+                WHERE e.kind = ? AND e.post_id IS ? AND e.event_id IS ?
+                GROUP BY c.email_id
+                UNION ALL
+                SELECT date(p.created_at), 0, 0, 0, 0, 1
+                FROM preference_changes p
+                JOIN emails e ON e.id = p.email_id
+                WHERE e.kind = ? AND e.post_id IS ? AND e.event_id IS ? AND p.change = ?
+            )
+            GROUP BY day
+            ORDER BY day
+            "#,
+            kind,
+            post_id,
+            event_id,
+            kind,
+            post_id,
+            event_id,
+            kind,
+            post_id,
+            event_id,
+            kind,
+            post_id,
+            event_id,
+            PreferenceChange::UNSUBSCRIBE,
+        )
+        .fetch_all(db)
+        .await?;
+
+        let links = sqlx::query_as!(
+            EmailReportLink,
+            r#"
+            SELECT l.url, COUNT(*) AS "clicks!: i64", COUNT(DISTINCT c.email_id) AS "unique_clicks!: i64"
+            FROM email_clicks c
+            JOIN email_links l ON l.id = c.link_id
+            JOIN emails e ON e.id = c.email_id
+            WHERE e.kind = ? AND e.post_id IS ? AND e.event_id IS ?
+            GROUP BY l.id
+            ORDER BY 2 DESC, 3 DESC
+            LIMIT ?
+            "#,
+            kind,
+            post_id,
+            event_id,
+            Self::TOP_LINKS,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(EmailReport { totals, days, links })
+    }
+}
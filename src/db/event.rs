@@ -4,6 +4,12 @@ use rand::Rng;
 use rand::rngs::OsRng;
 
 use crate::db::event_flyer::EventFlyer;
+use crate::db::event_guest_list::EventGuestList;
+use crate::db::event_photo::EventPhoto;
+use crate::db::event_session::EventSession;
+use crate::db::event_staff::EventStaff;
+use crate::db::invite_link::InviteLink;
+use crate::db::revision::Revision;
 use crate::db::rsvp::EventRsvp;
 use crate::db::spot::Spot;
 use crate::prelude::*;
@@ -331,7 +337,7 @@ impl Event {
     }
 
     /// Delete an event and all related records (cascade delete).
-    /// Deletes: rsvps, rsvp_sessions, manual_rsvps, event_spots, event_flyers, then the event itself.
+    /// Deletes: rsvps, rsvp_sessions, manual_rsvps, event_spots, event_sessions, event_flyers, then the event itself.
     /// Note: emails are NOT deleted (kept for history).
     pub async fn delete(db: &Db, id: i64, slug: &str) -> Result<()> {
         // Delete RSVPs for this event (via sessions)
@@ -353,8 +359,19 @@ impl Event {
         sqlx::query!("DELETE FROM event_spots WHERE event_id = ?", id)
             .execute(db)
             .await?;
+        // Delete sessions with their spot links and check-ins
+        EventSession::delete_for_event(db, id).await?;
+        // Delete per-event staff grants
+        EventStaff::delete_for_event(db, id).await?;
+        // Delete further guest lists and invite links
+        EventGuestList::delete_for_event(db, id).await?;
+        InviteLink::delete_for_event(db, id).await?;
         // Delete event flyer
         EventFlyer::delete(db, id, slug).await?;
+        // Delete event photos
+        EventPhoto::delete_for_event(db, id).await?;
+        // Delete content history
+        Revision::delete_for_event(db, id).await?;
         // Finally delete the event itself
         sqlx::query!("DELETE FROM events WHERE id = ?", id).execute(db).await?;
         Ok(())
@@ -414,10 +431,17 @@ impl Event {
 pub struct EventLimits {
     pub total_limit: i64,
     pub spot_limits: HashMap<i64, i64>,
+    pub session_limits: Vec<SessionLimit>,
+}
+
+/// Remaining capacity of a session, shared by every spot that grants it.
+pub struct SessionLimit {
+    pub limit: i64,
+    pub spot_ids: Vec<i64>,
 }
 
 impl Event {
-    /// Duplicate an event, including spots and flyer.
+    /// Duplicate an event, including spots, sessions and flyer.
     /// Returns the ID of the new event.
     pub async fn duplicate(db: &Db, event_id: i64) -> Result<i64> {
         let event = Event::lookup_by_id(db, event_id)
@@ -480,22 +504,33 @@ impl Event {
         .last_insert_rowid();
 
         // Duplicate spots (create new spot records and link to new event)
-        Spot::duplicate_for_event(db, event_id, new_event_id).await?;
+        let spot_ids = Spot::duplicate_for_event(db, event_id, new_event_id).await?;
+
+        // Duplicate sessions, linked to the new spots
+        EventSession::duplicate_for_event(db, event_id, new_event_id, &spot_ids).await?;
+
+        // Duplicate further guest lists; invite links are personal, so they aren't copied
+        EventGuestList::duplicate_for_event(db, event_id, new_event_id).await?;
 
         // Duplicate flyer if exists
         EventFlyer::duplicate(db, event_id, &event.slug, new_event_id, &new_slug).await?;
 
+        // Carry over the current descriptions and emails as the start of their history
+        Revision::duplicate_for_event(db, event_id, new_event_id).await?;
+
         Ok(new_event_id)
     }
 }
 
 impl Event {
     /// Calculate number of spots available of each type for this event.
+    /// `sessions` = the event's timed sessions, each with its own capacity (may be empty).
     /// `all_rsvps` = all reserved RSVPs counted toward capacity (may or may not include current session).
     /// `user_rsvps` = this user's reserved RSVPs across all their sessions (for per-person limits).
     /// `manual_count` = all manually added RSVPs counted toward capacity
     pub fn compute_limits(
-        &self, spots: &[Spot], all_rsvps: &[EventRsvp], user_rsvps: &[EventRsvp], manual_count: i64,
+        &self, spots: &[Spot], sessions: &[EventSession], all_rsvps: &[EventRsvp], user_rsvps: &[EventRsvp],
+        manual_count: i64,
     ) -> EventLimits {
         // Overall event limits
         let capacity_limit = (self.capacity - all_rsvps.len() as i64 - manual_count).max(0);
@@ -515,6 +550,20 @@ impl Event {
             *user_spot_counts.entry(rsvp.spot_id).or_default() += 1;
         }
 
+        // Per-session limits. Every spot granting a session draws from its capacity,
+        // and manual RSVPs are admitted to every session.
+        let mut session_limits = vec![];
+        for session in sessions {
+            let spot_ids: Vec<i64> = spots
+                .iter()
+                .filter(|spot| session.grants(sessions, spot.id))
+                .map(|spot| spot.id)
+                .collect();
+            let num_rsvps: i64 = spot_ids.iter().map(|id| spot_num_rsvps.get(id).unwrap_or(&0)).sum();
+            let limit = (session.capacity - num_rsvps - manual_count).max(0);
+            session_limits.push(SessionLimit { limit, spot_ids });
+        }
+
         // Per-spot limits
         let mut sum_spot_limits = 0;
         let mut spot_limits = HashMap::default();
@@ -522,7 +571,12 @@ impl Event {
             let spot_total_limit = spot.qty_total - spot_num_rsvps.get(&spot.id).unwrap_or(&0);
             let spot_per_person_limit = spot.qty_per_person;
             let spot_this_person_limit = spot_per_person_limit - user_spot_counts.get(&spot.id).unwrap_or(&0);
-            let spot_limit = spot_total_limit.min(spot_this_person_limit);
+            let mut spot_limit = spot_total_limit.min(spot_this_person_limit);
+
+            // A spot is only available while every session it grants has room
+            for session_limit in session_limits.iter().filter(|l| l.spot_ids.contains(&spot.id)) {
+                spot_limit = spot_limit.min(session_limit.limit);
+            }
 
             sum_spot_limits += spot_limit;
             spot_limits.insert(spot.id, spot_limit);
@@ -531,6 +585,6 @@ impl Event {
         // Final limit is no more than the sum of all per-spot limits
         let limit = limit.min(sum_spot_limits);
 
-        EventLimits { total_limit: limit, spot_limits }
+        EventLimits { total_limit: limit, spot_limits, session_limits }
     }
 }
@@ -1,5 +1,5 @@
 use std::sync::LazyLock;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Instant;
 
 use base64::Engine;
@@ -10,6 +10,8 @@ use image::DynamicImage;
 use sqlx::Row;
 
 use crate::prelude::*;
+use crate::utils::blobs::blobs;
+use crate::utils::image::{crop, encode_avif, encode_jpeg, encode_webp};
 
 static CACHE: LazyLock<EventFlyerCache> = LazyLock::new(EventFlyerCache::default);
 
@@ -18,6 +20,9 @@ pub struct EventFlyer {
     pub height: i64,
     pub thumb_base64: String,
     pub version: i64,
+    /// Where crops center, as fractions of the width and height.
+    pub focal_x: f64,
+    pub focal_y: f64,
 }
 
 pub struct GalleryEventFlyer {
@@ -32,6 +37,21 @@ pub struct GalleryEventFlyer {
 impl EventFlyer {
     pub const CONTENT_TYPE: &'static str = "image/jpeg";
 
+    /// Formats served instead of JPEG when the `Accept` header allows, best first.
+    const VARIANT_FORMATS: [(&'static str, &'static str); 2] =
+        [("avif", "image/avif"), ("webp", "image/webp")];
+    /// Sizes with WebP and AVIF variants. Thumbs are only inlined as JPEG placeholders.
+    const VARIANT_SIZES: [(&'static str, Option<u32>); 4] = [
+        ("full", None),
+        ("lg", Some(1200)),
+        ("md", Some(600)),
+        ("sm", Some(300)),
+    ];
+    /// Square crop around the focal point, e.g. for feeds and avatars.
+    const SQUARE_SIZE: u32 = 600;
+    /// Open Graph crop around the focal point, the size social sites preview links at.
+    pub const OG_SIZE: (u32, u32) = (1200, 630);
+
     pub async fn create_or_update(
         db: &Db, event_id: i64, event_slug: &str, image: &DynamicImage,
     ) -> Result<()> {
@@ -41,36 +61,222 @@ impl EventFlyer {
         let image_full = crate::utils::image::encode_jpeg(image, None).await;
         let image_lg = crate::utils::image::encode_jpeg(image, Some(1200)).await;
         let image_md = crate::utils::image::encode_jpeg(image, Some(600)).await;
-        let image_sm = crate::utils::image::encode_jpeg(image, Some(300)).await;
-        let image_thumb = crate::utils::image::encode_jpeg(image, Some(60)).await;
+        let image_sm = Bytes::from(crate::utils::image::encode_jpeg(image, Some(300)).await);
+        let image_thumb = Bytes::from(crate::utils::image::encode_jpeg(image, Some(60)).await);
+
+        let full_hash = blobs().put(image_full.into()).await?;
+        let lg_hash = blobs().put(image_lg.into()).await?;
+        let md_hash = blobs().put(image_md.into()).await?;
+        let sm_hash = blobs().put(image_sm.clone()).await?;
+        let thumb_hash = blobs().put(image_thumb.clone()).await?;
+        let (square, og) = Self::encode_crops(image.clone(), (0.5, 0.5)).await?;
+        let square_hash = blobs().put(square).await?;
+        let og_hash = blobs().put(og).await?;
 
         sqlx::query!(
-            r#"INSERT INTO event_flyers  (event_id, width, height, image_full, image_lg, image_md, image_sm, image_thumb, updated_at)
-               VALUES (?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+            r#"INSERT INTO event_flyers
+               (event_id, width, height, full_hash, lg_hash, md_hash, sm_hash, thumb_hash, square_hash, og_hash, updated_at)
+               VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
                ON CONFLICT(event_id) DO UPDATE SET
                 width = excluded.width,
                 height = excluded.height,
-                image_full = excluded.image_full,
-                image_lg = excluded.image_lg,
-                image_md = excluded.image_md,
-                image_sm = excluded.image_sm,
-                image_thumb = excluded.image_thumb,
+                full_hash = excluded.full_hash,
+                lg_hash = excluded.lg_hash,
+                md_hash = excluded.md_hash,
+                sm_hash = excluded.sm_hash,
+                thumb_hash = excluded.thumb_hash,
+                square_hash = excluded.square_hash,
+                og_hash = excluded.og_hash,
+                focal_x = 0.5,
+                focal_y = 0.5,
                 updated_at = CURRENT_TIMESTAMP"#,
             event_id,
             width,
             height,
-            image_full,
-            image_lg,
-            image_md,
-            image_sm,
-            image_thumb
+            full_hash,
+            lg_hash,
+            md_hash,
+            sm_hash,
+            thumb_hash,
+            square_hash,
+            og_hash
+        )
+        .execute(db)
+        .await?;
+
+        // Variants are slow to encode, so the JPEGs are served until they're ready.
+        sqlx::query!("DELETE FROM event_flyer_variants WHERE event_id = ?", event_id)
+            .execute(db)
+            .await?;
+
+        CACHE.insert(event_slug, "thumb", image_thumb);
+        CACHE.insert(event_slug, "sm", image_sm);
+
+        tokio::spawn({
+            let db = db.clone();
+            async move {
+                if let Err(e) = Self::generate_variants(&db, event_id).await {
+                    tracing::error!("Failed to generate flyer variants for event {event_id}: {e:?}");
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Move the point an event's flyer is cropped around, regenerating its crops.
+    pub async fn set_focal_point(db: &Db, event_id: i64, focal: (f64, f64)) -> Result<()> {
+        let (focal_x, focal_y) = (focal.0.clamp(0.0, 1.0), focal.1.clamp(0.0, 1.0));
+        let res = sqlx::query!(
+            r#"UPDATE event_flyers SET focal_x = ?, focal_y = ?, updated_at = CURRENT_TIMESTAMP
+               WHERE event_id = ? AND (focal_x IS NOT ? OR focal_y IS NOT ?)"#,
+            focal_x,
+            focal_y,
+            event_id,
+            focal_x,
+            focal_y
+        )
+        .execute(db)
+        .await?;
+        if res.rows_affected() == 0 {
+            return Ok(());
+        }
+        Self::generate_crops(db, event_id).await
+    }
+
+    /// Encode the square and Open Graph crops of an event's flyer around its focal point.
+    async fn generate_crops(db: &Db, event_id: i64) -> Result<()> {
+        let Some(flyer) = sqlx::query!(
+            r#"SELECT full_hash, focal_x, focal_y FROM event_flyers WHERE event_id = ?"#,
+            event_id
+        )
+        .fetch_optional(db)
+        .await?
+        else {
+            return Ok(());
+        };
+        let Some(bytes) = blobs().get(&flyer.full_hash).await? else {
+            bail!("missing flyer blob {}", flyer.full_hash);
+        };
+
+        let image = crate::utils::image::decode(&bytes).await?;
+        let (square, og) = Self::encode_crops(image, (flyer.focal_x, flyer.focal_y)).await?;
+        let square_hash = blobs().put(square).await?;
+        let og_hash = blobs().put(og).await?;
+
+        // Skip flyers replaced or refocused while encoding, their own crops win.
+        sqlx::query!(
+            r#"UPDATE event_flyers SET square_hash = ?, og_hash = ?
+               WHERE event_id = ? AND full_hash = ? AND focal_x = ? AND focal_y = ?"#,
+            square_hash,
+            og_hash,
+            event_id,
+            flyer.full_hash,
+            flyer.focal_x,
+            flyer.focal_y
         )
         .execute(db)
         .await?;
+        Ok(())
+    }
 
-        CACHE.insert(event_slug, "image_thumb", image_thumb);
-        CACHE.insert(event_slug, "image_sm", image_sm);
+    async fn encode_crops(image: DynamicImage, focal: (f64, f64)) -> Result<(Bytes, Bytes)> {
+        let (square, og) = tokio::task::spawn_blocking(move || {
+            let square = crop(&image, Self::SQUARE_SIZE, Self::SQUARE_SIZE, focal);
+            let og = crop(&image, Self::OG_SIZE.0, Self::OG_SIZE.1, focal);
+            (square, og)
+        })
+        .await?;
+        Ok((encode_jpeg(&square, None).await.into(), encode_jpeg(&og, None).await.into()))
+    }
 
+    /// Encode the WebP and AVIF variants of an event's flyer.
+    async fn generate_variants(db: &Db, event_id: i64) -> Result<()> {
+        let Some(full_hash) =
+            sqlx::query_scalar!("SELECT full_hash FROM event_flyers WHERE event_id = ?", event_id)
+                .fetch_optional(db)
+                .await?
+        else {
+            return Ok(());
+        };
+        let Some(bytes) = blobs().get(&full_hash).await? else {
+            bail!("missing flyer blob {full_hash}");
+        };
+
+        let image = crate::utils::image::decode(&bytes).await?;
+        let variants = tokio::task::spawn_blocking(move || -> Result<Vec<_>> {
+            let mut variants = vec![];
+            for (size, max_w) in Self::VARIANT_SIZES {
+                variants.push((size, "webp", encode_webp(&image, max_w)));
+                variants.push((size, "avif", encode_avif(&image, max_w)?));
+            }
+            Ok(variants)
+        })
+        .await??;
+
+        for (size, format, bytes) in variants {
+            let hash = blobs().put(bytes.into()).await?;
+            // Skip flyers replaced while encoding, the replacement's variants are on their way.
+            sqlx::query!(
+                r#"INSERT OR REPLACE INTO event_flyer_variants (event_id, size, format, hash)
+                   SELECT event_id, ?, ?, ? FROM event_flyers WHERE event_id = ? AND full_hash = ?"#,
+                size,
+                format,
+                hash,
+                event_id,
+                full_hash,
+            )
+            .execute(db)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Generate variants for flyers missing any, one at a time to leave the CPU to requests.
+    pub async fn backfill_variants(db: &Db) -> Result<()> {
+        static RUNNING: AtomicBool = AtomicBool::new(false);
+        if RUNNING.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let result = Self::backfill_missing_variants(db).await;
+        RUNNING.store(false, Ordering::Relaxed);
+        result
+    }
+
+    async fn backfill_missing_variants(db: &Db) -> Result<()> {
+        let uncropped = sqlx::query_scalar!(
+            "SELECT event_id FROM event_flyers WHERE square_hash IS NULL OR og_hash IS NULL ORDER BY event_id DESC"
+        )
+        .fetch_all(db)
+        .await?;
+        for event_id in &uncropped {
+            if let Err(e) = Self::generate_crops(db, *event_id).await {
+                tracing::error!("Failed to generate flyer crops for event {event_id}: {e:?}");
+            }
+        }
+
+        let expected = (Self::VARIANT_SIZES.len() * Self::VARIANT_FORMATS.len()) as i64;
+        let event_ids = sqlx::query_scalar!(
+            r#"SELECT f.event_id
+               FROM event_flyers f
+               WHERE (SELECT COUNT(*) FROM event_flyer_variants v WHERE v.event_id = f.event_id) < ?
+               ORDER BY f.event_id DESC"#,
+            expected
+        )
+        .fetch_all(db)
+        .await?;
+        if event_ids.is_empty() {
+            return Ok(());
+        }
+
+        let start = Instant::now();
+        for event_id in &event_ids {
+            // One broken flyer shouldn't hold up the rest.
+            if let Err(e) = Self::generate_variants(db, *event_id).await {
+                tracing::error!("Failed to generate flyer variants for event {event_id}: {e:?}");
+            }
+        }
+        tracing::info!("Backfilled variants for {} flyers in {:?}", event_ids.len(), start.elapsed());
         Ok(())
     }
 
@@ -78,8 +284,11 @@ impl EventFlyer {
         sqlx::query!("DELETE FROM event_flyers WHERE event_id = ?", event_id)
             .execute(db)
             .await?;
-        CACHE.remove(event_slug, "image_thumb");
-        CACHE.remove(event_slug, "image_sm");
+        sqlx::query!("DELETE FROM event_flyer_variants WHERE event_id = ?", event_id)
+            .execute(db)
+            .await?;
+        CACHE.remove(event_slug, "thumb");
+        CACHE.remove(event_slug, "sm");
         Ok(())
     }
 
@@ -98,55 +307,99 @@ impl EventFlyer {
     }
 
     pub async fn lookup(db: &Db, event_id: i64) -> Result<Option<EventFlyer>> {
-        let row = sqlx::query!(
-            r#"SELECT width, height, image_thumb, strftime('%s', updated_at) as "version!: i64"
+        let Some(row) = sqlx::query!(
+            r#"SELECT width, height, thumb_hash, focal_x, focal_y, strftime('%s', updated_at) as "version!: i64"
                FROM event_flyers WHERE event_id = ?"#,
             event_id
         )
         .fetch_optional(db)
-        .await?;
+        .await?
+        else {
+            return Ok(None);
+        };
 
-        Ok(row.map(|r| EventFlyer {
-            width: r.width,
-            height: r.height,
-            thumb_base64: format!("data:image/jpeg;base64,{}", BASE64.encode(&r.image_thumb)),
-            version: r.version,
+        let thumb = blobs().get(&row.thumb_hash).await?.unwrap_or_default();
+        Ok(Some(EventFlyer {
+            width: row.width,
+            height: row.height,
+            thumb_base64: format!("data:image/jpeg;base64,{}", BASE64.encode(&thumb)),
+            version: row.version,
+            focal_x: row.focal_x,
+            focal_y: row.focal_y,
         }))
     }
 
-    // Serve a flyer image by slug.
-    pub async fn serve(db: &Db, slug: &str, size: Option<&String>) -> HtmlResult {
+    // Serve a flyer image by slug, as AVIF or WebP when `accept` allows and the variant is ready.
+    // `square` and `og` are crops around the focal point, and only JPEG.
+    pub async fn serve(db: &Db, slug: &str, size: Option<&String>, accept: Option<&str>) -> HtmlResult {
         let size = match size.map(|s| s.as_str()) {
-            Some("thumb") => "image_thumb",
-            Some("sm") => "image_sm",
-            Some("md") => "image_md",
-            Some("lg") => "image_lg",
+            Some(size @ ("thumb" | "sm" | "md" | "lg" | "square" | "og")) => size,
             Some(_) => bail_invalid!(),
-            None => "image_full",
+            None => "full",
         };
+        let headers = |content_type| {
+            [
+                (header::CONTENT_TYPE, content_type),
+                (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+                (header::VARY, "Accept"),
+                (HeaderName::from_static("priority"), "u=1"),
+            ]
+        };
+
+        if Self::VARIANT_SIZES.iter().any(|(variant_size, _)| *variant_size == size) {
+            let accept = accept.unwrap_or_default();
+            for (format, content_type) in Self::VARIANT_FORMATS {
+                if !accept.contains(content_type) {
+                    continue;
+                }
+                let hash = sqlx::query_scalar!(
+                    r#"SELECT v.hash
+                       FROM event_flyer_variants v
+                       JOIN events e ON e.id = v.event_id
+                       WHERE e.slug = ? AND v.size = ? AND v.format = ?"#,
+                    slug,
+                    size,
+                    format
+                )
+                .fetch_optional(db)
+                .await?;
+                if let Some(hash) = hash
+                    && let Some(bytes) = blobs().get(&hash).await?
+                {
+                    return Ok((headers(content_type), bytes).into_response());
+                }
+            }
+        }
 
         let bytes = match CACHE.get(slug, size) {
             Some(bytes) => bytes,
             None => {
-                let query = format!(
-                    "SELECT f.{size} FROM event_flyers f JOIN events e ON e.id = f.event_id WHERE e.slug = ?"
-                );
-                match sqlx::query(&query).bind(slug).fetch_optional(db).await? {
-                    Some(row) => Bytes::from(row.try_get::<Vec<u8>, _>(0)?),
-                    None => bail_not_found!(),
-                }
+                let hash = sqlx::query_scalar!(
+                    r#"SELECT CASE ?1
+                           WHEN 'thumb' THEN f.thumb_hash
+                           WHEN 'sm' THEN f.sm_hash
+                           WHEN 'md' THEN f.md_hash
+                           WHEN 'lg' THEN f.lg_hash
+                           WHEN 'square' THEN f.square_hash
+                           WHEN 'og' THEN f.og_hash
+                           ELSE f.full_hash
+                       END AS "hash: String"
+                       FROM event_flyers f JOIN events e ON e.id = f.event_id
+                       WHERE e.slug = ?2"#,
+                    size,
+                    slug
+                )
+                .fetch_optional(db)
+                .await?;
+                // Crops are missing until generated.
+                let Some(Some(hash)) = hash else {
+                    bail_not_found!();
+                };
+                blobs().get(&hash).await?.ok_or_else(not_found)?
             }
         };
 
-        Ok((
-            [
-                (header::CONTENT_TYPE, EventFlyer::CONTENT_TYPE),
-                (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
-                (HeaderName::from_static("priority"), "u=1"),
-            ],
-            bytes,
-        )
-            .into_response())
+        Ok((headers(EventFlyer::CONTENT_TYPE), bytes).into_response())
     }
 
     /// Returns a counter that increments any time a flyer is added, modified, or removed.
@@ -163,32 +416,46 @@ impl EventFlyer {
         });
     }
 
-    pub async fn version_for_event(db: &Db, event_id: i64) -> Result<Option<i64>> {
-        let row = sqlx::query!(
-            r#"SELECT strftime('%s', updated_at) as "version!: i64" FROM event_flyers WHERE event_id = ?"#,
+    /// The version and byte length of an event's medium JPEG flyer, to link it from feeds.
+    pub async fn enclosure(db: &Db, event_id: i64) -> Result<Option<(i64, u64)>> {
+        let Some(row) = sqlx::query!(
+            r#"SELECT md_hash, strftime('%s', updated_at) as "version!: i64" FROM event_flyers WHERE event_id = ?"#,
             event_id
         )
         .fetch_optional(db)
-        .await?;
-        Ok(row.map(|r| r.version))
+        .await?
+        else {
+            return Ok(None);
+        };
+        let length = blobs().size(&row.md_hash).await?.unwrap_or_default();
+        Ok(Some((row.version, length)))
     }
 
-    /// Duplicate a flyer from one event to another.
+    /// Duplicate a flyer from one event to another, sharing its blobs.
     /// Does nothing if the source event has no flyer.
     pub async fn duplicate(
         db: &Db, old_event_id: i64, old_slug: &str, new_event_id: i64, new_slug: &str,
     ) -> Result<()> {
         sqlx::query!(
-            r#"INSERT INTO event_flyers (event_id, width, height, image_full, image_lg, image_md, image_sm, image_thumb, updated_at)
-               SELECT ?, width, height, image_full, image_lg, image_md, image_sm, image_thumb, CURRENT_TIMESTAMP
+            r#"INSERT INTO event_flyers
+               (event_id, width, height, full_hash, lg_hash, md_hash, sm_hash, thumb_hash, focal_x, focal_y, square_hash, og_hash, updated_at)
+               SELECT ?, width, height, full_hash, lg_hash, md_hash, sm_hash, thumb_hash, focal_x, focal_y, square_hash, og_hash, CURRENT_TIMESTAMP
                FROM event_flyers WHERE event_id = ?"#,
             new_event_id,
             old_event_id
         )
         .execute(db)
         .await?;
+        sqlx::query!(
+            r#"INSERT INTO event_flyer_variants (event_id, size, format, hash)
+               SELECT ?, size, format, hash FROM event_flyer_variants WHERE event_id = ?"#,
+            new_event_id,
+            old_event_id
+        )
+        .execute(db)
+        .await?;
 
-        for size in ["image_thumb", "image_sm"] {
+        for size in ["thumb", "sm"] {
             if let Some(bytes) = CACHE.get(old_slug, size) {
                 CACHE.insert(new_slug, size, bytes);
             }
@@ -196,10 +463,112 @@ impl EventFlyer {
 
         Ok(())
     }
+
+    /// Move images out of the tables renamed by the `move_flyer_blobs_to_store` migration and
+    /// into the blob store, then drop them. Each flyer moves in its own transaction, so an
+    /// interrupted run picks up where it left off.
+    pub async fn migrate_legacy_blobs(db: &Db) -> Result<()> {
+        // The legacy tables are gone once this finished, so these queries can't be checked at compile time.
+        let pending: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'event_flyers_legacy')",
+        )
+        .fetch_one(db)
+        .await?;
+        if !pending {
+            return Ok(());
+        }
+        let start = Instant::now();
+
+        let event_ids: Vec<i64> = sqlx::query_scalar("SELECT event_id FROM event_flyers_legacy")
+            .fetch_all(db)
+            .await?;
+        for event_id in &event_ids {
+            let row = sqlx::query(
+                "SELECT image_thumb, image_sm, image_md, image_lg, image_full FROM event_flyers_legacy WHERE event_id = ?",
+            )
+            .bind(event_id)
+            .fetch_one(db)
+            .await?;
+            let mut hashes = vec![];
+            for column in 0..5 {
+                hashes.push(blobs().put(row.try_get::<Vec<u8>, _>(column)?.into()).await?);
+            }
+
+            let mut tx = db.begin().await?;
+            sqlx::query(
+                r#"INSERT INTO event_flyers
+                   (event_id, width, height, thumb_hash, sm_hash, md_hash, lg_hash, full_hash, created_at, updated_at)
+                   SELECT event_id, width, height, ?, ?, ?, ?, ?, created_at, updated_at
+                   FROM event_flyers_legacy WHERE event_id = ?"#,
+            )
+            .bind(&hashes[0])
+            .bind(&hashes[1])
+            .bind(&hashes[2])
+            .bind(&hashes[3])
+            .bind(&hashes[4])
+            .bind(event_id)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("DELETE FROM event_flyers_legacy WHERE event_id = ?")
+                .bind(event_id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+        }
+
+        let variants: Vec<(i64, String, String)> =
+            sqlx::query_as("SELECT event_id, size, format FROM event_flyer_variants_legacy")
+                .fetch_all(db)
+                .await?;
+        for (event_id, size, format) in &variants {
+            let image: Vec<u8> = sqlx::query_scalar(
+                "SELECT image FROM event_flyer_variants_legacy WHERE event_id = ? AND size = ? AND format = ?",
+            )
+            .bind(event_id)
+            .bind(size)
+            .bind(format)
+            .fetch_one(db)
+            .await?;
+            let hash = blobs().put(image.into()).await?;
+
+            let mut tx = db.begin().await?;
+            sqlx::query(
+                "INSERT INTO event_flyer_variants (event_id, size, format, hash) VALUES (?, ?, ?, ?)",
+            )
+            .bind(event_id)
+            .bind(size)
+            .bind(format)
+            .bind(&hash)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(
+                "DELETE FROM event_flyer_variants_legacy WHERE event_id = ? AND size = ? AND format = ?",
+            )
+            .bind(event_id)
+            .bind(size)
+            .bind(format)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+        }
+
+        sqlx::query("DROP TABLE event_flyers_legacy").execute(db).await?;
+        sqlx::query("DROP TABLE event_flyer_variants_legacy").execute(db).await?;
+        // Give the space the images took back to the filesystem.
+        sqlx::query("VACUUM").execute(db).await?;
+
+        tracing::info!(
+            "Moved {} flyers and {} variants to the blob store in {:?}",
+            event_ids.len(),
+            variants.len(),
+            start.elapsed()
+        );
+        Ok(())
+    }
 }
 
-// Flyer image bytes cache (slug, size_column) -> Bytes.
-// Only "image_thumb" and "image_sm" are cached (~12mb for ~350 flyers at the time of writing).
+// Flyer image bytes cache (slug, size) -> Bytes.
+// Only "thumb" and "sm" JPEGs are cached (~12mb for ~350 flyers at the time of writing).
 #[derive(Default)]
 struct EventFlyerCache {
     map: DashMap<(&'static str, &'static str), Bytes>,
@@ -237,7 +606,7 @@ impl EventFlyerCache {
         let start = Instant::now();
 
         let rows = sqlx::query!(
-            r#"SELECT e.slug, f.image_thumb, f.image_sm
+            r#"SELECT e.slug, f.thumb_hash, f.sm_hash
                FROM event_flyers f
                JOIN events e ON e.id = f.event_id"#
         )
@@ -245,8 +614,12 @@ impl EventFlyerCache {
         .await?;
 
         for r in rows {
-            self.insert(&r.slug, "image_thumb", r.image_thumb);
-            self.insert(&r.slug, "image_sm", r.image_sm);
+            if let Some(bytes) = blobs().get(&r.thumb_hash).await? {
+                self.insert(&r.slug, "thumb", bytes);
+            }
+            if let Some(bytes) = blobs().get(&r.sm_hash).await? {
+                self.insert(&r.slug, "sm", bytes);
+            }
         }
 
         tracing::info!("Populated flyer cache in {:?}", start.elapsed());
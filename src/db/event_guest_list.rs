@@ -0,0 +1,82 @@
+use crate::db::event::Event;
+use crate::prelude::*;
+
+/// Further lists admitted to a private event, on top of its `guest_list_id`.
+pub struct EventGuestList;
+
+impl EventGuestList {
+    /// Ids of the lists admitted besides the event's `guest_list_id`.
+    pub async fn list_ids_for_event(db: &Db, event_id: i64) -> Result<Vec<i64>> {
+        Ok(sqlx::query!(
+            "SELECT list_id FROM event_guest_lists WHERE event_id = ? ORDER BY list_id",
+            event_id
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|row| row.list_id)
+        .collect())
+    }
+
+    /// Replace the further lists admitted to an event.
+    pub async fn set_for_event(db: &Db, event_id: i64, list_ids: &[i64]) -> Result<()> {
+        Self::delete_for_event(db, event_id).await?;
+        for list_id in list_ids {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO event_guest_lists (event_id, list_id) VALUES (?, ?)",
+                event_id,
+                list_id
+            )
+            .execute(db)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Whether a user may RSVP to a private event: they're on one of its guest lists, or they
+    /// redeemed one of its invite links.
+    pub async fn admits(db: &Db, event: &Event, user_id: i64) -> Result<bool> {
+        let admitted = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM list_members lm
+                WHERE lm.user_id = ?
+                  AND (lm.list_id = ? OR lm.list_id IN (SELECT list_id FROM event_guest_lists WHERE event_id = ?))
+                UNION ALL
+                SELECT 1
+                FROM event_invite_redemptions r
+                JOIN event_invite_links l ON l.id = r.link_id
+                WHERE r.user_id = ? AND l.event_id = ?
+            ) AS "exists!: bool"
+            "#,
+            user_id,
+            event.guest_list_id,
+            event.id,
+            user_id,
+            event.id,
+        )
+        .fetch_one(db)
+        .await?;
+        Ok(admitted)
+    }
+
+    pub async fn duplicate_for_event(db: &Db, source_event_id: i64, target_event_id: i64) -> Result<()> {
+        sqlx::query!(
+            r#"INSERT INTO event_guest_lists (event_id, list_id)
+               SELECT ?, list_id FROM event_guest_lists WHERE event_id = ?"#,
+            target_event_id,
+            source_event_id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_for_event(db: &Db, event_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM event_guest_lists WHERE event_id = ?", event_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use bytes::Bytes;
+use image::DynamicImage;
+
+use crate::prelude::*;
+use crate::utils::blobs::blobs;
+use crate::utils::image::encode_jpeg;
+
+/// A photo from an event, stored in the same sizes as flyers. Photos never change once uploaded,
+/// so their urls are cached for good.
+#[derive(Debug, serde::Serialize)]
+pub struct EventPhoto {
+    pub id: i64,
+    pub event_id: i64,
+    pub position: i64,
+    pub caption: String,
+    /// Shown first and largest.
+    pub cover: bool,
+    pub width: i64,
+    pub height: i64,
+    pub created_at: NaiveDateTime,
+}
+
+impl EventPhoto {
+    pub const CONTENT_TYPE: &'static str = "image/jpeg";
+    /// Largest body accepted by the event form, which bulk uploads photos.
+    pub const UPLOAD_LIMIT: usize = 256 * 1024 * 1024;
+
+    /// Add a photo after the event's others.
+    pub async fn create(db: &Db, event_id: i64, image: &DynamicImage) -> Result<i64> {
+        let width = image.width();
+        let height = image.height();
+
+        let full_hash = blobs().put(encode_jpeg(image, None).await.into()).await?;
+        let lg_hash = blobs().put(encode_jpeg(image, Some(1200)).await.into()).await?;
+        let md_hash = blobs().put(encode_jpeg(image, Some(600)).await.into()).await?;
+        let sm_hash = blobs().put(encode_jpeg(image, Some(300)).await.into()).await?;
+        let thumb_hash = blobs().put(encode_jpeg(image, Some(60)).await.into()).await?;
+
+        let res = sqlx::query!(
+            r#"INSERT INTO event_photos
+               (event_id, position, width, height, thumb_hash, sm_hash, md_hash, lg_hash, full_hash)
+               VALUES (?1, (SELECT COALESCE(MAX(position), -1) + 1 FROM event_photos WHERE event_id = ?1),
+                       ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
+            event_id,
+            width,
+            height,
+            thumb_hash,
+            sm_hash,
+            md_hash,
+            lg_hash,
+            full_hash,
+        )
+        .execute(db)
+        .await?;
+        Ok(res.last_insert_rowid())
+    }
+
+    /// List an event's photos in their display order.
+    pub async fn list_for_event(db: &Db, event_id: i64) -> Result<Vec<EventPhoto>> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"SELECT id, event_id, position, caption, cover, width, height, created_at
+               FROM event_photos
+               WHERE event_id = ?
+               ORDER BY position, id"#,
+            event_id
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    pub async fn update(db: &Db, id: i64, caption: &str, position: i64) -> Result<()> {
+        sqlx::query!(
+            "UPDATE event_photos SET caption = ?, position = ? WHERE id = ?",
+            caption,
+            position,
+            id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Make one of an event's photos its cover, or none.
+    pub async fn set_cover(db: &Db, event_id: i64, photo_id: Option<i64>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE event_photos SET cover = (id IS ?) WHERE event_id = ?",
+            photo_id,
+            event_id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete(db: &Db, id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM event_photos WHERE id = ?", id).execute(db).await?;
+        Ok(())
+    }
+
+    pub async fn delete_for_event(db: &Db, event_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM event_photos WHERE event_id = ?", event_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Serve a photo of the event with `slug`.
+    pub async fn serve(db: &Db, slug: &str, id: i64, size: Option<&String>) -> HtmlResult {
+        let size = match size.map(|s| s.as_str()) {
+            Some(size @ ("thumb" | "sm" | "md" | "lg")) => size,
+            Some(_) => bail_invalid!(),
+            None => "full",
+        };
+        let hash = sqlx::query_scalar!(
+            r#"SELECT CASE ?1
+                   WHEN 'thumb' THEN p.thumb_hash
+                   WHEN 'sm' THEN p.sm_hash
+                   WHEN 'md' THEN p.md_hash
+                   WHEN 'lg' THEN p.lg_hash
+                   ELSE p.full_hash
+               END AS "hash!: String"
+               FROM event_photos p JOIN events e ON e.id = p.event_id
+               WHERE e.slug = ?2 AND p.id = ?3"#,
+            size,
+            slug,
+            id
+        )
+        .fetch_optional(db)
+        .await?;
+        let Some(hash) = hash else {
+            bail_not_found!();
+        };
+        let bytes: Bytes = blobs().get(&hash).await?.ok_or_else(not_found)?;
+
+        Ok((
+            [
+                (header::CONTENT_TYPE, Self::CONTENT_TYPE),
+                (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+            ],
+            bytes,
+        )
+            .into_response())
+    }
+}
@@ -0,0 +1,229 @@
+use sqlx::QueryBuilder;
+
+use crate::prelude::*;
+
+/// A timed session within an event, e.g. one day of a festival or one workshop slot.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct EventSession {
+    pub id: i64,
+    pub event_id: i64,
+    pub name: String,
+    pub start: NaiveDateTime,
+    pub end: Option<NaiveDateTime>,
+    pub capacity: i64,
+    pub sort: i64,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+
+    /// Spots explicitly linked to this session.
+    pub spot_ids: Vec<i64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct UpdateEventSession {
+    pub id: Option<i64>,
+    pub name: String,
+    pub start: NaiveDateTime,
+    pub end: Option<NaiveDateTime>,
+    pub capacity: i64,
+    pub sort: i64,
+}
+
+impl EventSession {
+    /// Whether `spot_id` grants access to this session.
+    /// Spots not linked to any session are full passes and grant every session.
+    pub fn grants(&self, sessions: &[EventSession], spot_id: i64) -> bool {
+        self.spot_ids.contains(&spot_id) || !sessions.iter().any(|s| s.spot_ids.contains(&spot_id))
+    }
+
+    pub async fn list_for_event(db: &Db, event_id: i64) -> Result<Vec<EventSession>> {
+        let rows = sqlx::query!(
+            r#"SELECT
+                 es.id AS "id!", es.event_id AS "event_id!", es.name AS "name!",
+                 es.start AS "start!: NaiveDateTime", es.end AS "end?: NaiveDateTime",
+                 es.capacity AS "capacity!", es.sort AS "sort!",
+                 es.created_at AS "created_at!: NaiveDateTime", es.updated_at AS "updated_at!: NaiveDateTime",
+                 COALESCE(GROUP_CONCAT(ss.spot_id), '') AS "spot_ids!: String"
+               FROM event_sessions es
+               LEFT JOIN spot_sessions ss ON ss.event_session_id = es.id
+               WHERE es.event_id = ?
+               GROUP BY es.id
+               ORDER BY es.sort"#,
+            event_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| EventSession {
+                id: row.id,
+                event_id: row.event_id,
+                name: row.name,
+                start: row.start,
+                end: row.end,
+                capacity: row.capacity,
+                sort: row.sort,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+                spot_ids: row.spot_ids.split(',').filter_map(|s| s.parse().ok()).collect(),
+            })
+            .collect())
+    }
+
+    pub async fn list_ids_for_event(db: &Db, event_id: i64) -> Result<Vec<i64>> {
+        Ok(sqlx::query!("SELECT id FROM event_sessions WHERE event_id = ?", event_id)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|row| row.id)
+            .collect())
+    }
+
+    pub async fn create(db: &Db, event_id: i64, session: &UpdateEventSession) -> Result<i64> {
+        let row = sqlx::query!(
+            r#"INSERT INTO event_sessions (event_id, name, start, end, capacity, sort)
+               VALUES (?, ?, ?, ?, ?, ?)"#,
+            event_id,
+            session.name,
+            session.start,
+            session.end,
+            session.capacity,
+            session.sort,
+        )
+        .execute(db)
+        .await?;
+        Ok(row.last_insert_rowid())
+    }
+
+    pub async fn update(db: &Db, id: i64, session: &UpdateEventSession) -> Result<()> {
+        sqlx::query!(
+            "UPDATE event_sessions
+               SET name = ?,
+                   start = ?,
+                   end = ?,
+                   capacity = ?,
+                   sort = ?,
+                   updated_at = CURRENT_TIMESTAMP
+               WHERE id = ?",
+            session.name,
+            session.start,
+            session.end,
+            session.capacity,
+            session.sort,
+            id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Replace the spots linked to a session.
+    pub async fn set_spots(db: &Db, id: i64, spot_ids: Vec<i64>) -> Result<()> {
+        sqlx::query!("DELETE FROM spot_sessions WHERE event_session_id = ?", id)
+            .execute(db)
+            .await?;
+        if spot_ids.is_empty() {
+            return Ok(());
+        }
+
+        QueryBuilder::new("INSERT INTO spot_sessions (spot_id, event_session_id) ")
+            .push_values(spot_ids, |mut b, spot_id| {
+                b.push_bind(spot_id).push_bind(id);
+            })
+            .build()
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Delete sessions along with their spot links and check-ins.
+    pub async fn delete(db: &Db, ids: Vec<i64>) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        for table in ["spot_sessions", "event_session_checkins"] {
+            QueryBuilder::new(format!("DELETE FROM {table} WHERE event_session_id IN "))
+                .push_tuples(&ids, |mut b, id| {
+                    b.push_bind(id);
+                })
+                .build()
+                .execute(db)
+                .await?;
+        }
+        QueryBuilder::new("DELETE FROM event_sessions WHERE id IN ")
+            .push_tuples(&ids, |mut b, id| {
+                b.push_bind(id);
+            })
+            .build()
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_for_event(db: &Db, event_id: i64) -> Result<()> {
+        let ids = Self::list_ids_for_event(db, event_id).await?;
+        Self::delete(db, ids).await
+    }
+
+    /// Duplicate all sessions from one event to another.
+    /// `spot_ids` maps source spot ids to their copies so spot links carry over.
+    pub async fn duplicate_for_event(
+        db: &Db, source_event_id: i64, target_event_id: i64, spot_ids: &HashMap<i64, i64>,
+    ) -> Result<()> {
+        for session in Self::list_for_event(db, source_event_id).await? {
+            let new_id = Self::create(
+                db,
+                target_event_id,
+                &UpdateEventSession {
+                    id: None,
+                    name: session.name,
+                    start: session.start,
+                    end: session.end,
+                    capacity: session.capacity,
+                    sort: session.sort,
+                },
+            )
+            .await?;
+            let new_spot_ids = session.spot_ids.iter().filter_map(|id| spot_ids.get(id).copied()).collect();
+            Self::set_spots(db, new_id, new_spot_ids).await?;
+        }
+        Ok(())
+    }
+
+    /// Check-in times for a session by user_id.
+    pub async fn list_checkins(db: &Db, id: i64) -> Result<HashMap<i64, NaiveDateTime>> {
+        Ok(sqlx::query!(
+            "SELECT user_id, checkin_at FROM event_session_checkins WHERE event_session_id = ?",
+            id
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|row| (row.user_id, row.checkin_at))
+        .collect())
+    }
+
+    pub async fn set_checkin_at(db: &Db, id: i64, user_id: i64) -> Result<()> {
+        sqlx::query!(
+            "INSERT OR IGNORE INTO event_session_checkins (event_session_id, user_id) VALUES (?, ?)",
+            id,
+            user_id,
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn clear_checkin_at(db: &Db, id: i64, user_id: i64) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM event_session_checkins WHERE event_session_id = ? AND user_id = ?",
+            id,
+            user_id,
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+}
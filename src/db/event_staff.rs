@@ -0,0 +1,115 @@
+use crate::db::event::Event;
+use crate::prelude::*;
+
+/// A user granted access to a single event, e.g. a door volunteer.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct EventStaff {
+    pub event_id: i64,
+    pub user_id: i64,
+    pub role: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// A staff grant with user details, for the attendees page.
+#[derive(Debug, serde::Serialize)]
+pub struct EventStaffMember {
+    pub user_id: i64,
+    pub email: String,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub role: String,
+}
+
+impl EventStaff {
+    /// Can view the attendee list and check attendees in.
+    pub const CHECKIN: &'static str = "checkin";
+    /// Everything check-in staff can do, plus the stats page.
+    pub const ORGANIZER: &'static str = "organizer";
+
+    pub fn is_valid_role(role: &str) -> bool {
+        [Self::CHECKIN, Self::ORGANIZER].contains(&role)
+    }
+
+    pub fn is_organizer(&self) -> bool {
+        self.role == Self::ORGANIZER
+    }
+
+    /// Per-event equivalent of `User::has_permission`, only `EventsCheckin` can be granted.
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        permission == Permission::EventsCheckin
+    }
+
+    /// Lookup a user's grant for an event, ignoring grants for events that are over.
+    pub async fn lookup_active(db: &Db, event: &Event, user_id: i64) -> Result<Option<EventStaff>> {
+        if event.is_over() {
+            return Ok(None);
+        }
+        Ok(sqlx::query_as!(
+            EventStaff,
+            "SELECT * FROM event_staff WHERE event_id = ? AND user_id = ?",
+            event.id,
+            user_id
+        )
+        .fetch_optional(db)
+        .await?)
+    }
+
+    /// List a user's grants along with their events, skipping events that are over.
+    pub async fn list_active_for_user(db: &Db, user_id: i64) -> Result<Vec<(EventStaff, Event)>> {
+        let grants = sqlx::query_as!(EventStaff, "SELECT * FROM event_staff WHERE user_id = ?", user_id)
+            .fetch_all(db)
+            .await?;
+
+        let mut active = vec![];
+        for grant in grants {
+            if let Some(event) = Event::lookup_by_id(db, grant.event_id).await?
+                && !event.is_over()
+            {
+                active.push((grant, event));
+            }
+        }
+        active.sort_by_key(|(_, event)| event.start);
+        Ok(active)
+    }
+
+    pub async fn list_for_event(db: &Db, event_id: i64) -> Result<Vec<EventStaffMember>> {
+        Ok(sqlx::query_as!(
+            EventStaffMember,
+            r#"SELECT s.user_id, u.email, u.first_name, u.last_name, s.role
+               FROM event_staff s
+               JOIN users u ON u.id = s.user_id
+               WHERE s.event_id = ?
+               ORDER BY s.created_at"#,
+            event_id
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// Grant a user access to an event, replacing any existing grant.
+    pub async fn grant(db: &Db, event_id: i64, user_id: i64, role: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT OR REPLACE INTO event_staff (event_id, user_id, role) VALUES (?, ?, ?)",
+            event_id,
+            user_id,
+            role
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn revoke(db: &Db, event_id: i64, user_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM event_staff WHERE event_id = ? AND user_id = ?", event_id, user_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_for_event(db: &Db, event_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM event_staff WHERE event_id = ?", event_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
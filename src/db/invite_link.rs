@@ -0,0 +1,175 @@
+use rand::Rng;
+use rand::rngs::OsRng;
+
+use crate::prelude::*;
+
+/// A personal link onto a private event's guest list, bypassing the list check.
+#[derive(Clone, Debug, sqlx::FromRow, serde::Serialize)]
+pub struct InviteLink {
+    pub id: i64,
+    pub event_id: i64,
+    pub token: String,
+    /// Who the link is for, e.g. "Press" or "Plus-one for Sam".
+    pub label: String,
+    pub max_uses: Option<i64>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub created_by: i64,
+    pub created_at: NaiveDateTime,
+}
+
+/// An invite link with who redeemed it, for the admin page.
+#[derive(Debug, serde::Serialize)]
+pub struct InviteLinkWithRedemptions {
+    pub link: InviteLink,
+    pub redemptions: Vec<InviteRedemption>,
+}
+
+/// A user who redeemed an invite link.
+#[derive(Debug, serde::Serialize)]
+pub struct InviteRedemption {
+    pub link_id: i64,
+    pub user_id: i64,
+    pub email: String,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl InviteLink {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= Utc::now().naive_utc())
+    }
+
+    pub async fn create(
+        db: &Db, event_id: i64, label: &str, max_uses: Option<i64>, expires_at: Option<NaiveDateTime>,
+        created_by: i64,
+    ) -> Result<i64> {
+        let token = format!("{:08x}", OsRng.r#gen::<u64>());
+        let res = sqlx::query!(
+            r#"INSERT INTO event_invite_links (event_id, token, label, max_uses, expires_at, created_by)
+               VALUES (?, ?, ?, ?, ?, ?)"#,
+            event_id,
+            token,
+            label,
+            max_uses,
+            expires_at,
+            created_by,
+        )
+        .execute(db)
+        .await?;
+        Ok(res.last_insert_rowid())
+    }
+
+    pub async fn lookup_by_id(db: &Db, id: i64) -> Result<Option<InviteLink>> {
+        Ok(sqlx::query_as!(Self, "SELECT * FROM event_invite_links WHERE id = ?", id)
+            .fetch_optional(db)
+            .await?)
+    }
+
+    pub async fn lookup_by_token(db: &Db, event_id: i64, token: &str) -> Result<Option<InviteLink>> {
+        Ok(sqlx::query_as!(
+            Self,
+            "SELECT * FROM event_invite_links WHERE event_id = ? AND token = ?",
+            event_id,
+            token
+        )
+        .fetch_optional(db)
+        .await?)
+    }
+
+    /// List an event's links, newest first, along with who redeemed each.
+    pub async fn list_for_event(db: &Db, event_id: i64) -> Result<Vec<InviteLinkWithRedemptions>> {
+        let links = sqlx::query_as!(
+            Self,
+            "SELECT * FROM event_invite_links WHERE event_id = ? ORDER BY id DESC",
+            event_id
+        )
+        .fetch_all(db)
+        .await?;
+        let redemptions = sqlx::query_as!(
+            InviteRedemption,
+            r#"SELECT r.link_id, r.user_id, u.email, u.first_name, u.last_name, r.created_at
+               FROM event_invite_redemptions r
+               JOIN event_invite_links l ON l.id = r.link_id
+               JOIN users u ON u.id = r.user_id
+               WHERE l.event_id = ?
+               ORDER BY r.created_at"#,
+            event_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let mut by_link: HashMap<i64, Vec<InviteRedemption>> = HashMap::new();
+        for redemption in redemptions {
+            by_link.entry(redemption.link_id).or_default().push(redemption);
+        }
+        Ok(links
+            .into_iter()
+            .map(|link| {
+                let redemptions = by_link.remove(&link.id).unwrap_or_default();
+                InviteLinkWithRedemptions { link, redemptions }
+            })
+            .collect())
+    }
+
+    /// Record a user redeeming the link. Returns false if the link is expired or used up, unless
+    /// this user already redeemed it: a redemption admits them for good and only counts once.
+    pub async fn redeem(&self, db: &Db, user_id: i64) -> Result<bool> {
+        let res = sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO event_invite_redemptions (link_id, user_id)
+                SELECT l.id, ?
+                FROM event_invite_links l
+                WHERE l.id = ?
+                  AND (l.expires_at IS NULL OR l.expires_at > CURRENT_TIMESTAMP)
+                  AND (
+                      l.max_uses IS NULL
+                      OR (SELECT COUNT(*) FROM event_invite_redemptions r WHERE r.link_id = l.id) < l.max_uses
+                  )
+            "#,
+            user_id,
+            self.id,
+        )
+        .execute(db)
+        .await?;
+        if res.rows_affected() > 0 {
+            return Ok(true);
+        }
+
+        // Nothing inserted: either the link is unusable, or this user already redeemed it.
+        let redeemed = sqlx::query_scalar!(
+            r#"SELECT EXISTS (
+                   SELECT 1 FROM event_invite_redemptions WHERE link_id = ? AND user_id = ?
+               ) AS "exists!: bool""#,
+            self.id,
+            user_id
+        )
+        .fetch_one(db)
+        .await?;
+        Ok(redeemed)
+    }
+
+    /// Delete a link. Its redemptions go too, so redeemers no longer get past the guest list.
+    pub async fn delete(db: &Db, id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM event_invite_redemptions WHERE link_id = ?", id)
+            .execute(db)
+            .await?;
+        sqlx::query!("DELETE FROM event_invite_links WHERE id = ?", id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_for_event(db: &Db, event_id: i64) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM event_invite_redemptions WHERE link_id IN (SELECT id FROM event_invite_links WHERE event_id = ?)",
+            event_id
+        )
+        .execute(db)
+        .await?;
+        sqlx::query!("DELETE FROM event_invite_links WHERE event_id = ?", event_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
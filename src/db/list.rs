@@ -1,3 +1,6 @@
+use rand::Rng;
+use rand::rngs::OsRng;
+
 use crate::db::user::CreateUser;
 use crate::prelude::*;
 
@@ -8,6 +11,16 @@ pub struct List {
     pub description: String,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+
+    /// Public lists can be joined by anyone from `/lists/{slug}/signup` and the directory.
+    pub slug: String,
+    pub public: bool,
+    pub signup_title: Option<String>,
+    pub signup_description: Option<String>,
+    /// Shown after signing up, instead of the default message.
+    pub confirmation_message: Option<String>,
+    /// Track opens and clicks of posts and invites sent to the list.
+    pub tracking: bool,
 }
 
 #[derive(serde::Deserialize)]
@@ -15,9 +28,22 @@ pub struct UpdateList {
     pub id: Option<i64>,
     pub name: String,
     pub description: String,
+    pub slug: String,
+    #[serde(default)]
+    pub public: bool,
+    pub signup_title: String,
+    pub signup_description: String,
+    pub confirmation_message: String,
+    #[serde(default)]
+    pub tracking: bool,
     pub emails: String,
 }
 
+/// Treat blank optional form fields as unset.
+fn non_empty(s: &str) -> Option<&str> {
+    Some(s.trim()).filter(|s| !s.is_empty())
+}
+
 #[derive(serde::Serialize)]
 pub struct ListWithCount {
     pub id: i64,
@@ -25,24 +51,34 @@ pub struct ListWithCount {
     pub description: String,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    /// Confirmed members.
     pub count: i64,
+    /// Signups still waiting on their confirmation link.
+    pub pending: i64,
 }
 
+/// Unconfirmed signups for one or more lists, sharing a confirmation token.
+pub struct ListSignup;
+
 impl List {
-    /// List all lists.
-    pub async fn list(db: &Db) -> Result<Vec<List>> {
-        let lists = sqlx::query_as!(Self, "SELECT * FROM lists").fetch_all(db).await?;
+    /// List the lists anyone can sign up for.
+    pub async fn list_public(db: &Db) -> Result<Vec<List>> {
+        let lists = sqlx::query_as!(Self, "SELECT * FROM lists WHERE public ORDER BY name")
+            .fetch_all(db)
+            .await?;
         Ok(lists)
     }
 
     /// List all lists, and count the number of members in each list via list_members join.
     pub async fn list_with_counts(db: &Db) -> Result<Vec<ListWithCount>> {
+        let cutoff = ListSignup::expiry_cutoff();
         let lists = sqlx::query_as!(
             ListWithCount,
-            "SELECT l.*, COUNT(m.list_id) AS count
-             FROM lists l
-             LEFT JOIN list_members m ON l.id = m.list_id
-             GROUP BY l.id"
+            r#"SELECT l.id, l.name, l.description, l.created_at, l.updated_at,
+                      (SELECT COUNT(*) FROM list_members m WHERE m.list_id = l.id) AS "count!: i64",
+                      (SELECT COUNT(*) FROM list_signups s WHERE s.list_id = l.id AND s.created_at >= ?) AS "pending!: i64"
+               FROM lists l"#,
+            cutoff
         )
         .fetch_all(db)
         .await?;
@@ -51,12 +87,21 @@ impl List {
 
     /// Create a list.
     pub async fn create(db: &Db, event: &UpdateList) -> Result<i64> {
+        let signup_title = non_empty(&event.signup_title);
+        let signup_description = non_empty(&event.signup_description);
+        let confirmation_message = non_empty(&event.confirmation_message);
         let res = sqlx::query!(
             r#"INSERT INTO lists
-               (name, description)
-               VALUES (?, ?)"#,
+               (name, description, slug, public, signup_title, signup_description, confirmation_message, tracking)
+               VALUES (?, ?, ?, ?, ?, ?, ?, ?)"#,
             event.name,
-            event.description
+            event.description,
+            event.slug,
+            event.public,
+            signup_title,
+            signup_description,
+            confirmation_message,
+            event.tracking,
         )
         .execute(db)
         .await?;
@@ -65,12 +110,22 @@ impl List {
 
     /// Update a list.
     pub async fn update(db: &Db, id: i64, event: &UpdateList) -> Result<()> {
+        let signup_title = non_empty(&event.signup_title);
+        let signup_description = non_empty(&event.signup_description);
+        let confirmation_message = non_empty(&event.confirmation_message);
         sqlx::query!(
             r#"UPDATE lists
-               SET name = ?, description = ?
+               SET name = ?, description = ?, slug = ?, public = ?,
+                   signup_title = ?, signup_description = ?, confirmation_message = ?, tracking = ?
                WHERE id = ?"#,
             event.name,
             event.description,
+            event.slug,
+            event.public,
+            signup_title,
+            signup_description,
+            confirmation_message,
+            event.tracking,
             id
         )
         .execute(db)
@@ -78,6 +133,20 @@ impl List {
         Ok(())
     }
 
+    /// Whether opens and clicks may be tracked for email sent to these lists: none opted out.
+    pub async fn tracking_allowed(db: &Db, list_ids: &[i64]) -> Result<bool> {
+        let list_ids = serde_json::to_string(list_ids)?;
+        let allowed = sqlx::query_scalar!(
+            r#"SELECT NOT EXISTS (
+                   SELECT 1 FROM lists WHERE id IN (SELECT value FROM json_each(?)) AND NOT tracking
+               ) AS "allowed!: bool""#,
+            list_ids
+        )
+        .fetch_one(db)
+        .await?;
+        Ok(allowed)
+    }
+
     /// Lookup a list by id, if one exists.
     pub async fn lookup_by_id(db: &Db, id: i64) -> Result<Option<List>> {
         let list = sqlx::query_as!(
@@ -92,26 +161,28 @@ impl List {
         Ok(list)
     }
 
-    /// Lookup the members of a list.
-    pub async fn list_members(db: &Db, list_id: i64) -> Result<Vec<User>> {
-        User::lookup_by_list_id(db, list_id).await
+    /// Lookup a list by slug, if one exists.
+    pub async fn lookup_by_slug(db: &Db, slug: &str) -> Result<Option<List>> {
+        let list = sqlx::query_as!(Self, "SELECT * FROM lists WHERE slug = ?", slug)
+            .fetch_optional(db)
+            .await?;
+        Ok(list)
     }
 
-    pub async fn has_user_id(db: &Db, id: i64, user_id: i64) -> Result<bool> {
-        let exists = sqlx::query_scalar!(
-            r#"
-            SELECT EXISTS(
-                SELECT 1
-                FROM list_members
-                WHERE list_id = ? AND user_id = ?
-            ) AS "exists!: bool"
-            "#,
-            id,
-            user_id,
+    /// List the lists a user is a member of.
+    pub async fn list_for_user(db: &Db, user_id: i64) -> Result<Vec<List>> {
+        Ok(sqlx::query_as!(
+            Self,
+            "SELECT l.* FROM lists l JOIN list_members lm ON lm.list_id = l.id WHERE lm.user_id = ? ORDER BY l.name",
+            user_id
         )
-        .fetch_one(db)
-        .await?;
-        Ok(exists)
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// Lookup the members of a list.
+    pub async fn list_members(db: &Db, list_id: i64) -> Result<Vec<User>> {
+        User::lookup_by_list_id(db, list_id).await
     }
 
     pub async fn has_email(db: &Db, id: i64, email: &str) -> Result<bool> {
@@ -166,3 +237,68 @@ impl List {
         Ok(())
     }
 }
+
+impl ListSignup {
+    /// Signups created before this are expired.
+    pub fn expiry_cutoff() -> NaiveDateTime {
+        Utc::now().naive_utc() - chrono::Duration::hours(config().app.list_signup_expiry_hours as i64)
+    }
+
+    /// Record pending signups for a user, replacing any earlier ones for the same lists.
+    /// Returns the confirmation token.
+    pub async fn create(db: &Db, user_id: i64, list_ids: &[i64]) -> Result<String> {
+        let token = format!("{:08x}", OsRng.r#gen::<u64>());
+        for list_id in list_ids {
+            sqlx::query!(
+                "INSERT OR REPLACE INTO list_signups (list_id, user_id, token) VALUES (?, ?, ?)",
+                list_id,
+                user_id,
+                token
+            )
+            .execute(db)
+            .await?;
+        }
+        Ok(token)
+    }
+
+    /// Lookup the lists an unexpired token would confirm.
+    pub async fn lookup_lists(db: &Db, token: &str) -> Result<Vec<List>> {
+        let cutoff = Self::expiry_cutoff();
+        Ok(sqlx::query_as!(
+            List,
+            r#"SELECT l.* FROM lists l
+               JOIN list_signups s ON s.list_id = l.id
+               WHERE s.token = ? AND s.created_at >= ?
+               ORDER BY l.name"#,
+            token,
+            cutoff
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// Confirm the signups for a token, adding the user to their lists.
+    pub async fn confirm(db: &Db, token: &str) -> Result<()> {
+        let cutoff = Self::expiry_cutoff();
+        sqlx::query!(
+            r#"INSERT OR IGNORE INTO list_members (list_id, user_id)
+               SELECT list_id, user_id FROM list_signups WHERE token = ? AND created_at >= ?"#,
+            token,
+            cutoff
+        )
+        .execute(db)
+        .await?;
+        sqlx::query!("DELETE FROM list_signups WHERE token = ?", token)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_expired(db: &Db) -> Result<()> {
+        let cutoff = Self::expiry_cutoff();
+        sqlx::query!("DELETE FROM list_signups WHERE created_at < ?", cutoff)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
@@ -6,18 +6,32 @@ use crate::utils::config::DbConfig;
 
 pub type Db = SqlitePool;
 
+pub mod audit_log;
+pub mod blob;
 pub mod email;
+pub mod email_click;
 pub mod event;
 pub mod event_flyer;
+pub mod event_guest_list;
+pub mod event_photo;
+pub mod event_session;
+pub mod event_staff;
+pub mod invite_link;
 pub mod list;
 pub mod manual_rsvp;
 pub mod notification;
 pub mod post;
+pub mod preference;
+pub mod revision;
+pub mod role;
 pub mod rsvp;
 pub mod rsvp_session;
+pub mod segment;
 pub mod spot;
+pub mod ticket;
 pub mod token;
 pub mod user;
+pub mod user_export;
 
 /// Create a new db connection pool, initializing and running migrations if necessary.
 pub async fn init(db_config: &DbConfig) -> Result<Db> {
@@ -29,6 +43,10 @@ pub async fn init(db_config: &DbConfig) -> Result<Db> {
 
     sqlx::migrate!("./migrations").run(&db).await?;
 
+    // Flyer images live in the blob store, move any still in the database from before it.
+    crate::utils::blobs::init(&db_config.blobs_dir).await?;
+    event_flyer::EventFlyer::migrate_legacy_blobs(&db).await?;
+
     if let Some(seed_data) = &db_config.seed_data {
         let sql = tokio::fs::read_to_string(seed_data).await?;
         sqlx::raw_sql(&sql).execute(&db).await?;
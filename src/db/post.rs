@@ -1,3 +1,4 @@
+use crate::db::revision::Revision;
 use crate::prelude::*;
 
 #[derive(Clone, Debug, sqlx::FromRow, serde::Serialize)]
@@ -9,6 +10,10 @@ pub struct Post {
     pub content: String,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    /// One of `DRAFT`, `SCHEDULED` or `PUBLISHED`.
+    pub status: String,
+    /// When the post went or goes public. Kept when unpublishing, so republishing doesn't bump it.
+    pub published_at: Option<NaiveDateTime>,
 }
 
 #[derive(serde::Deserialize)]
@@ -20,20 +25,53 @@ pub struct UpdatePost {
 }
 
 impl Post {
-    // List all posts.
-    pub async fn list(db: &Db) -> Result<Vec<Post>> {
-        let posts = sqlx::query_as!(Self, "SELECT * FROM posts ORDER BY updated_at DESC")
+    pub const DRAFT: &'static str = "draft";
+    pub const SCHEDULED: &'static str = "scheduled";
+    pub const PUBLISHED: &'static str = "published";
+    pub const STATUSES: [&'static str; 3] = [Self::DRAFT, Self::SCHEDULED, Self::PUBLISHED];
+
+    /// Whether anyone can read the post.
+    pub fn is_published(&self) -> bool {
+        self.status == Self::PUBLISHED
+    }
+
+    // List all posts, or only those with a status.
+    pub async fn list(db: &Db, status: Option<&str>) -> Result<Vec<Post>> {
+        let posts = sqlx::query_as!(
+            Self,
+            "SELECT * FROM posts WHERE ?1 IS NULL OR status = ?1 ORDER BY updated_at DESC",
+            status
+        )
+        .fetch_all(db)
+        .await?;
+        Ok(posts)
+    }
+
+    /// List published posts, newest first.
+    pub async fn list_published(db: &Db) -> Result<Vec<Post>> {
+        let posts = sqlx::query_as!(
+            Self,
+            "SELECT * FROM posts WHERE status = 'published' ORDER BY published_at DESC"
+        )
+        .fetch_all(db)
+        .await?;
+        Ok(posts)
+    }
+
+    /// Count posts by status.
+    pub async fn count_by_status(db: &Db) -> Result<HashMap<String, i64>> {
+        let rows = sqlx::query!(r#"SELECT status, COUNT(*) AS "count!: i64" FROM posts GROUP BY status"#)
             .fetch_all(db)
             .await?;
-        Ok(posts)
+        Ok(rows.into_iter().map(|row| (row.status, row.count)).collect())
     }
 
-    /// Create a new post.
+    /// Create a new draft post.
     pub async fn create(db: &Db, post: &UpdatePost) -> Result<(i64, NaiveDateTime)> {
         let row = sqlx::query!(
             r#"INSERT INTO posts
-               (title, slug, author, content)
-               VALUES (?, ?, ?, ?)
+               (title, slug, author, content, status)
+               VALUES (?, ?, ?, ?, 'draft')
                RETURNING id, updated_at"#,
             post.title,
             post.slug,
@@ -67,10 +105,63 @@ impl Post {
         Ok((row.id, row.updated_at))
     }
 
+    /// Move a post to a status. Scheduling takes the time to publish at. Publishing keeps a past
+    /// publish time, so unpublishing and republishing doesn't bump the post, and otherwise uses now.
+    pub async fn set_status(db: &Db, id: i64, status: &str, publish_at: Option<NaiveDateTime>) -> Result<()> {
+        match status {
+            Self::DRAFT => {
+                sqlx::query!("UPDATE posts SET status = 'draft' WHERE id = ?", id)
+                    .execute(db)
+                    .await?;
+            }
+            Self::SCHEDULED => {
+                let Some(publish_at) = publish_at else {
+                    bail!("scheduling post {} without a time", id);
+                };
+                sqlx::query!(
+                    "UPDATE posts SET status = 'scheduled', published_at = ? WHERE id = ?",
+                    publish_at,
+                    id
+                )
+                .execute(db)
+                .await?;
+            }
+            Self::PUBLISHED => {
+                sqlx::query!(
+                    r#"UPDATE posts
+                       SET published_at = CASE
+                             WHEN published_at <= CURRENT_TIMESTAMP THEN published_at
+                             ELSE CURRENT_TIMESTAMP
+                           END,
+                         status = 'published'
+                       WHERE id = ?"#,
+                    id
+                )
+                .execute(db)
+                .await?;
+            }
+            _ => {
+                bail!("unknown post status {:?}", status);
+            }
+        }
+        Ok(())
+    }
+
+    /// Publish scheduled posts whose time has come, returning their ids.
+    pub async fn publish_scheduled(db: &Db) -> Result<Vec<i64>> {
+        Ok(sqlx::query_scalar!(
+            r#"UPDATE posts SET status = 'published'
+               WHERE status = 'scheduled' AND published_at <= CURRENT_TIMESTAMP
+               RETURNING id"#
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
     /// Delete a post.
     pub async fn delete(db: &Db, id: i64) -> Result<()> {
         sqlx::query!("DELETE FROM posts WHERE id = ?", id).execute(db).await?;
-        Ok(())
+        Revision::delete_for_post(db, id).await
     }
 
     /// Lookup a post by URL, if one exists.
@@ -80,4 +171,12 @@ impl Post {
             .await?;
         Ok(row)
     }
+
+    /// Lookup a post by id, if one exists.
+    pub async fn lookup_by_id(db: &Db, id: i64) -> Result<Option<Post>> {
+        let row = sqlx::query_as!(Self, "SELECT * FROM posts WHERE id = ?", id)
+            .fetch_optional(db)
+            .await?;
+        Ok(row)
+    }
 }
@@ -0,0 +1,74 @@
+use crate::prelude::*;
+
+/// A change made from the email preference center.
+#[derive(Debug, sqlx::FromRow, serde::Serialize)]
+pub struct PreferenceChange {
+    pub id: i64,
+    pub user_id: i64,
+    /// The email whose link was used to make the change.
+    pub email_id: i64,
+    pub change: String,
+    pub list_id: Option<i64>,
+    pub created_at: NaiveDateTime,
+}
+
+impl PreferenceChange {
+    pub const SUBSCRIBE: &'static str = "subscribe";
+    pub const UNSUBSCRIBE: &'static str = "unsubscribe";
+    pub const PAUSE: &'static str = "pause";
+    pub const RESUME: &'static str = "resume";
+    pub const NAME: &'static str = "name";
+
+    pub async fn record(db: &Db, email: &Email, change: &str, list_id: Option<i64>) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO preference_changes (user_id, email_id, change, list_id) VALUES (?, ?, ?, ?)",
+            email.user_id,
+            email.id,
+            change,
+            list_id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Pausing all list email for a while.
+pub struct EmailPause;
+
+impl EmailPause {
+    /// How long "pause all email" lasts.
+    pub const DAYS: i64 = 30;
+
+    /// When the user's pause ends, if they're paused right now.
+    pub async fn lookup(db: &Db, user_id: i64) -> Result<Option<NaiveDateTime>> {
+        let now = Utc::now().naive_utc();
+        Ok(sqlx::query_scalar!(
+            r#"SELECT paused_until AS "paused_until: NaiveDateTime" FROM email_pauses
+               WHERE user_id = ? AND paused_until > ?"#,
+            user_id,
+            now
+        )
+        .fetch_optional(db)
+        .await?)
+    }
+
+    pub async fn pause(db: &Db, user_id: i64) -> Result<()> {
+        let until = Utc::now().naive_utc() + chrono::Duration::days(Self::DAYS);
+        sqlx::query!(
+            "INSERT OR REPLACE INTO email_pauses (user_id, paused_until) VALUES (?, ?)",
+            user_id,
+            until
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn resume(db: &Db, user_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM email_pauses WHERE user_id = ?", user_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
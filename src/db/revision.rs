@@ -0,0 +1,106 @@
+use crate::prelude::*;
+
+/// A saved version of editor content, so earlier versions can be compared and restored.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Revision {
+    pub id: i64,
+    /// The editor's snapshot prefix, one of `KINDS`.
+    pub kind: String,
+    pub entity_id: i64,
+    /// Only set for emails.
+    pub subject: Option<String>,
+    pub html: String,
+    pub author_id: Option<i64>,
+    pub author_email: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Revision {
+    pub const POST: &'static str = "post";
+    pub const EVENT_DESCRIPTION: &'static str = "event/description";
+    pub const EVENT_INVITE: &'static str = "event/invite";
+    pub const EVENT_CONFIRMATION: &'static str = "event/confirmation";
+    pub const EVENT_DAYOF: &'static str = "event/dayof";
+
+    /// The kind for an event field as it appears in urls, e.g. `invite` in `/events/1/invite/edit`.
+    pub fn event_kind(field: &str) -> Option<&'static str> {
+        match field {
+            "description" => Some(Self::EVENT_DESCRIPTION),
+            "invite" => Some(Self::EVENT_INVITE),
+            "confirmation" => Some(Self::EVENT_CONFIRMATION),
+            "dayof" => Some(Self::EVENT_DAYOF),
+            _ => None,
+        }
+    }
+
+    /// Record a save, unless nothing changed since the last one.
+    pub async fn record(
+        db: &Db, kind: &str, entity_id: i64, subject: Option<&str>, html: &str, author: &User,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"INSERT INTO revisions (kind, entity_id, subject, html, author_id)
+               SELECT ?1, ?2, ?3, ?4, ?5
+               WHERE NOT EXISTS (
+                   SELECT 1 FROM revisions
+                   WHERE id = (SELECT MAX(id) FROM revisions WHERE kind = ?1 AND entity_id = ?2)
+                     AND subject IS ?3 AND html = ?4
+               )"#,
+            kind,
+            entity_id,
+            subject,
+            html,
+            author.id,
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// List the revisions of some content, newest first.
+    pub async fn list(db: &Db, kind: &str, entity_id: i64) -> Result<Vec<Revision>> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"SELECT r.id, r.kind, r.entity_id, r.subject, r.html, r.author_id,
+                 u.email AS "author_email?", r.created_at
+               FROM revisions r LEFT JOIN users u ON u.id = r.author_id
+               WHERE r.kind = ? AND r.entity_id = ?
+               ORDER BY r.id DESC"#,
+            kind,
+            entity_id
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    pub async fn delete_for_post(db: &Db, post_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM revisions WHERE kind = 'post' AND entity_id = ?", post_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Start a duplicated event's histories with the latest revision of each field.
+    pub async fn duplicate_for_event(db: &Db, from_event_id: i64, to_event_id: i64) -> Result<()> {
+        sqlx::query!(
+            r#"INSERT INTO revisions (kind, entity_id, subject, html, author_id, created_at)
+               SELECT kind, ?2, subject, html, author_id, created_at FROM revisions
+               WHERE id IN (
+                   SELECT MAX(id) FROM revisions
+                   WHERE kind LIKE 'event/%' AND entity_id = ?1
+                   GROUP BY kind
+               )"#,
+            from_event_id,
+            to_event_id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_for_event(db: &Db, event_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM revisions WHERE kind LIKE 'event/%' AND entity_id = ?", event_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+use crate::prelude::*;
+
+/// A permission granted by roles, checked by `AppRouter::restricted_routes`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Permission {
+    /// Create and edit events, their emails and attendees.
+    EventsEdit,
+    /// View attendees and check them in.
+    EventsCheckin,
+    /// Create and edit lists and their members.
+    ListsEdit,
+    /// Send posts and invites to lists.
+    ListsSend,
+    /// Write and publish posts.
+    PostsPublish,
+    /// Assign roles to users.
+    UsersManage,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 6] = [
+        Self::EventsEdit,
+        Self::EventsCheckin,
+        Self::ListsEdit,
+        Self::ListsSend,
+        Self::PostsPublish,
+        Self::UsersManage,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::EventsEdit => "events.edit",
+            Self::EventsCheckin => "events.checkin",
+            Self::ListsEdit => "lists.edit",
+            Self::ListsSend => "lists.send",
+            Self::PostsPublish => "posts.publish",
+            Self::UsersManage => "users.manage",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_str() == s)
+    }
+}
+
+/// A named set of permissions.
+#[derive(Debug, serde::Serialize)]
+pub struct Role {
+    pub name: String,
+    pub description: String,
+    pub permissions: Vec<Permission>,
+}
+
+/// A user holding a role, for the roles admin page.
+#[derive(Debug, sqlx::FromRow, serde::Serialize)]
+pub struct RoleAssignment {
+    pub role: String,
+    pub user_id: i64,
+    pub email: String,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Role {
+    /// Has every permission, including ones added after the role was seeded.
+    pub const ADMIN: &'static str = "admin";
+
+    pub async fn list(db: &Db) -> Result<Vec<Role>> {
+        let rows = sqlx::query!(
+            r#"SELECT r.name, r.description, COALESCE(GROUP_CONCAT(rp.permission), '') AS "permissions!: String"
+               FROM roles r
+               LEFT JOIN role_permissions rp ON rp.role = r.name
+               GROUP BY r.name
+               ORDER BY r.created_at, r.name"#
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Role {
+                name: row.name,
+                description: row.description,
+                permissions: row.permissions.split(',').filter_map(Permission::parse).collect(),
+            })
+            .collect())
+    }
+
+    pub async fn exists(db: &Db, name: &str) -> Result<bool> {
+        let row = sqlx::query!("SELECT name FROM roles WHERE name = ?", name)
+            .fetch_optional(db)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    pub async fn list_assignments(db: &Db) -> Result<Vec<RoleAssignment>> {
+        Ok(sqlx::query_as!(
+            RoleAssignment,
+            r#"SELECT ur.role, u.id AS user_id, u.email, u.first_name, u.last_name, ur.created_at
+               FROM user_roles ur
+               JOIN users u ON u.id = ur.user_id
+               ORDER BY ur.role, u.email"#
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// How many users hold a role.
+    pub async fn count_holders(db: &Db, role: &str) -> Result<i64> {
+        Ok(
+            sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!: i64" FROM user_roles WHERE role = ?"#, role)
+                .fetch_one(db)
+                .await?,
+        )
+    }
+
+    pub async fn assign(db: &Db, user_id: i64, role: &str) -> Result<()> {
+        sqlx::query!("INSERT OR IGNORE INTO user_roles (user_id, role) VALUES (?, ?)", user_id, role)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn unassign(db: &Db, user_id: i64, role: &str) -> Result<()> {
+        sqlx::query!("DELETE FROM user_roles WHERE user_id = ? AND role = ?", user_id, role)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
@@ -93,6 +93,7 @@ pub struct AdminAttendeesRsvp {
     pub session_token: Option<String>,
     pub status: String,
     pub note: Option<String>,
+    pub spot_id: i64,
 }
 
 pub struct AttendeeEdit {
@@ -146,7 +147,8 @@ impl Rsvp {
                 rs.id AS "session_id!: i64",
                 rs.token AS session_token,
                 rs.status AS "status!",
-                r.note AS "note?: String"
+                r.note AS "note?: String",
+                r.spot_id AS "spot_id!: i64"
             FROM rsvps r
             JOIN rsvp_sessions rs ON rs.id = r.session_id
             JOIN spots sp ON sp.id = r.spot_id
@@ -173,7 +175,8 @@ impl Rsvp {
                 0 AS "session_id!: i64",
                 NULL AS session_token,
                 '' AS "status!",
-                mr.note
+                mr.note,
+                0 AS "spot_id!: i64"
             FROM manual_rsvps mr
             JOIN users u ON u.id = mr.user_id
             JOIN users cu ON cu.id = mr.creator_user_id
@@ -0,0 +1,262 @@
+use crate::db::rsvp_session::RsvpSession;
+use crate::prelude::*;
+
+/// Rules selecting the members of a segment. Every rule that is set must match.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SegmentRules {
+    /// Members of these lists. Required, so segments only reach people who signed up for email.
+    pub lists: Vec<i64>,
+    /// Require membership of every list instead of any one of them.
+    #[serde(default)]
+    pub match_all_lists: bool,
+    /// RSVP'd to any of these events.
+    #[serde(default)]
+    pub attended_events: Vec<i64>,
+    /// RSVP'd to any event within this many months.
+    #[serde(default)]
+    pub rsvp_within_months: Option<i64>,
+    /// Opened none of the last 3 posts or invites they were sent.
+    #[serde(default)]
+    pub unopened_recent: bool,
+    /// Skip people who already RSVP'd to the event being invited to.
+    #[serde(default)]
+    pub exclude_event_rsvps: bool,
+}
+
+/// A saved audience for posts and invites.
+#[derive(Debug, serde::Serialize)]
+pub struct Segment {
+    pub id: i64,
+    pub name: String,
+    pub rules: SegmentRules,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+struct SegmentRow {
+    id: i64,
+    name: String,
+    rules: String,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+impl SegmentRow {
+    fn parse(self) -> Result<Segment> {
+        Ok(Segment {
+            id: self.id,
+            name: self.name,
+            rules: serde_json::from_str(&self.rules)?,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+/// A segment with its current size, for the send pages.
+#[derive(Debug, serde::Serialize)]
+pub struct SegmentCount {
+    pub id: i64,
+    pub name: String,
+    pub count: i64,
+    /// Members already sent the post or invite being sent.
+    pub sent: i64,
+}
+
+/// Who a post or invite is sent to, parsed from the `audience` field of the send forms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+pub enum Audience {
+    List(i64),
+    Segment(i64),
+}
+
+impl Audience {
+    /// Parse values like `list-1` or `segment-2`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (kind, id) = s.split_once('-')?;
+        let id = id.parse().ok()?;
+        match kind {
+            "list" => Some(Self::List(id)),
+            "segment" => Some(Self::Segment(id)),
+            _ => None,
+        }
+    }
+
+    /// The lists whose tracking preferences apply to email sent to this audience.
+    pub async fn list_ids(self, db: &Db) -> Result<Vec<i64>> {
+        Ok(match self {
+            Self::List(list_id) => vec![list_id],
+            Self::Segment(segment_id) => match Segment::lookup_by_id(db, segment_id).await? {
+                Some(segment) => segment.rules.lists,
+                None => vec![],
+            },
+        })
+    }
+}
+
+impl Segment {
+    pub async fn list(db: &Db) -> Result<Vec<Segment>> {
+        sqlx::query_as!(SegmentRow, "SELECT * FROM segments ORDER BY name")
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(SegmentRow::parse)
+            .collect()
+    }
+
+    pub async fn lookup_by_id(db: &Db, id: i64) -> Result<Option<Segment>> {
+        sqlx::query_as!(SegmentRow, "SELECT * FROM segments WHERE id = ?", id)
+            .fetch_optional(db)
+            .await?
+            .map(SegmentRow::parse)
+            .transpose()
+    }
+
+    pub async fn create(db: &Db, name: &str, rules: &SegmentRules) -> Result<i64> {
+        let rules = serde_json::to_string(rules)?;
+        let res = sqlx::query!("INSERT INTO segments (name, rules) VALUES (?, ?)", name, rules)
+            .execute(db)
+            .await?;
+        Ok(res.last_insert_rowid())
+    }
+
+    pub async fn update(db: &Db, id: i64, name: &str, rules: &SegmentRules) -> Result<()> {
+        let rules = serde_json::to_string(rules)?;
+        sqlx::query!(
+            "UPDATE segments SET name = ?, rules = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+            name,
+            rules,
+            id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Delete a segment. Emails already sent to it keep their `segment_id`.
+    pub async fn delete(db: &Db, id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM segments WHERE id = ?", id).execute(db).await?;
+        Ok(())
+    }
+
+    /// Count the members of every segment, and how many were already sent a post or invite.
+    pub async fn list_with_counts(
+        db: &Db, kind: &str, post_id: Option<i64>, event_id: Option<i64>,
+    ) -> Result<Vec<SegmentCount>> {
+        let mut counts = vec![];
+        for segment in Self::list(db).await? {
+            let user_ids = segment.resolve(db, event_id).await?;
+            let sent = Email::count_sent_to(db, kind, post_id, event_id, &user_ids).await?;
+            counts.push(SegmentCount {
+                id: segment.id,
+                name: segment.name,
+                count: user_ids.len() as i64,
+                sent,
+            });
+        }
+        Ok(counts)
+    }
+
+    /// The ids of the users currently matching the segment's rules.
+    ///
+    /// "Attended" means a held reservation (payment pending or confirmed) or a manual RSVP, so
+    /// abandoned carts and refunds don't count. `event_id` is the event being invited to, used
+    /// by `exclude_event_rsvps`. Users who paused list email are skipped.
+    pub async fn resolve(&self, db: &Db, event_id: Option<i64>) -> Result<Vec<i64>> {
+        let rules = &self.rules;
+        let lists = serde_json::to_string(&rules.lists)?;
+        let events = serde_json::to_string(&rules.attended_events)?;
+        let exclude_event_id = event_id.filter(|_| rules.exclude_event_rsvps);
+        Ok(sqlx::query!(
+            r#"
+            SELECT u.id AS "id!: i64"
+            FROM users u
+            WHERE u.id IN (
+                    SELECT lm.user_id
+                    FROM list_members lm
+                    WHERE lm.list_id IN (SELECT value FROM json_each(?))
+                    GROUP BY lm.user_id
+                    HAVING NOT ? OR COUNT(DISTINCT lm.list_id) = json_array_length(?)
+                )
+              AND (json_array_length(?) = 0 OR u.id IN (
+                    SELECT r.user_id
+                    FROM rsvps r
+                    JOIN rsvp_sessions rs ON rs.id = r.session_id
+                    WHERE rs.event_id IN (SELECT value FROM json_each(?))
+                      AND rs.status IN (?, ?)
+                    UNION
+                    SELECT m.user_id
+                    FROM manual_rsvps m
+                    WHERE m.event_id IN (SELECT value FROM json_each(?))
+                ))
+              AND (? IS NULL OR u.id IN (
+                    SELECT r.user_id
+                    FROM rsvps r
+                    JOIN rsvp_sessions rs ON rs.id = r.session_id
+                    WHERE r.created_at >= datetime('now', printf('-%d months', ?))
+                      AND rs.status IN (?, ?)
+                    UNION
+                    SELECT m.user_id
+                    FROM manual_rsvps m
+                    WHERE m.created_at >= datetime('now', printf('-%d months', ?))
+                ))
+              AND (NOT ? OR (
+                    EXISTS (SELECT 1 FROM emails e WHERE e.user_id = u.id AND e.kind IN (?, ?) AND e.sent_at IS NOT NULL)
+                    AND NOT EXISTS (
+                        SELECT 1
+                        FROM (
+                            SELECT e.opened_at
+                            FROM emails e
+                            WHERE e.user_id = u.id AND e.kind IN (?, ?) AND e.sent_at IS NOT NULL
+                            ORDER BY e.sent_at DESC, e.id DESC
+                            LIMIT 3
+                        ) recent
+                        WHERE recent.opened_at IS NOT NULL
+                    )
+                ))
+              AND (? IS NULL OR u.id NOT IN (
+                    SELECT r.user_id
+                    FROM rsvps r
+                    JOIN rsvp_sessions rs ON rs.id = r.session_id
+                    WHERE rs.event_id = ?
+                      AND rs.status IN (?, ?)
+                    UNION
+                    SELECT m.user_id
+                    FROM manual_rsvps m
+                    WHERE m.event_id = ?
+                ))
+              AND NOT EXISTS (SELECT 1 FROM email_pauses p WHERE p.user_id = u.id AND p.paused_until > CURRENT_TIMESTAMP)
+            ORDER BY u.id
+            "#,
+            lists,
+            rules.match_all_lists,
+            lists,
+            events,
+            events,
+            RsvpSession::PAYMENT_PENDING,
+            RsvpSession::PAYMENT_CONFIRMED,
+            events,
+            rules.rsvp_within_months,
+            rules.rsvp_within_months,
+            RsvpSession::PAYMENT_PENDING,
+            RsvpSession::PAYMENT_CONFIRMED,
+            rules.rsvp_within_months,
+            rules.unopened_recent,
+            Email::POST,
+            Email::EVENT_INVITE,
+            Email::POST,
+            Email::EVENT_INVITE,
+            exclude_event_id,
+            exclude_event_id,
+            RsvpSession::PAYMENT_PENDING,
+            RsvpSession::PAYMENT_CONFIRMED,
+            exclude_event_id,
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect())
+    }
+}
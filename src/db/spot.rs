@@ -241,9 +241,13 @@ impl Spot {
 
     /// Duplicate all spots from one event to another.
     /// Creates new spot records (copies) and links them to the new event.
-    pub async fn duplicate_for_event(db: &Db, source_event_id: i64, target_event_id: i64) -> Result<()> {
+    /// Returns a map from source spot id to its copy.
+    pub async fn duplicate_for_event(
+        db: &Db, source_event_id: i64, target_event_id: i64,
+    ) -> Result<HashMap<i64, i64>> {
         let spots = Spot::list_for_event(db, source_event_id).await?;
         let mut new_spot_ids = Vec::with_capacity(spots.len());
+        let mut spot_id_map = HashMap::default();
 
         for spot in spots {
             let new_spot_id = sqlx::query!(
@@ -269,10 +273,11 @@ impl Spot {
             .last_insert_rowid();
 
             new_spot_ids.push(new_spot_id);
+            spot_id_map.insert(spot.id, new_spot_id);
         }
 
         Spot::add_to_event(db, target_event_id, new_spot_ids).await?;
-        Ok(())
+        Ok(spot_id_map)
     }
 }
 
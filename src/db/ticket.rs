@@ -0,0 +1,147 @@
+use crate::db::event::Event;
+use crate::db::rsvp_session::RsvpSession;
+use crate::prelude::*;
+
+/// A user's place at an event, for the "My tickets" page.
+/// Either a reservation they own, a spot someone else reserved for them, or a manual RSVP.
+pub struct Ticket {
+    pub event_id: i64,
+    /// Token of the owned reservation, for linking to the manage page. None for guests and manual RSVPs.
+    pub reservation: Option<String>,
+    pub status: String,
+    /// Name of whoever reserved the spot, for guests.
+    pub guest_of: Option<String>,
+    pub spot_count: i64,
+    pub checkin_at: Option<NaiveDateTime>,
+    pub is_manual: bool,
+}
+
+impl Ticket {
+    pub fn is_refunded(&self) -> bool {
+        matches!(
+            self.status.as_str(),
+            RsvpSession::REFUND_PENDING | RsvpSession::REFUND_CONFIRMED
+        )
+    }
+
+    /// Whether the ticket admits the user: paid for, or a manual RSVP.
+    pub fn is_confirmed(&self) -> bool {
+        self.is_manual || self.status == RsvpSession::PAYMENT_CONFIRMED
+    }
+
+    /// Human label for the status, matching the admin attendees page.
+    pub fn status_label(&self) -> &'static str {
+        match self.status.as_str() {
+            _ if self.is_manual => "Confirmed",
+            RsvpSession::PAYMENT_CONFIRMED => "Confirmed",
+            RsvpSession::PAYMENT_PENDING => "Unpaid",
+            RsvpSession::REFUND_PENDING | RsvpSession::REFUND_CONFIRMED => "Refunded",
+            _ => "",
+        }
+    }
+
+    /// List every ticket a user holds, across reservations they own, guest spots and manual RSVPs.
+    pub async fn list_for_user(db: &Db, user_id: i64) -> Result<Vec<Ticket>> {
+        // Reservations the user made, counting spots added later via child sessions
+        let owned = sqlx::query!(
+            r#"SELECT
+                 rs.event_id, rs.token, rs.status,
+                 (SELECT COUNT(*)
+                  FROM rsvps r
+                  JOIN rsvp_sessions c ON c.id = r.session_id
+                  WHERE (c.id = rs.id OR c.parent_session_id = rs.id) AND c.status IN (?, ?, ?, ?)
+                 ) AS "spot_count!: i64",
+                 (SELECT MAX(r.checkin_at)
+                  FROM rsvps r
+                  WHERE r.session_id = rs.id AND r.user_id = rs.user_id
+                 ) AS "checkin_at?: NaiveDateTime"
+               FROM rsvp_sessions rs
+               WHERE rs.user_id = ? AND rs.parent_session_id IS NULL AND rs.status IN (?, ?, ?, ?)"#,
+            RsvpSession::PAYMENT_PENDING,
+            RsvpSession::PAYMENT_CONFIRMED,
+            RsvpSession::REFUND_PENDING,
+            RsvpSession::REFUND_CONFIRMED,
+            user_id,
+            RsvpSession::PAYMENT_PENDING,
+            RsvpSession::PAYMENT_CONFIRMED,
+            RsvpSession::REFUND_PENDING,
+            RsvpSession::REFUND_CONFIRMED,
+        )
+        .fetch_all(db)
+        .await?;
+
+        // Spots reserved for the user by someone else
+        let guest = sqlx::query!(
+            r#"SELECT rs.event_id, rs.status, r.checkin_at, u.first_name, u.last_name
+               FROM rsvps r
+               JOIN rsvp_sessions rs ON rs.id = r.session_id
+               LEFT JOIN users u ON u.id = rs.user_id
+               WHERE r.user_id = ? AND rs.user_id IS NOT ? AND rs.status IN (?, ?, ?, ?)"#,
+            user_id,
+            user_id,
+            RsvpSession::PAYMENT_PENDING,
+            RsvpSession::PAYMENT_CONFIRMED,
+            RsvpSession::REFUND_PENDING,
+            RsvpSession::REFUND_CONFIRMED,
+        )
+        .fetch_all(db)
+        .await?;
+
+        let manual = sqlx::query!("SELECT event_id, checkin_at FROM manual_rsvps WHERE user_id = ?", user_id)
+            .fetch_all(db)
+            .await?;
+
+        let owned = owned.into_iter().map(|row| Ticket {
+            event_id: row.event_id,
+            reservation: Some(row.token),
+            status: row.status,
+            guest_of: None,
+            spot_count: row.spot_count,
+            checkin_at: row.checkin_at,
+            is_manual: false,
+        });
+        let guest = guest.into_iter().map(|row| Ticket {
+            event_id: row.event_id,
+            reservation: None,
+            status: row.status,
+            guest_of: match (row.first_name, row.last_name) {
+                (Some(first), Some(last)) => Some(format!("{first} {last}")),
+                (first, last) => first.or(last),
+            },
+            spot_count: 1,
+            checkin_at: row.checkin_at,
+            is_manual: false,
+        });
+        let manual = manual.into_iter().map(|row| Ticket {
+            event_id: row.event_id,
+            reservation: None,
+            status: RsvpSession::PAYMENT_CONFIRMED.into(),
+            guest_of: None,
+            spot_count: 1,
+            checkin_at: row.checkin_at,
+            is_manual: true,
+        });
+        Ok(owned.chain(guest).chain(manual).collect())
+    }
+
+    /// List every ticket a user holds along with its event, newest event first.
+    pub async fn list_with_events(db: &Db, user_id: i64) -> Result<Vec<(Ticket, Event)>> {
+        let mut events: HashMap<i64, Event> = HashMap::new();
+        let mut tickets = vec![];
+        for ticket in Self::list_for_user(db, user_id).await? {
+            let event = match events.get(&ticket.event_id) {
+                Some(event) => event.clone(),
+                None => {
+                    let Some(event) = Event::lookup_by_id(db, ticket.event_id).await? else {
+                        continue;
+                    };
+                    events.insert(event.id, event.clone());
+                    event
+                }
+            };
+            tickets.push((ticket, event));
+        }
+        tickets.sort_by_key(|(_, event)| std::cmp::Reverse(event.start));
+        Ok(tickets)
+    }
+}
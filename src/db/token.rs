@@ -6,9 +6,13 @@ use crate::prelude::*;
 /// A token which can be used to authenticate as a user.
 #[derive(Debug, sqlx::FromRow, serde::Serialize)]
 pub struct SessionToken {
+    pub id: i64,
     pub user_id: i64,
     pub token: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
     pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
 }
 
 /// A token which can be used to login as a user.
@@ -20,17 +24,107 @@ pub struct LoginToken {
     pub used_at: Option<NaiveDateTime>,
 }
 
+/// A short numeric code which can be used to login as a user, bound to the email that requested it.
+#[derive(Debug, sqlx::FromRow, serde::Serialize)]
+pub struct LoginCode {
+    pub user_id: i64,
+    pub email: String,
+    pub code: String,
+    pub attempts: i64,
+    pub created_at: NaiveDateTime,
+}
+
+/// A pending change of a user's email address, applied once the new address is verified.
+#[derive(Debug, sqlx::FromRow, serde::Serialize)]
+pub struct EmailChange {
+    pub user_id: i64,
+    pub email: String,
+    pub token: String,
+    pub created_at: NaiveDateTime,
+}
+
 impl SessionToken {
-    /// Create a new session token for a user.
+    /// Minimum time between `last_seen_at` updates, to avoid a write on every request.
+    const TOUCH_INTERVAL_MINUTES: i64 = 5;
+
+    /// Sessions created before this are expired, matching the session cookie's max-age.
+    pub fn expiry_cutoff() -> NaiveDateTime {
+        Utc::now().naive_utc() - chrono::Duration::days(config().app.session_expiry_days as i64)
+    }
+
+    /// Create a new session token for a user, clearing out any of their expired sessions.
     pub async fn create(db: &Db, user: &User) -> Result<String> {
         let token = format!("{:08x}", OsRng.r#gen::<u64>());
 
+        let cutoff = Self::expiry_cutoff();
+        sqlx::query!(
+            "DELETE FROM session_tokens WHERE user_id = ? AND created_at < ?",
+            user.id,
+            cutoff
+        )
+        .execute(db)
+        .await?;
         sqlx::query!("INSERT INTO session_tokens (user_id, token) VALUES (?, ?)", user.id, token)
             .execute(db)
             .await?;
 
         Ok(token)
     }
+
+    /// List a user's unexpired sessions, most recently used first.
+    pub async fn list_for_user(db: &Db, user_id: i64) -> Result<Vec<SessionToken>> {
+        let cutoff = Self::expiry_cutoff();
+        Ok(sqlx::query_as!(
+            SessionToken,
+            "SELECT * FROM session_tokens WHERE user_id = ? AND created_at >= ? ORDER BY last_seen_at DESC",
+            user_id,
+            cutoff
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// Record that a session was just used, and from where.
+    pub async fn touch(db: &Db, token: &str, ip: Option<String>, user_agent: Option<String>) -> Result<()> {
+        let stale = Utc::now().naive_utc() - chrono::Duration::minutes(Self::TOUCH_INTERVAL_MINUTES);
+        sqlx::query!(
+            r#"UPDATE session_tokens
+               SET last_seen_at = CURRENT_TIMESTAMP, ip = ?, user_agent = ?
+               WHERE token = ? AND (last_seen_at < ? OR ip IS NOT ? OR user_agent IS NOT ?)"#,
+            ip,
+            user_agent,
+            token,
+            stale,
+            ip,
+            user_agent,
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_by_token(db: &Db, token: &str) -> Result<()> {
+        sqlx::query!("DELETE FROM session_tokens WHERE token = ?", token)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Delete one of a user's sessions, scoped to the user so ids can't be guessed across accounts.
+    pub async fn delete_by_id(db: &Db, user_id: i64, id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM session_tokens WHERE user_id = ? AND id = ?", user_id, id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Log a user out everywhere.
+    pub async fn delete_by_user_id(db: &Db, user_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM session_tokens WHERE user_id = ?", user_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
 }
 
 impl LoginToken {
@@ -59,3 +153,105 @@ impl LoginToken {
         Ok(())
     }
 }
+
+impl LoginCode {
+    /// Wrong guesses allowed before the code is discarded.
+    pub const MAX_ATTEMPTS: i64 = 5;
+    /// How long a code stays valid after it is emailed.
+    pub const EXPIRY_MINUTES: i64 = 10;
+
+    /// Create a new 6-digit login code for a user, replacing any outstanding code.
+    /// Wrong guesses carry over while the replaced code was still valid, so asking for a new code
+    /// doesn't buy more guesses.
+    pub async fn create(db: &Db, user: &User) -> Result<String> {
+        let code = format!("{:06}", OsRng.gen_range(0..1_000_000));
+        let expiry = format!("-{} minutes", Self::EXPIRY_MINUTES);
+
+        sqlx::query!(
+            r#"INSERT INTO login_codes (user_id, email, code) VALUES (?1, ?2, ?3)
+               ON CONFLICT (user_id) DO UPDATE SET
+                   email = excluded.email,
+                   code = excluded.code,
+                   attempts = CASE WHEN created_at > datetime('now', ?4) THEN attempts ELSE 0 END,
+                   created_at = CURRENT_TIMESTAMP"#,
+            user.id,
+            user.email,
+            code,
+            expiry
+        )
+        .execute(db)
+        .await?;
+
+        Ok(code)
+    }
+
+    /// Exchange a code for the id of the user it was sent to.
+    /// Codes are single use, and stop working once expired or after too many wrong guesses. Each guess
+    /// is counted before it is checked, so parallel guesses can't get past the limit.
+    pub async fn redeem(db: &Db, email: &str, code: &str) -> Result<Option<i64>> {
+        let expiry = format!("-{} minutes", Self::EXPIRY_MINUTES);
+        let Some(row) = sqlx::query!(
+            r#"UPDATE login_codes SET attempts = attempts + 1
+               WHERE email = ? COLLATE NOCASE AND attempts < ? AND created_at > datetime('now', ?)
+               RETURNING user_id, code"#,
+            email,
+            Self::MAX_ATTEMPTS,
+            expiry
+        )
+        .fetch_optional(db)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        if row.code != code {
+            return Ok(None);
+        }
+
+        Self::delete_by_user_id(db, row.user_id).await?;
+        Ok(Some(row.user_id))
+    }
+
+    pub async fn delete_by_user_id(db: &Db, user_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM login_codes WHERE user_id = ?", user_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
+
+impl EmailChange {
+    /// How long the verification link stays valid.
+    pub const EXPIRY_HOURS: i64 = 24;
+
+    /// Start changing a user's email, replacing any pending change. Returns the verification token.
+    pub async fn create(db: &Db, user: &User, email: &str) -> Result<String> {
+        let token = format!("{:08x}", OsRng.r#gen::<u64>());
+
+        sqlx::query!(
+            "INSERT OR REPLACE INTO email_changes (user_id, email, token) VALUES (?, ?, ?)",
+            user.id,
+            email,
+            token
+        )
+        .execute(db)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Consume a verification token, returning the pending change if it hasn't expired.
+    pub async fn redeem(db: &Db, token: &str) -> Result<Option<EmailChange>> {
+        let change = sqlx::query_as!(EmailChange, "SELECT * FROM email_changes WHERE token = ?", token)
+            .fetch_optional(db)
+            .await?;
+        let Some(change) = change else { return Ok(None) };
+
+        sqlx::query!("DELETE FROM email_changes WHERE user_id = ?", change.user_id)
+            .execute(db)
+            .await?;
+
+        let expires_at = change.created_at + chrono::Duration::hours(Self::EXPIRY_HOURS);
+        Ok((Utc::now().naive_utc() <= expires_at).then_some(change))
+    }
+}
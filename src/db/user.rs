@@ -1,3 +1,6 @@
+use crate::db::audit_log::AuditLog;
+use crate::db::role::Role;
+use crate::db::token::SessionToken;
 use crate::prelude::*;
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -12,6 +15,7 @@ pub struct User {
 
     pub version: i64,
     pub roles: Vec<String>,
+    pub permissions: Vec<Permission>,
 }
 
 #[derive(Clone, Debug, serde::Deserialize)]
@@ -61,6 +65,7 @@ macro_rules! map_row {
 
             version: $row.version,
             roles: $row.roles.split(',').filter(|s| !s.is_empty()).map(|s| s.to_string()).collect(),
+            permissions: $row.permissions.split(',').filter_map(Permission::parse).collect(),
         }
     };
 }
@@ -78,6 +83,7 @@ macro_rules! map_row_fuck {
 
             version: $row.version,
             roles: $row.roles.split(',').filter(|s| !s.is_empty()).map(|s| s.to_string()).collect(),
+            permissions: $row.permissions.split(',').filter_map(Permission::parse).collect(),
         }
     };
 }
@@ -101,10 +107,8 @@ fn normalize_name(name: &Option<String>) -> Option<String> {
 }
 
 impl User {
-    /// Full access to everything.
-    pub const ADMIN: &'static str = "admin";
-    /// Can manage posts.
-    pub const WRITER: &'static str = "writer";
+    /// How long a user created by a login or list signup form has to register before being deleted.
+    pub const PENDING_EXPIRY_SQL: &str = "-1 day";
 
     pub async fn get_or_create(db: &Db, info: &CreateUser) -> Result<User> {
         Ok(match Self::lookup_by_email(db, &info.email).await? {
@@ -169,7 +173,7 @@ impl User {
             r#"INSERT INTO users
                (email, first_name, last_name, phone)
                VALUES (?, ?, ?, ?)
-               RETURNING *, 0 as version, '' as roles
+               RETURNING *, 0 as version, '' as roles, '' as permissions
             "#,
             user.email,
             first_name,
@@ -199,8 +203,10 @@ impl User {
         let first_name = normalize_name(&info.first_name);
         let last_name = normalize_name(&info.last_name);
 
-        let unchanged =
-            first_name == self.first_name && last_name == self.last_name && info.phone == self.phone;
+        let unchanged = info.email == self.email
+            && first_name == self.first_name
+            && last_name == self.last_name
+            && info.phone == self.phone;
         if unchanged {
             return Ok(self.clone());
         }
@@ -241,13 +247,6 @@ impl User {
         Ok(Self::lookup_by_id(db, self.id).await?.unwrap())
     }
 
-    // pub async fn add_role(db: &Db, user_id: i64, role: &str) -> Result<()> {
-    //     sqlx::query!(r#"INSERT INTO user_roles (user_id, role) VALUES (?, ?)"#, user_id, role)
-    //         .execute(db)
-    //         .await?;
-    //     Ok(())
-    // }
-
     /// Lookup a user by id, if one exists.
     pub async fn lookup_by_id(db: &Db, id: i64) -> Result<Option<User>> {
         let row = sqlx::query!(
@@ -255,10 +254,12 @@ impl User {
             SELECT
                 u.*,
                 COALESCE(MAX(h.version), 0) as "version!: i64",
-                COALESCE(GROUP_CONCAT(r.role), '') AS "roles!: String"
+                COALESCE(GROUP_CONCAT(r.role), '') AS "roles!: String",
+                COALESCE(p.permissions, '') AS "permissions!: String"
             FROM users u
             LEFT JOIN user_roles r ON r.user_id = u.id
             JOIN user_history h ON h.user_id = u.id
+            LEFT JOIN user_permissions p ON p.user_id = u.id
             WHERE u.id = ?
             GROUP BY u.id
             "#,
@@ -276,10 +277,12 @@ impl User {
             SELECT
                 u.*,
                 COALESCE(MAX(h.version), 0) as "version!: i64",
-                COALESCE(GROUP_CONCAT(r.role), '') AS "roles!: String"
+                COALESCE(GROUP_CONCAT(r.role), '') AS "roles!: String",
+                COALESCE(p.permissions, '') AS "permissions!: String"
             FROM users u
             LEFT JOIN user_roles r ON r.user_id = u.id
             JOIN user_history h ON h.user_id = u.id
+            LEFT JOIN user_permissions p ON p.user_id = u.id
             WHERE u.email = ? COLLATE NOCASE
             GROUP BY u.id
             "#,
@@ -356,10 +359,12 @@ impl User {
             SELECT
                 u.*,
                 COALESCE(MAX(h.version), 0) as "version!: i64",
-                COALESCE(GROUP_CONCAT(r.role), '') AS "roles!: String"
+                COALESCE(GROUP_CONCAT(r.role), '') AS "roles!: String",
+                COALESCE(p.permissions, '') AS "permissions!: String"
             FROM users u
             LEFT JOIN login_tokens t ON t.user_id = u.id
             JOIN user_history h ON h.user_id = u.id
+            LEFT JOIN user_permissions p ON p.user_id = u.id
             LEFT JOIN user_roles r ON r.user_id = u.id
             WHERE t.token = ?
             GROUP BY u.id"#,
@@ -370,22 +375,26 @@ impl User {
 
         Ok(row.map(|r| map_row_fuck!(r)))
     }
-    /// Lookup a user by a session token, if it's valid.
+    /// Lookup a user by a session token, if it's valid and unexpired.
     pub async fn lookup_by_session_token(db: &Db, token: &str) -> Result<Option<User>> {
+        let cutoff = SessionToken::expiry_cutoff();
         let row = sqlx::query!(
             r#"
             SELECT
                 u.*,
                 COALESCE(MAX(h.version), 0) as "version!: i64",
-                COALESCE(GROUP_CONCAT(r.role), '') AS "roles!: String"
+                COALESCE(GROUP_CONCAT(r.role), '') AS "roles!: String",
+                COALESCE(p.permissions, '') AS "permissions!: String"
             FROM users u
             LEFT JOIN session_tokens t ON t.user_id = u.id
             JOIN user_history h ON h.user_id = u.id
+            LEFT JOIN user_permissions p ON p.user_id = u.id
             LEFT JOIN user_roles r ON r.user_id = u.id
-            WHERE t.token = ?
+            WHERE t.token = ? AND t.created_at >= ?
             GROUP BY u.id
             "#,
-            token
+            token,
+            cutoff
         )
         .fetch_optional(db)
         .await?;
@@ -398,10 +407,12 @@ impl User {
             SELECT
                 u.*,
                 COALESCE(MAX(h.version), 0) as "version!: i64",
-                COALESCE(GROUP_CONCAT(r.role), '') AS "roles!: String"
+                COALESCE(GROUP_CONCAT(r.role), '') AS "roles!: String",
+                COALESCE(p.permissions, '') AS "permissions!: String"
             FROM list_members lm
             JOIN users u ON u.id = lm.user_id
             JOIN user_history h ON h.user_id = u.id
+            LEFT JOIN user_permissions p ON p.user_id = u.id
             LEFT JOIN user_roles r ON r.user_id = u.id
             WHERE lm.list_id = ?
             GROUP BY u.id
@@ -413,7 +424,68 @@ impl User {
         Ok(rows.into_iter().map(|r| map_row!(r)).collect())
     }
 
+    /// Erase a user's personal data on request, keeping the row so financial records stay intact.
+    ///
+    /// Reservations, payments and sent email records still reference the user id, but no longer
+    /// lead back to a name, email or phone number. Audit log entries about the user are scrubbed too.
+    /// Everything happens in one transaction so a failure can't leave a half-erased user.
+    pub async fn anonymize(&self, db: &Db) -> Result<()> {
+        let email = format!("deleted-{}@deleted.invalid", self.id);
+        let mut tx = db.begin().await?;
+
+        sqlx::query!(
+            r#"UPDATE users
+               SET email = ?, first_name = NULL, last_name = NULL, phone = NULL, updated_at = CURRENT_TIMESTAMP
+               WHERE id = ?"#,
+            email,
+            self.id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE user_history SET email = ?, first_name = NULL, last_name = NULL, phone = NULL WHERE user_id = ?",
+            email,
+            self.id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!("UPDATE rsvps SET note = NULL WHERE user_id = ?", self.id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("UPDATE manual_rsvps SET note = NULL WHERE user_id = ?", self.id)
+            .execute(&mut *tx)
+            .await?;
+
+        for table in [
+            "list_members",
+            "list_signups",
+            "email_pauses",
+            "user_roles",
+            "user_attrs",
+            "event_staff",
+            "session_tokens",
+            "login_tokens",
+            "login_codes",
+            "email_changes",
+        ] {
+            sqlx::query(&format!("DELETE FROM {table} WHERE user_id = ?"))
+                .bind(self.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        AuditLog::redact_user(&mut tx, self).await?;
+        tx.commit().await?;
+
+        tracing::info!("Anonymized user_id={}", self.id);
+        Ok(())
+    }
+
     /// Delete orphaned users, typically from expired anonymous RSVP sessions.
+    ///
+    /// Users created by a login or list signup form stay pending until they register, log in or
+    /// join a list. Pending users whose only emails are login or list signup emails older than
+    /// `PENDING_EXPIRY_SQL` are deleted along with those emails.
     pub async fn delete_orphaned(db: &Db) -> Result<()> {
         let deleted = sqlx::query!(
             r#"
@@ -424,9 +496,23 @@ impl User {
               AND NOT EXISTS (SELECT 1 FROM rsvp_sessions rs WHERE rs.user_id = users.id)
               AND NOT EXISTS (SELECT 1 FROM manual_rsvps m WHERE m.user_id = users.id)
               AND NOT EXISTS (SELECT 1 FROM list_members lm WHERE lm.user_id = users.id)
-              AND NOT EXISTS (SELECT 1 FROM emails e WHERE e.user_id = users.id)
+              AND NOT EXISTS (SELECT 1 FROM list_signups ls WHERE ls.user_id = users.id)
+              AND NOT EXISTS (SELECT 1 FROM session_tokens st WHERE st.user_id = users.id)
+              AND NOT EXISTS (
+                    SELECT 1 FROM emails e
+                    WHERE e.user_id = users.id
+                      AND (e.kind NOT IN (?, ?)
+                           OR e.created_at > datetime('now', ?)
+                           OR users.first_name IS NOT NULL
+                           OR users.last_name IS NOT NULL)
+                )
+              AND NOT EXISTS (SELECT 1 FROM event_staff es WHERE es.user_id = users.id)
+              AND NOT EXISTS (SELECT 1 FROM event_invite_redemptions ir WHERE ir.user_id = users.id)
             RETURNING email
-            "#
+            "#,
+            Email::LOGIN,
+            Email::LIST_SIGNUP,
+            Self::PENDING_EXPIRY_SQL,
         )
         .fetch_all(db)
         .await?;
@@ -437,6 +523,15 @@ impl User {
         sqlx::query!("DELETE FROM user_attrs WHERE user_id NOT IN (SELECT id FROM users)")
             .execute(db)
             .await?;
+        sqlx::query!("DELETE FROM emails WHERE user_id NOT IN (SELECT id FROM users)")
+            .execute(db)
+            .await?;
+        sqlx::query!("DELETE FROM login_tokens WHERE user_id NOT IN (SELECT id FROM users)")
+            .execute(db)
+            .await?;
+        sqlx::query!("DELETE FROM login_codes WHERE user_id NOT IN (SELECT id FROM users)")
+            .execute(db)
+            .await?;
 
         for row in &deleted {
             tracing::info!("Deleted orphaned user: {}", row.email);
@@ -445,11 +540,12 @@ impl User {
         Ok(())
     }
 
-    pub fn has_role(&self, role: &str) -> bool {
-        self.roles.iter().any(|r| r == role || r == Self::ADMIN)
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.roles.iter().any(|r| r == Role::ADMIN) || self.permissions.contains(&permission)
     }
 
-    pub fn has_staff_role(&self) -> bool {
-        self.roles.iter().any(|r| [Self::ADMIN, Self::WRITER].contains(&&**r))
+    /// Whether the user has any permission at all, i.e. should see the staff dashboard.
+    pub fn is_staff(&self) -> bool {
+        self.roles.iter().any(|r| r == Role::ADMIN) || !self.permissions.is_empty()
     }
 }
@@ -0,0 +1,210 @@
+use crate::db::preference::PreferenceChange;
+use crate::prelude::*;
+
+/// Everything we hold about a user, for data access requests.
+///
+/// Contact form submissions are forwarded by email and never stored, so they aren't included.
+#[derive(serde::Serialize)]
+pub struct UserExport {
+    pub exported_at: NaiveDateTime,
+    pub user: ExportUser,
+    pub history: Vec<ExportUserVersion>,
+    pub roles: Vec<String>,
+    pub emails: Vec<ExportEmail>,
+    pub rsvps: Vec<ExportRsvp>,
+    pub manual_rsvps: Vec<ExportManualRsvp>,
+    pub lists: Vec<ExportListMembership>,
+    /// Signups not yet confirmed from the emailed link.
+    pub pending_lists: Vec<ExportListMembership>,
+    pub sessions: Vec<ExportSession>,
+    pub preference_changes: Vec<PreferenceChange>,
+}
+
+#[derive(serde::Serialize)]
+pub struct ExportUser {
+    pub id: i64,
+    pub email: String,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub phone: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// A past version of the user's profile.
+#[derive(serde::Serialize)]
+pub struct ExportUserVersion {
+    pub version: i64,
+    pub email: String,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub phone: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(serde::Serialize)]
+pub struct ExportEmail {
+    pub kind: String,
+    pub event: Option<String>,
+    pub post: Option<String>,
+    pub list: Option<String>,
+    pub segment: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub sent_at: Option<NaiveDateTime>,
+    pub opened_at: Option<NaiveDateTime>,
+}
+
+#[derive(serde::Serialize)]
+pub struct ExportRsvp {
+    pub event: String,
+    pub spot: String,
+    pub contribution: i64,
+    pub status: String,
+    /// Whether the user made the reservation, rather than being a guest on it.
+    pub is_owner: bool,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub checkin_at: Option<NaiveDateTime>,
+}
+
+#[derive(serde::Serialize)]
+pub struct ExportManualRsvp {
+    pub event: String,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub checkin_at: Option<NaiveDateTime>,
+}
+
+#[derive(serde::Serialize)]
+pub struct ExportListMembership {
+    pub list: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(serde::Serialize)]
+pub struct ExportSession {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+}
+
+impl UserExport {
+    pub async fn build(db: &Db, user: &User) -> Result<UserExport> {
+        let user_row = sqlx::query_as!(
+            ExportUser,
+            "SELECT id, email, first_name, last_name, phone, created_at, updated_at FROM users WHERE id = ?",
+            user.id
+        )
+        .fetch_one(db)
+        .await?;
+
+        let history = sqlx::query_as!(
+            ExportUserVersion,
+            r#"SELECT version, email, first_name, last_name, phone, created_at
+               FROM user_history WHERE user_id = ? ORDER BY version"#,
+            user.id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let emails = sqlx::query_as!(
+            ExportEmail,
+            r#"SELECT e.kind, ev.title AS "event?", p.title AS "post?", l.name AS "list?",
+                      s.name AS "segment?", e.created_at, e.sent_at, e.opened_at
+               FROM emails e
+               LEFT JOIN events ev ON ev.id = e.event_id
+               LEFT JOIN posts p ON p.id = e.post_id
+               LEFT JOIN lists l ON l.id = e.list_id
+               LEFT JOIN segments s ON s.id = e.segment_id
+               WHERE e.user_id = ?
+               ORDER BY e.created_at"#,
+            user.id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let rsvps = sqlx::query_as!(
+            ExportRsvp,
+            r#"SELECT ev.title AS event, COALESCE(s.name, '') AS "spot!: String", r.contribution,
+                      rs.status, rs.user_id IS ? AS "is_owner!: bool", r.note, r.created_at, r.checkin_at
+               FROM rsvps r
+               JOIN rsvp_sessions rs ON rs.id = r.session_id
+               JOIN events ev ON ev.id = rs.event_id
+               LEFT JOIN spots s ON s.id = r.spot_id
+               WHERE r.user_id = ? OR rs.user_id = ?
+               ORDER BY r.created_at"#,
+            user.id,
+            user.id,
+            user.id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let manual_rsvps = sqlx::query_as!(
+            ExportManualRsvp,
+            r#"SELECT ev.title AS event, m.note, m.created_at, m.checkin_at
+               FROM manual_rsvps m
+               JOIN events ev ON ev.id = m.event_id
+               WHERE m.user_id = ?
+               ORDER BY m.created_at"#,
+            user.id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let lists = sqlx::query_as!(
+            ExportListMembership,
+            r#"SELECT l.name AS list, lm.created_at
+               FROM list_members lm
+               JOIN lists l ON l.id = lm.list_id
+               WHERE lm.user_id = ?
+               ORDER BY lm.created_at"#,
+            user.id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let pending_lists = sqlx::query_as!(
+            ExportListMembership,
+            r#"SELECT l.name AS list, s.created_at
+               FROM list_signups s
+               JOIN lists l ON l.id = s.list_id
+               WHERE s.user_id = ?
+               ORDER BY s.created_at"#,
+            user.id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let sessions = sqlx::query_as!(
+            ExportSession,
+            "SELECT user_agent, ip, created_at, last_seen_at FROM session_tokens WHERE user_id = ? ORDER BY created_at",
+            user.id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let preference_changes = sqlx::query_as!(
+            PreferenceChange,
+            "SELECT * FROM preference_changes WHERE user_id = ? ORDER BY created_at",
+            user.id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(UserExport {
+            exported_at: Utc::now().naive_utc(),
+            user: user_row,
+            history,
+            roles: user.roles.clone(),
+            emails,
+            rsvps,
+            manual_rsvps,
+            lists,
+            pending_lists,
+            sessions,
+            preference_changes,
+        })
+    }
+}
@@ -3,6 +3,10 @@ use std::sync::Arc;
 use tokio_schedule::{Job, every};
 
 use crate::Config;
+use crate::db::blob::Blob;
+use crate::db::event_flyer::EventFlyer;
+use crate::db::list::ListSignup;
+use crate::db::post::Post;
 use crate::db::rsvp_session::RsvpSession;
 use crate::db::user::User;
 use crate::utils::types::SharedAppState;
@@ -19,8 +23,49 @@ pub async fn init(state: SharedAppState, config: Config) {
             .in_timezone(&tz)
             .perform(move || expire_rsvp_sessions(state_.clone())),
     );
+
+    let state_ = state.clone();
+    tokio::spawn(
+        every(1)
+            .minute()
+            .at(0)
+            .in_timezone(&tz)
+            .perform(move || publish_scheduled_posts(state_.clone())),
+    );
+
+    // Encode variants for flyers uploaded before a format was added, or whose encoding was cut short.
+    tokio::spawn(backfill_flyer_variants(state.clone()));
+    let state_ = state.clone();
+    tokio::spawn(
+        every(1)
+            .hour()
+            .at(30, 0)
+            .in_timezone(&tz)
+            .perform(move || backfill_flyer_variants(state_.clone())),
+    );
+
+    let state_ = state.clone();
+    tokio::spawn(
+        every(1)
+            .hour()
+            .at(45, 0)
+            .in_timezone(&tz)
+            .perform(move || sweep_blobs(state_.clone())),
+    );
 }
 async fn expire_rsvp_sessions(state: SharedAppState) {
     let _ = RsvpSession::delete_expired(&state.db).await;
+    let _ = ListSignup::delete_expired(&state.db).await;
     let _ = User::delete_orphaned(&state.db).await;
 }
+async fn publish_scheduled_posts(state: SharedAppState) {
+    let _ = Post::publish_scheduled(&state.db).await;
+}
+async fn backfill_flyer_variants(state: SharedAppState) {
+    let _ = EventFlyer::backfill_variants(&state.db).await;
+}
+async fn sweep_blobs(state: SharedAppState) {
+    if let Err(e) = Blob::sweep(&state.db).await {
+        tracing::error!("Failed to sweep blobs: {e:?}");
+    }
+}
@@ -22,6 +22,7 @@ pub use serde_json::json;
 pub use crate::alert;
 pub use crate::db::Db;
 pub use crate::db::email::Email;
+pub use crate::db::role::Permission;
 pub use crate::db::user::User;
 pub use crate::utils::alerts;
 pub use crate::utils::config::{Config, config};
@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use sha2::{Digest, Sha256};
+
+use crate::prelude::*;
+
+/// Global blob store, set once at startup.
+static STORE: OnceLock<Box<dyn BlobStore>> = OnceLock::new();
+pub fn blobs() -> &'static dyn BlobStore {
+    STORE.get().expect("blob store not initialized").as_ref()
+}
+
+/// Open the blob store under `dir`.
+pub async fn init(dir: &Path) -> Result<()> {
+    let store = FileBlobStore::open(dir).await?;
+    STORE.set(Box::new(store)).unwrap_or_else(|_| unreachable!());
+    Ok(())
+}
+
+/// Immutable blobs named by the hex SHA-256 of their contents, so storing the same bytes twice
+/// is free and references to them can be copied around.
+///
+/// Futures are boxed so backends can be swapped behind a `dyn BlobStore`, e.g. for S3.
+pub trait BlobStore: Send + Sync {
+    /// Store bytes, returning their hash. Storing bytes that are already there counts as writing them.
+    fn put(&self, bytes: Bytes) -> BoxFuture<'_, Result<String>>;
+    /// Read the bytes for a hash, if stored.
+    fn get<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<Bytes>>>;
+    /// The length in bytes of a blob, if stored, without reading it.
+    fn size<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<u64>>>;
+    /// The hashes of blobs not written for at least `age`.
+    fn list_stale(&self, age: Duration) -> BoxFuture<'_, Result<Vec<String>>>;
+    /// Remove a blob. Callers check it's no longer referenced first.
+    fn delete<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Hash bytes the way blobs are named.
+pub fn hash(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// Blobs as files on local disk, fanned out into directories by the first two hash characters.
+pub struct FileBlobStore {
+    dir: PathBuf,
+}
+
+impl FileBlobStore {
+    pub async fn open(dir: &Path) -> Result<Self> {
+        tokio::fs::create_dir_all(dir).await?;
+        Ok(Self { dir: dir.to_path_buf() })
+    }
+
+    fn path(&self, hash: &str) -> Result<PathBuf> {
+        // Hashes come from the database, but never let one escape the store directory.
+        if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            bail!("invalid blob hash {:?}", hash);
+        }
+        Ok(self.dir.join(&hash[..2]).join(hash))
+    }
+}
+
+impl BlobStore for FileBlobStore {
+    fn put(&self, bytes: Bytes) -> BoxFuture<'_, Result<String>> {
+        Box::pin(async move {
+            let hash = hash(&bytes);
+            let path = self.path(&hash)?;
+            if tokio::fs::try_exists(&path).await? {
+                // Bump the write time so a sweep running now leaves it alone.
+                let file = tokio::fs::File::options().append(true).open(&path).await?;
+                file.into_std().await.set_modified(SystemTime::now())?;
+                return Ok(hash);
+            }
+
+            // Write to a temporary file and rename, so readers never see a partial blob.
+            let dir = path.parent().expect("blob paths have a parent");
+            tokio::fs::create_dir_all(dir).await?;
+            let tmp = dir.join(format!("{hash}.{}.tmp", uuid::Uuid::now_v7()));
+            tokio::fs::write(&tmp, &bytes).await?;
+            tokio::fs::rename(&tmp, &path).await?;
+            Ok(hash)
+        })
+    }
+
+    fn get<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<Bytes>>> {
+        Box::pin(async move {
+            match tokio::fs::read(self.path(hash)?).await {
+                Ok(bytes) => Ok(Some(Bytes::from(bytes))),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+
+    fn size<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<u64>>> {
+        Box::pin(async move {
+            match tokio::fs::metadata(self.path(hash)?).await {
+                Ok(metadata) => Ok(Some(metadata.len())),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+
+    fn list_stale(&self, age: Duration) -> BoxFuture<'_, Result<Vec<String>>> {
+        Box::pin(async move {
+            let cutoff = SystemTime::now() - age;
+            let mut hashes = vec![];
+            let mut dirs = tokio::fs::read_dir(&self.dir).await?;
+            while let Some(dir) = dirs.next_entry().await? {
+                if !dir.file_type().await?.is_dir() {
+                    continue;
+                }
+                let mut files = tokio::fs::read_dir(dir.path()).await?;
+                while let Some(file) = files.next_entry().await? {
+                    // Skips temporary files from writes in progress.
+                    let Some(name) = file.file_name().to_str().map(str::to_string) else {
+                        continue;
+                    };
+                    if self.path(&name).is_err() {
+                        continue;
+                    }
+                    if file.metadata().await?.modified()? < cutoff {
+                        hashes.push(name);
+                    }
+                }
+            }
+            Ok(hashes)
+        })
+    }
+
+    fn delete<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.path(hash)?).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            }
+        })
+    }
+}
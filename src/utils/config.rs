@@ -52,6 +52,22 @@ pub struct AppConfig {
     pub tz: Tz,
     /// How long until a login session expires.
     pub session_expiry_days: u32,
+    /// How long a list signup waits for its confirmation link to be clicked.
+    #[serde(default = "default_list_signup_expiry_hours")]
+    pub list_signup_expiry_hours: u32,
+    /// Where events happen, for structured data in link previews and search results.
+    pub venue: Option<VenueConfig>,
+}
+fn default_list_signup_expiry_hours() -> u32 {
+    48
+}
+
+/// Venue events take place at.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct VenueConfig {
+    pub name: String,
+    /// Street address, as one line.
+    pub address: String,
 }
 
 /// Database configuration.
@@ -60,6 +76,12 @@ pub struct DbConfig {
     /// Path to sqlite3 database file.
     pub file: PathBuf,
     pub seed_data: Option<PathBuf>,
+    /// Directory for the blob store holding images, see `utils::blobs`.
+    #[serde(default = "default_blobs_dir")]
+    pub blobs_dir: PathBuf,
+}
+fn default_blobs_dir() -> PathBuf {
+    "blobs".into()
 }
 
 /// Networking configuration.
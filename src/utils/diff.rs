@@ -0,0 +1,65 @@
+use similar::{ChangeTag, TextDiff};
+
+/// A line of a diff between two versions of some html.
+pub struct DiffLine {
+    /// `equal`, `delete` or `insert`, or `skip` between hunks.
+    pub tag: &'static str,
+    /// Runs of the line's text, and whether each run changed.
+    pub parts: Vec<(bool, String)>,
+}
+
+/// Lines of unchanged context around each change.
+const CONTEXT: usize = 3;
+
+/// Diff html source by line, marking the changed words within lines. The editor saves html
+/// without newlines, so lines are first broken after block elements.
+pub fn html_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let old = break_lines(old);
+    let new = break_lines(new);
+    let diff = TextDiff::from_lines(&old, &new);
+
+    let mut lines = vec![];
+    for (i, group) in diff.grouped_ops(CONTEXT).iter().enumerate() {
+        if i > 0 {
+            lines.push(DiffLine { tag: "skip", parts: vec![] });
+        }
+        for op in group {
+            for change in diff.iter_inline_changes(op) {
+                let tag = match change.tag() {
+                    ChangeTag::Equal => "equal",
+                    ChangeTag::Delete => "delete",
+                    ChangeTag::Insert => "insert",
+                };
+                let parts = change
+                    .iter_strings_lossy()
+                    .map(|(changed, text)| (changed, text.trim_end_matches('\n').to_string()))
+                    .collect();
+                lines.push(DiffLine { tag, parts });
+            }
+        }
+    }
+    lines
+}
+
+fn break_lines(html: &str) -> String {
+    let mut html = html.to_string();
+    for tag in [
+        "</p>",
+        "</div>",
+        "</h1>",
+        "</h2>",
+        "</h3>",
+        "</h4>",
+        "</h5>",
+        "</h6>",
+        "</li>",
+        "</ul>",
+        "</ol>",
+        "</blockquote>",
+        "<br>",
+        "<hr>",
+    ] {
+        html = html.replace(tag, &format!("{tag}\n"));
+    }
+    html
+}
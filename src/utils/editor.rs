@@ -10,6 +10,10 @@ pub struct Editor {
     /// Returns JSON, either {id: 123} or {error: ""}
     pub url: &'static str,
     pub snapshot_prefix: &'static str,
+    /// The content's revision history, once it has been saved.
+    pub revisions_url: Option<String>,
+    /// Merge tags the content can use, see `MergeTags`. The editor warns about others before saving.
+    pub merge_tags: &'static [&'static str],
 
     pub entity_id: Option<i64>,
     pub content: Option<EditorContent>,
@@ -2,14 +2,23 @@ use std::borrow::Cow;
 use std::io::Cursor;
 
 use axum::body::Bytes;
+use image::codecs::avif::AvifEncoder;
 use image::imageops::FilterType;
-use image::{DynamicImage, ImageReader};
+use image::{DynamicImage, ExtendedColorType, ImageDecoder, ImageEncoder, ImageReader};
 use jpeg_encoder::{ColorType, Encoder};
 
 use crate::prelude::*;
 
+/// Decode an upload, turned upright according to its EXIF orientation.
+///
+/// Only pixels are kept: the encoders below never write EXIF, so camera and GPS metadata from
+/// phone photos is stripped from everything stored.
 pub async fn decode(bytes: &Bytes) -> Result<DynamicImage> {
-    Ok(ImageReader::new(Cursor::new(bytes)).with_guessed_format()?.decode()?)
+    let mut decoder = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
 }
 
 pub async fn encode_jpeg(image: &DynamicImage, max_w: Option<u32>) -> Vec<u8> {
@@ -17,16 +26,7 @@ pub async fn encode_jpeg(image: &DynamicImage, max_w: Option<u32>) -> Vec<u8> {
     // This seems like the optimal size/quality tradeoff, with a slight pref for quality.
     const QUALITY: u8 = 85;
 
-    let mut image = Cow::Borrowed(image);
-    if let Some(max_width) = max_w
-        && image.width() > max_width
-    {
-        let ratio = max_width as f32 / image.width() as f32;
-        let new_height = (image.height() as f32 * ratio) as u32;
-        image = Cow::Owned(image.resize(max_width, new_height, FilterType::Lanczos3));
-    }
-
-    let rgb = image.to_rgb8();
+    let rgb = resize(image, max_w).to_rgb8();
     let mut bytes = vec![];
     let mut encoder = Encoder::new(&mut bytes, QUALITY);
 
@@ -44,3 +44,62 @@ pub async fn encode_jpeg(image: &DynamicImage, max_w: Option<u32>) -> Vec<u8> {
         .expect("JPEG encoding should not fail");
     bytes
 }
+
+/// Encode a lossy WebP. Blocks for a while on large images, so call it off the async runtime.
+pub fn encode_webp(image: &DynamicImage, max_w: Option<u32>) -> Vec<u8> {
+    // Roughly matches the JPEG quality above.
+    const QUALITY: f32 = 80.0;
+
+    let rgb = resize(image, max_w).to_rgb8();
+    webp::Encoder::from_rgb(rgb.as_raw(), rgb.width(), rgb.height())
+        .encode(QUALITY)
+        .to_vec()
+}
+
+/// Encode an AVIF. Much slower than JPEG or WebP, so call it off the async runtime.
+pub fn encode_avif(image: &DynamicImage, max_w: Option<u32>) -> Result<Vec<u8>> {
+    // Roughly matches the JPEG quality above.
+    const QUALITY: u8 = 70;
+    // 1 (slowest) to 10 (fastest). Past 6 files grow quickly for little time saved.
+    const SPEED: u8 = 6;
+
+    let rgb = resize(image, max_w).to_rgb8();
+    let mut bytes = vec![];
+    AvifEncoder::new_with_speed_quality(&mut bytes, SPEED, QUALITY).write_image(
+        rgb.as_raw(),
+        rgb.width(),
+        rgb.height(),
+        ExtendedColorType::Rgb8,
+    )?;
+    Ok(bytes)
+}
+
+/// Crop an image to exactly `width` x `height`, keeping as much as fits around `focal`, a point given
+/// as fractions of the image's width and height.
+pub fn crop(image: &DynamicImage, width: u32, height: u32, focal: (f64, f64)) -> DynamicImage {
+    // Largest window of the target aspect ratio that fits the image.
+    let scale = f64::min(image.width() as f64 / width as f64, image.height() as f64 / height as f64);
+    let window_w = ((width as f64 * scale) as u32).clamp(1, image.width());
+    let window_h = ((height as f64 * scale) as u32).clamp(1, image.height());
+
+    // Center it on the focal point, then slide it back inside the image.
+    let x = (focal.0.clamp(0.0, 1.0) * image.width() as f64 - window_w as f64 / 2.0).max(0.0) as u32;
+    let y = (focal.1.clamp(0.0, 1.0) * image.height() as f64 - window_h as f64 / 2.0).max(0.0) as u32;
+    let x = x.min(image.width() - window_w);
+    let y = y.min(image.height() - window_h);
+
+    image
+        .crop_imm(x, y, window_w, window_h)
+        .resize_exact(width, height, FilterType::Lanczos3)
+}
+
+/// Scale an image down to fit `max_w` wide and twice that high, keeping its aspect ratio. The height
+/// cap keeps very tall images from producing huge thumbnails. Smaller images are left alone.
+fn resize(image: &DynamicImage, max_w: Option<u32>) -> Cow<'_, DynamicImage> {
+    match max_w {
+        Some(max_width) if image.width() > max_width || image.height() > max_width * 2 => {
+            Cow::Owned(image.resize(max_width, max_width * 2, FilterType::Lanczos3))
+        }
+        _ => Cow::Borrowed(image),
+    }
+}
@@ -0,0 +1,185 @@
+use crate::db::event::Event;
+use crate::db::rsvp::Rsvp;
+use crate::db::rsvp_session::RsvpSession;
+use crate::prelude::*;
+
+/// Per-recipient values for merge tags like `{{first_name}}` in email content.
+///
+/// A tag can give a fallback for when its value is empty, e.g. `{{first_name|friend}}`. Values are
+/// escaped for html, and anything that isn't a known tag is left as written.
+pub struct MergeTags {
+    values: HashMap<&'static str, String>,
+}
+
+impl MergeTags {
+    /// Tags available in posts.
+    pub const USER_TAGS: &'static [&'static str] = &["first_name", "last_name", "email"];
+    /// Tags available in event emails.
+    pub const EVENT_TAGS: &'static [&'static str] = &[
+        "first_name",
+        "last_name",
+        "email",
+        "event.title",
+        "event.start",
+        "event.end",
+        "event.url",
+        "rsvp_url",
+        "manage_url",
+        "spots",
+        "guest_names",
+    ];
+
+    /// Empty values for when there's no recipient, e.g. a post on the web, so tags show their fallbacks.
+    pub fn empty(tags: &[&'static str]) -> MergeTags {
+        MergeTags { values: tags.iter().map(|tag| (*tag, String::new())).collect() }
+    }
+
+    /// The recipient a preview or test send renders tags for: the user with `email` if one is given,
+    /// otherwise the viewer.
+    pub async fn preview_recipient<E>(db: &Db, viewer: User, email: Option<&str>) -> Result<User, E>
+    where
+        E: From<AnyError> + From<AppError>,
+    {
+        match email.map(str::trim).filter(|email| !email.is_empty()) {
+            Some(email) => match User::lookup_by_email(db, email).await? {
+                Some(user) => Ok(user),
+                None => bail_bad_request!("No user with that email to preview as."),
+            },
+            None => Ok(viewer),
+        }
+    }
+
+    /// Values for a recipient of a post.
+    pub fn for_user(user: &User) -> MergeTags {
+        let mut values = HashMap::new();
+        values.insert("first_name", user.first_name.clone().unwrap_or_default());
+        values.insert("last_name", user.last_name.clone().unwrap_or_default());
+        values.insert("email", user.email.clone());
+        MergeTags { values }
+    }
+
+    /// Values for a recipient of an event email, including their reservation if they have one.
+    pub async fn for_event(db: &Db, event: &Event, user: &User) -> Result<MergeTags> {
+        let mut tags = Self::for_user(user);
+        let app_url = &config().app.url;
+        let format = "%A %B %-d, %-I:%M%p";
+        let values = &mut tags.values;
+        values.insert("event.title", event.title.clone());
+        values.insert("event.start", filters::format_datetime(&event.start, format)?);
+        values.insert("event.end", filters::format_optional_datetime(&event.end, format)?);
+        values.insert("event.url", format!("{app_url}/e/{}", event.slug));
+        values.insert("rsvp_url", format!("{app_url}/e/{}/rsvp", event.slug));
+
+        // Guests added later get child sessions, the parent is the one to manage from.
+        let sessions = RsvpSession::list_for_user(db, user.id, event.id).await?;
+        let manage_url = sessions
+            .iter()
+            .find(|session| session.parent_session_id.is_none())
+            .map(|session| format!("{app_url}/e/{}/rsvp/manage?reservation={}", event.slug, session.token));
+        values.insert("manage_url", manage_url.unwrap_or_default());
+
+        let attendees = Rsvp::list_family_attendees(db, event, user.id).await?;
+        let mut spots: Vec<(String, usize)> = vec![];
+        for attendee in &attendees {
+            match spots.iter_mut().find(|(name, _)| *name == attendee.spot_name) {
+                Some((_, count)) => *count += 1,
+                None => spots.push((attendee.spot_name.clone(), 1)),
+            }
+        }
+        let spots: Vec<_> = spots.iter().map(|(name, count)| format!("{count} × {name}")).collect();
+        values.insert("spots", spots.join(", "));
+
+        let guest_names: Vec<_> = attendees
+            .iter()
+            .filter(|attendee| attendee.user_id != Some(user.id))
+            .filter_map(|attendee| {
+                let name = [&attendee.first_name, &attendee.last_name]
+                    .into_iter()
+                    .flatten()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (!name.is_empty()).then_some(name)
+            })
+            .collect();
+        values.insert("guest_names", guest_names.join(", "));
+
+        Ok(tags)
+    }
+
+    /// Expand the tags in an event's email subjects and bodies.
+    pub fn apply(&self, event: &mut Event) {
+        let htmls = [
+            &mut event.invite_html,
+            &mut event.confirmation_html,
+            &mut event.dayof_html,
+        ];
+        for html in htmls.into_iter().flatten() {
+            *html = self.expand_html(html);
+        }
+        let subjects = [
+            &mut event.invite_subject,
+            &mut event.confirmation_subject,
+            &mut event.dayof_subject,
+        ];
+        for subject in subjects.into_iter().flatten() {
+            *subject = self.expand_text(subject);
+        }
+    }
+
+    /// Expand the tags in html, escaping their values.
+    pub fn expand_html(&self, html: &str) -> String {
+        self.expand(html, true)
+    }
+
+    /// Expand the tags in plain text, e.g. a subject.
+    pub fn expand_text(&self, text: &str) -> String {
+        self.expand(text, false)
+    }
+
+    fn expand(&self, content: &str, escape: bool) -> String {
+        let mut out = String::with_capacity(content.len());
+        let mut rest = content;
+        while let Some((before, tag, fallback, after)) = next_tag(rest) {
+            out.push_str(before);
+            match self.values.get(tag) {
+                Some(value) if value.is_empty() => out.push_str(fallback.unwrap_or_default()),
+                Some(value) if escape => out.push_str(&escape_html(value)),
+                Some(value) => out.push_str(value),
+                None => out.push_str(&rest[before.len()..rest.len() - after.len()]),
+            }
+            rest = after;
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Find the next `{{tag}}` or `{{tag|fallback}}`, returning the text before it, the tag, the
+/// fallback and the text after it.
+fn next_tag(content: &str) -> Option<(&str, &str, Option<&str>, &str)> {
+    let mut offset = 0;
+    loop {
+        let start = offset + content[offset..].find("{{")?;
+        let end = start + content[start..].find("}}")?;
+        let inner = &content[start + 2..end];
+        let (tag, fallback) = match inner.split_once('|') {
+            Some((tag, fallback)) => (tag.trim(), Some(fallback)),
+            None => (inner.trim(), None),
+        };
+        let is_tag = !tag.is_empty() && tag.chars().all(|c| c.is_ascii_lowercase() || c == '_' || c == '.');
+        if is_tag {
+            return Some((&content[..start], tag, fallback, &content[end + 2..]));
+        }
+        offset = start + 2;
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
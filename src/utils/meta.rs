@@ -0,0 +1,176 @@
+use crate::db::event::Event;
+use crate::db::event_flyer::EventFlyer;
+use crate::db::post::Post;
+use crate::db::spot::{Spot, SpotCounts};
+use crate::prelude::*;
+
+/// Link preview tags for a public page, rendered by `meta.html` into the layout's `meta` block:
+/// Open Graph, a Twitter card, and optionally schema.org JSON-LD.
+pub struct PageMeta {
+    pub title: String,
+    pub description: String,
+    /// Absolute canonical url.
+    pub url: String,
+    /// Absolute image url with its size, if the page has one.
+    pub image: Option<(String, u32, u32)>,
+    /// Open Graph type, e.g. `website` or `article`.
+    pub kind: &'static str,
+    /// Serialized JSON-LD, safe to place inside a `<script>`.
+    pub json_ld: Option<String>,
+}
+
+impl PageMeta {
+    /// Longest description excerpt, about what previews show before cutting off.
+    const DESCRIPTION_CHARS: usize = 200;
+
+    pub fn for_event(
+        event: &Event, flyer: Option<&EventFlyer>, spots: &[Spot], counts: &HashMap<i64, SpotCounts>,
+    ) -> PageMeta {
+        let app_url = &config().app.url;
+        let url = format!("{app_url}/e/{}", event.slug);
+        let description = event
+            .description_html
+            .as_deref()
+            .map(|html| excerpt(html, Self::DESCRIPTION_CHARS))
+            .filter(|description| !description.is_empty())
+            .unwrap_or_else(|| {
+                filters::format_datetime(&event.start, "%A %B %-d, %-I:%M%p").unwrap_or_default()
+            });
+        let image = flyer.map(|flyer| {
+            let (width, height) = EventFlyer::OG_SIZE;
+            (
+                format!("{app_url}/e/{}/flyer?v={}&size=og", event.slug, flyer.version),
+                width,
+                height,
+            )
+        });
+
+        let offers: Vec<_> = spots
+            .iter()
+            .map(|spot| {
+                let taken = counts.get(&spot.id).map_or(0, |c| c.rsvp_count + c.cart_count);
+                let available = event.registration_open() && taken < spot.qty_total;
+                let mut offer = json!({
+                    "@type": "Offer",
+                    "name": spot.name,
+                    "url": format!("{url}/rsvp"),
+                    "priceCurrency": "USD",
+                    "availability": if available { "https://schema.org/InStock" } else { "https://schema.org/SoldOut" },
+                });
+                match spot.kind.as_str() {
+                    Spot::FIXED => offer["price"] = json!(spot.required_contribution.unwrap_or(0)),
+                    Spot::VARIABLE => {
+                        offer["price"] = json!(spot.suggested_contribution.unwrap_or(0));
+                        offer["priceSpecification"] = json!({
+                            "@type": "PriceSpecification",
+                            "priceCurrency": "USD",
+                            "minPrice": spot.min_contribution,
+                            "maxPrice": spot.max_contribution,
+                        });
+                    }
+                    _ => offer["price"] = json!(0),
+                }
+                offer
+            })
+            .collect();
+
+        let tz = config().app.tz;
+        let mut json_ld = json!({
+            "@context": "https://schema.org",
+            "@type": "Event",
+            "name": event.title,
+            "description": description,
+            "url": url,
+            "startDate": event.start.and_utc().with_timezone(&tz).to_rfc3339(),
+            "eventStatus": "https://schema.org/EventScheduled",
+            "eventAttendanceMode": "https://schema.org/OfflineEventAttendanceMode",
+        });
+        if let Some(end) = event.end {
+            json_ld["endDate"] = json!(end.and_utc().with_timezone(&tz).to_rfc3339());
+        }
+        if let Some(venue) = &config().app.venue {
+            json_ld["location"] = json!({
+                "@type": "Place",
+                "name": venue.name,
+                "address": venue.address,
+            });
+        }
+        if let Some((image, _, _)) = &image {
+            json_ld["image"] = json!([image]);
+        }
+        if !offers.is_empty() {
+            json_ld["offers"] = json!(offers);
+        }
+
+        PageMeta {
+            title: event.title.clone(),
+            description,
+            url,
+            image,
+            kind: "website",
+            json_ld: Some(script_json(&json_ld)),
+        }
+    }
+
+    pub fn for_post(post: &Post) -> PageMeta {
+        let app_url = &config().app.url;
+        // Posts have no cover, so preview their first absolute image.
+        let image = first_image(&post.content)
+            .filter(|src| src.starts_with("https://") || src.starts_with("http://"))
+            .map(|src| (src, 0, 0));
+        PageMeta {
+            title: post.title.clone(),
+            description: excerpt(&post.content, Self::DESCRIPTION_CHARS),
+            url: format!("{app_url}/p/{}", post.slug),
+            image,
+            kind: "article",
+            json_ld: None,
+        }
+    }
+}
+
+/// Plain text from the start of some html, cut at a word boundary to at most `max_chars`.
+pub fn excerpt(html: &str, max_chars: usize) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                // Tags often separate words, e.g. `<p>one</p><p>two</p>`.
+                text.push(' ');
+            }
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    let cut = cut.rsplit_once(' ').map_or(cut.as_str(), |(head, _)| head);
+    format!("{}…", cut.trim_end_matches(|c: char| c.is_ascii_punctuation()))
+}
+
+/// The `src` of the first `<img>` in some html.
+fn first_image(html: &str) -> Option<String> {
+    let tag = &html[html.find("<img")?..];
+    let tag = &tag[..tag.find('>')?];
+    let src = &tag[tag.find("src=\"")? + 5..];
+    Some(src[..src.find('"')?].replace("&amp;", "&"))
+}
+
+/// Serialize JSON for a `<script>` tag, where `</` would end the script early.
+fn script_json(value: &serde_json::Value) -> String {
+    value.to_string().replace("</", "<\\/")
+}
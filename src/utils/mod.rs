@@ -1,14 +1,19 @@
 pub mod alerts;
+pub mod blobs;
 pub mod cloudflare;
 pub mod config;
+pub mod diff;
 pub mod editor;
 pub mod emailer;
 pub mod error;
 pub mod h3;
 pub mod image;
+pub mod merge_tags;
+pub mod meta;
 pub mod ratelimit;
 pub mod routing;
 pub mod stripe;
 pub mod templates;
 pub mod tracing;
+pub mod tracking;
 pub mod types;
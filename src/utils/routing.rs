@@ -1,3 +1,5 @@
+use crate::db::event::Event;
+use crate::db::event_staff::EventStaff;
 use crate::prelude::*;
 
 pub type AxumRouter = axum::Router<SharedAppState>;
@@ -25,15 +27,15 @@ impl AppRouter {
         self
     }
 
-    /// Add some routes which require authorization and a specific role.
+    /// Add some routes which require authorization and a specific permission.
     pub fn restricted_routes(
-        mut self, role: &'static str, func: impl FnOnce(AxumRouter) -> AxumRouter,
+        mut self, permission: Permission, func: impl FnOnce(AxumRouter) -> AxumRouter,
     ) -> Self {
         let subrouter = func(AxumRouter::new());
         let subrouter = subrouter.route_layer(axum::middleware::from_fn_with_state(
             self.state.clone(),
             move |user: User, req: Request, next: Next| async move {
-                if !user.has_role(role) {
+                if !user.has_permission(permission) {
                     return Err(Redirect::to(&format!("/login?redirect={}", req.uri().path())));
                 }
                 Ok(next.run(req).await)
@@ -42,4 +44,50 @@ impl AppRouter {
         self.router = self.router.merge(subrouter);
         self
     }
+
+    /// Add some routes which require authorization and any permission, or a staff grant for any event.
+    pub fn staff_routes(mut self, func: impl FnOnce(AxumRouter) -> AxumRouter) -> Self {
+        let subrouter = func(AxumRouter::new());
+        let subrouter = subrouter.route_layer(axum::middleware::from_fn_with_state(
+            self.state.clone(),
+            move |user: User, State(state): State<SharedAppState>, req: Request, next: Next| async move {
+                if !user.is_staff() && EventStaff::list_active_for_user(&state.db, user.id).await?.is_empty()
+                {
+                    return Ok(Redirect::to(&format!("/login?redirect={}", req.uri().path())).into_response());
+                }
+                Ok::<_, HtmlError>(next.run(req).await)
+            },
+        ));
+        self.router = self.router.merge(subrouter);
+        self
+    }
+
+    /// Add some per-event routes with an `{id}` path param, which require either a permission
+    /// or an unexpired `EventStaff` grant for that event.
+    pub fn event_routes(
+        mut self, permission: Permission, func: impl FnOnce(AxumRouter) -> AxumRouter,
+    ) -> Self {
+        let subrouter = func(AxumRouter::new());
+        let subrouter = subrouter.route_layer(axum::middleware::from_fn_with_state(
+            self.state.clone(),
+            move |user: User,
+                  State(state): State<SharedAppState>,
+                  Path(params): Path<HashMap<String, String>>,
+                  req: Request,
+                  next: Next| async move {
+                if user.has_permission(permission) {
+                    return Ok(next.run(req).await);
+                }
+
+                let event_id = params.get("id").and_then(|id| id.parse().ok()).ok_or_else(not_found)?;
+                let event = Event::lookup_by_id(&state.db, event_id).await?.ok_or_else(not_found)?;
+                match EventStaff::lookup_active(&state.db, &event, user.id).await? {
+                    Some(staff) if staff.has_permission(permission) => Ok(next.run(req).await),
+                    _ => Err(HtmlError::from(not_found())),
+                }
+            },
+        ));
+        self.router = self.router.merge(subrouter);
+        self
+    }
 }
@@ -11,14 +11,14 @@ pub mod filters {
 
     use super::*;
 
-    /// Check if a user is logged in and has a role.
-    pub fn has_role(user: &Option<User>, role: &str) -> Result<bool, askama::Error> {
-        Ok(user.as_ref().is_some_and(|u| u.has_role(role)))
+    /// Check if a user is logged in and has a permission, e.g. `has_permission(Permission::EventsEdit)`.
+    pub fn has_permission(user: &Option<User>, permission: Permission) -> Result<bool, askama::Error> {
+        Ok(user.as_ref().is_some_and(|u| u.has_permission(permission)))
     }
 
-    /// Check if a user is logged in and has a staff role.
-    pub fn has_staff_role(user: &Option<User>) -> Result<bool, askama::Error> {
-        Ok(user.as_ref().is_some_and(|u| u.has_staff_role()))
+    /// Check if a user is logged in and has any permission at all.
+    pub fn is_staff(user: &Option<User>) -> Result<bool, askama::Error> {
+        Ok(user.as_ref().is_some_and(|u| u.is_staff()))
     }
 
     /// Format a datetime with a `strftime` format string.
@@ -74,6 +74,10 @@ pub mod filters {
         Ok(format!("{}/emails/{token}/unsubscribe", config().app.url))
     }
 
+    pub fn preferences_url(token: &str) -> Result<String, askama::Error> {
+        Ok(format!("{}/emails/{token}/preferences", config().app.url))
+    }
+
     /// Livereload script enabled on debug builds.
     /// Askama doesn't support plain global functions, so we have to take a dummy argument.
     #[cfg(debug_assertions)]
@@ -0,0 +1,69 @@
+use crate::db::email_click::EmailLink;
+use crate::prelude::*;
+
+/// Rewrites the links of bulk emails to go through click redirects, see `/emails/{token}/click/{link_id}`.
+///
+/// When the audience opted out of tracking, links are left alone. The open pixel is left out by the
+/// email templates instead, see their `track_opens`.
+pub struct LinkTracker {
+    enabled: bool,
+    /// Link ids by url, so each url is only stored once per send.
+    links: HashMap<String, i64>,
+}
+
+impl LinkTracker {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, links: HashMap::new() }
+    }
+
+    /// Rewrite a rendered email sent with `token`.
+    pub async fn track(&mut self, db: &Db, html: String, token: &str) -> Result<String> {
+        if !self.enabled {
+            return Ok(html);
+        }
+        let app_url = &config().app.url;
+
+        // Our own email links (preferences, unsubscribe) already identify the email.
+        let own_prefix = format!("{app_url}/emails/");
+        let mut tracked = String::with_capacity(html.len());
+        let mut rest = html.as_str();
+        while let Some(start) = rest.find(r#"href=""#) {
+            let (before, after) = rest.split_at(start + r#"href=""#.len());
+            tracked.push_str(before);
+            let Some(end) = after.find('"') else {
+                rest = after;
+                break;
+            };
+
+            let href = &after[..end];
+            let url = unescape(href);
+            if (url.starts_with("https://") || url.starts_with("http://")) && !url.starts_with(&own_prefix) {
+                let link_id = match self.links.get(&url) {
+                    Some(link_id) => *link_id,
+                    None => {
+                        let link_id = EmailLink::lookup_or_create(db, &url).await?;
+                        self.links.insert(url, link_id);
+                        link_id
+                    }
+                };
+                tracked.push_str(&format!("{app_url}/emails/{token}/click/{link_id}"));
+            } else {
+                tracked.push_str(href);
+            }
+            rest = &after[end..];
+        }
+        tracked.push_str(rest);
+        Ok(tracked)
+    }
+}
+
+/// Undo the HTML escaping of an attribute value.
+fn unescape(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&#34;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}